@@ -0,0 +1,67 @@
+// Compares fitting tracks one at a time against fitting them in bundles of 8.
+// Run with `cargo +nightly bench --features unstable --bench bundle`.
+
+#![feature(test)]
+extern crate test;
+
+use kalman_rs::filter::bundle::TrackBundle8;
+use kalman_rs::filter::{prediction, filter_gain, utils};
+use kalman_rs::config::*;
+
+use test::{Bencher, black_box};
+
+const TRACKS : usize = 8 * 64;
+const SENSORS : usize = 10;
+
+fn jacobian() -> Mat5 {
+    let mut jacobian = Mat5::identity();
+    jacobian[(0, 2)] = 0.5;
+    jacobian[(1, 3)] = 0.5;
+    jacobian
+}
+
+fn seeds() -> (Vec<Vec5>, Vec<Mat5>) {
+    (vec![Vec5::new(0., 0., 0.1, 0.3, 0.5); TRACKS], vec![Mat5::identity() * 0.1; TRACKS])
+}
+
+#[bench]
+fn scalar(b: &mut Bencher) {
+    let jacobian = jacobian();
+    let meas_map_mat = utils::sensor_mapping_mat();
+    let measurement = Vec2::new(0.1, 0.2);
+    let noise = Mat2::identity() * 0.01;
+    let (state_vecs, cov_mats) = seeds();
+
+    b.iter(|| {
+        for (x, c) in state_vecs.iter().zip(cov_mats.iter()) {
+            let mut x = *x;
+            let mut c = *c;
+            for _ in 0..SENSORS {
+                let pred_x = prediction::state_vector(&jacobian, &x);
+                let pred_c = prediction::covariance_matrix(&jacobian, &c);
+                let gain = filter_gain::kalman_gain(&pred_c, &meas_map_mat, &noise);
+                x = filter_gain::state_vector(&pred_x, &gain, &measurement, &meas_map_mat);
+                c = filter_gain::covariance_matrix(&gain, &meas_map_mat, &pred_c);
+            }
+            black_box((x, c));
+        }
+    });
+}
+
+#[bench]
+fn bundle8(b: &mut Bencher) {
+    let jacobian = jacobian();
+    let (packed_m, packed_v) = TrackBundle8::pack_measurements(&[Vec2::new(0.1, 0.2); 8], &[Mat2::identity() * 0.01; 8]);
+    let (state_vecs, cov_mats) = seeds();
+
+    b.iter(|| {
+        for (xs, cs) in state_vecs.chunks(8).zip(cov_mats.chunks(8)) {
+            let mut bundle = TrackBundle8::from_tracks(xs, cs);
+            for _ in 0..SENSORS {
+                bundle.predict(&jacobian);
+                bundle.update(&packed_m, &packed_v);
+            }
+            black_box(bundle);
+        }
+    });
+}
@@ -1,11 +1,24 @@
 
 // reusable types to be referenced from functions;
 use nalgebra as na;
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+
+use super::error::*;
+
+/// Float type of the filters. The geometry, the core math (`prediction`, `filter_gain`,
+/// `smoothing`, `filter_means`, `FitWorkspace` and `linear::run_in`) and the `KalmanFilter`,
+/// `unscented`, `combinatorial`, `ambiguity`, `annealing` and `gaussian_sum` filters are generic
+/// over `nalgebra::Real` and default to this type; the remaining filters only run in `Real`.
 pub type Real = f64;
 
 pub type Vec2 = na::Vector2<Real>;
 pub type Vec3 = na::Vector3<Real>;
 pub type Vec5 = na::Vector5<Real>;
+pub type Vec6 = na::Vector6<Real>;
+pub type Vec7 = na::VectorN<Real, na::U7>;
 
 pub type P2 = na::Point2<Real>;
 pub type P3 = na::Point3<Real>;
@@ -14,9 +27,18 @@ pub type Mat2 = na::Matrix2<Real>;
 pub type Mat3 = na::Matrix3<Real>;
 pub type Mat4 = na::Matrix4<Real>;
 pub type Mat5 = na::Matrix5<Real>;
+pub type Mat6 = na::Matrix6<Real>;
+pub type Mat7 = na::MatrixN<Real, na::U7>;
 
 pub type Mat2x5 =na::Matrix2x5<Real>;
 pub type Mat5x2 =na::Matrix5x2<Real>;
+pub type Mat3x5 = na::Matrix3x5<Real>;
+pub type Mat5x3 = na::Matrix5x3<Real>;
+pub type Mat5x7 = na::MatrixMN<Real, na::U5, na::U7>;
+pub type Mat7x5 = na::MatrixMN<Real, na::U7, na::U5>;
+
+pub type DVec = na::DVector<Real>;
+pub type DMat = na::DMatrix<Real>;
 
 pub type Trf3 = na::Transform3<Real>;
 pub type Trl3 = na::Translation3<Real>;
@@ -24,4 +46,255 @@ pub type Rot3 = na::Rotation3<Real>;
 pub type Aff3 = na::Affine3<Real>;
 
 
-pub const DOT_PRODUCT_EPSILON : Real = 0.0005;
\ No newline at end of file
+pub const DOT_PRODUCT_EPSILON : Real = 0.0005;
+
+// lengths are in mm and times in ns
+pub const SPEED_OF_LIGHT : Real = 299.792458;
+
+// transverse momentum (GeV) per unit charge, field (T) and radius of curvature (mm)
+pub const B_FIELD_CONSTANT : Real = 0.299792458e-3;
+
+
+/// How the state is carried from one sensor to the next
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Propagator {
+    /// straight line extrapolation with a linearized covariance
+    Linear,
+    /// straight line extrapolation of sigma points
+    Unscented
+}
+
+/// Particle assumed when calculating material effects
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticleHypothesis {
+    Electron,
+    Muon,
+    Pion,
+    Kaon,
+    Proton
+}
+
+impl ParticleHypothesis {
+    /// Rest mass in GeV
+    pub fn mass(&self) -> Real {
+        match *self {
+            ParticleHypothesis::Electron => 0.000_510_999,
+            ParticleHypothesis::Muon => 0.105_658,
+            ParticleHypothesis::Pion => 0.139_570,
+            ParticleHypothesis::Kaon => 0.493_677,
+            ParticleHypothesis::Proton => 0.938_272
+        }
+    }
+}
+
+/// Form of the filtered covariance matrix
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CovarianceUpdate {
+    /// C = (1 - KH) C
+    Standard,
+    /// C = (1 - KH) C (1 - KH)^T + K V K^T, which stays positive definite with rounding errors
+    Joseph
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Smoother {
+    RauchTungStriebel,
+    /// return the filtered states without smoothing
+    Disabled
+}
+
+/// How far outside the bounds of a sensor a point may lie and still count as inside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance<N: na::Real = Real> {
+    /// the strict bounds of `Transform::inside`
+    None,
+    /// a distance in the local frame
+    Absolute(N),
+    /// a number of standard deviations of the local position towards the boundary
+    Sigmas(N)
+}
+
+impl Tolerance {
+    /// The same tolerance in the float type of a filter, since the options are always read as `Real`
+    pub fn convert<N: na::Real>(&self) -> Tolerance<N> {
+        match *self {
+            Tolerance::None => Tolerance::None,
+            Tolerance::Absolute(distance) => Tolerance::Absolute(na::convert(distance)),
+            Tolerance::Sigmas(sigmas) => Tolerance::Sigmas(na::convert(sigmas))
+        }
+    }
+}
+
+/// Settings used by the fitter. Use `FitterOptions::builder()` to change individual settings from
+/// their defaults or `FitterOptions::from_toml_file` to read them from a configuration file.
+#[derive(Debug, Clone)]
+pub struct FitterOptions {
+    pub propagator: Propagator,
+    pub material_effects: bool,
+    pub radiation_length_fraction: Real,    // x / X_0 of every sensor
+    pub particle_hypothesis: ParticleHypothesis,
+    pub outlier_chi_squared: Real,          // measurements above this chi squared are ignored
+    pub covariance_update: CovarianceUpdate,
+    pub smoother: Smoother,
+    pub max_iterations: usize,              // passes made by `filter::iterated::run`
+    pub seed_covariance: Mat5,
+    pub boundary_tolerance: Tolerance       // allowance for predictions just outside a sensor
+}
+
+impl Default for FitterOptions {
+    fn default() -> Self {
+        FitterOptions{propagator: Propagator::Linear,
+                      material_effects: false,
+                      radiation_length_fraction: 0.,
+                      particle_hypothesis: ParticleHypothesis::Pion,
+                      outlier_chi_squared: Real::INFINITY,
+                      covariance_update: CovarianceUpdate::Standard,
+                      smoother: Smoother::RauchTungStriebel,
+                      max_iterations: 1,
+                      seed_covariance: Mat5::identity(),
+                      boundary_tolerance: Tolerance::None}
+    }
+}
+
+impl FitterOptions {
+    pub fn builder() -> FitterOptionsBuilder {
+        FitterOptionsBuilder{options: FitterOptions::default()}
+    }
+
+    /// Reads options from a TOML file. Keys that are left out keep their default value.
+    /// `seed_covariance` is either the 5 diagonal elements or all 25 elements in row major order.
+    ///
+    /// ```toml
+    /// propagator = "unscented"
+    /// material_effects = true
+    /// radiation_length_fraction = 0.02
+    /// particle_hypothesis = "electron"
+    /// outlier_chi_squared = 25.0
+    /// covariance_update = "joseph"
+    /// smoother = "rauch_tung_striebel"
+    /// max_iterations = 5
+    /// seed_covariance = [1.0, 1.0, 0.01, 0.01, 0.1]
+    /// boundary_tolerance = 0.001          # or boundary_tolerance_sigmas = 3.0
+    /// ```
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)?;
+        Self::from_toml_str(&contents)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, ConfigError> {
+        let file : FitterOptionsFile = toml::from_str(contents)?;
+
+        let mut builder = FitterOptions::builder();
+
+        if let Some(propagator) = file.propagator {builder = builder.propagator(propagator)}
+        if let Some(material_effects) = file.material_effects {builder = builder.material_effects(material_effects)}
+        if let Some(fraction) = file.radiation_length_fraction {builder = builder.radiation_length_fraction(fraction)}
+        if let Some(particle) = file.particle_hypothesis {builder = builder.particle_hypothesis(particle)}
+        if let Some(cut) = file.outlier_chi_squared {builder = builder.outlier_chi_squared(cut)}
+        if let Some(update) = file.covariance_update {builder = builder.covariance_update(update)}
+        if let Some(smoother) = file.smoother {builder = builder.smoother(smoother)}
+        if let Some(iterations) = file.max_iterations {builder = builder.max_iterations(iterations)}
+
+        if let Some(elements) = file.seed_covariance {
+            let seed_covariance =
+                match elements.len() {
+                    5 => Mat5::from_diagonal(&Vec5::from_row_slice(&elements)),
+                    25 => Mat5::from_row_slice(&elements),
+                    _ => return Err(ConfigError::InvalidValue("seed_covariance needs 5 or 25 elements".to_string()))
+                };
+            builder = builder.seed_covariance(seed_covariance);
+        }
+
+        match (file.boundary_tolerance, file.boundary_tolerance_sigmas) {
+            (Some(_), Some(_)) => return Err(ConfigError::InvalidValue("only one of boundary_tolerance and boundary_tolerance_sigmas can be set".to_string())),
+            (Some(distance), None) => builder = builder.boundary_tolerance(Tolerance::Absolute(distance)),
+            (None, Some(sigmas)) => builder = builder.boundary_tolerance(Tolerance::Sigmas(sigmas)),
+            (None, None) => {}
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Builder for `FitterOptions`. Every setting starts from `FitterOptions::default()`
+#[derive(Debug, Clone)]
+pub struct FitterOptionsBuilder {
+    options: FitterOptions
+}
+
+impl FitterOptionsBuilder {
+    pub fn propagator(mut self, propagator: Propagator) -> Self {
+        self.options.propagator = propagator;
+        self
+    }
+
+    pub fn material_effects(mut self, material_effects: bool) -> Self {
+        self.options.material_effects = material_effects;
+        self
+    }
+
+    pub fn radiation_length_fraction(mut self, radiation_length_fraction: Real) -> Self {
+        self.options.radiation_length_fraction = radiation_length_fraction;
+        self
+    }
+
+    pub fn particle_hypothesis(mut self, particle_hypothesis: ParticleHypothesis) -> Self {
+        self.options.particle_hypothesis = particle_hypothesis;
+        self
+    }
+
+    pub fn outlier_chi_squared(mut self, outlier_chi_squared: Real) -> Self {
+        self.options.outlier_chi_squared = outlier_chi_squared;
+        self
+    }
+
+    pub fn covariance_update(mut self, covariance_update: CovarianceUpdate) -> Self {
+        self.options.covariance_update = covariance_update;
+        self
+    }
+
+    pub fn smoother(mut self, smoother: Smoother) -> Self {
+        self.options.smoother = smoother;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.options.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn seed_covariance(mut self, seed_covariance: Mat5) -> Self {
+        self.options.seed_covariance = seed_covariance;
+        self
+    }
+
+    pub fn boundary_tolerance(mut self, boundary_tolerance: Tolerance) -> Self {
+        self.options.boundary_tolerance = boundary_tolerance;
+        self
+    }
+
+    pub fn build(self) -> FitterOptions {
+        self.options
+    }
+}
+
+// layout of the configuration file. Every key is optional
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FitterOptionsFile {
+    propagator: Option<Propagator>,
+    material_effects: Option<bool>,
+    radiation_length_fraction: Option<Real>,
+    particle_hypothesis: Option<ParticleHypothesis>,
+    outlier_chi_squared: Option<Real>,
+    covariance_update: Option<CovarianceUpdate>,
+    smoother: Option<Smoother>,
+    max_iterations: Option<usize>,
+    seed_covariance: Option<Vec<Real>>,
+    boundary_tolerance: Option<Real>,
+    boundary_tolerance_sigmas: Option<Real>
+}
@@ -40,20 +40,44 @@ macro_rules! impl_from {
     };
 }
 
+use std::io::Error as IoError;
+use toml::de::Error as TomlError;
+
 #[derive(Debug)]
 pub enum Error{
     Matrix(MatrixError),
-    Sensor(SensorError)
+    Sensor(SensorError),
+    Config(ConfigError),
+    Input(InputError)
 }
 
 #[derive(Debug)]
 pub enum MatrixError {
     NonInvertible,
+    NotPositiveDefinite,
+    DimensionMismatch,
 }
 
 #[derive(Debug)]
 pub enum SensorError {
-    OutsideSensorBounds
+    OutsideSensorBounds,
+    InvalidBounds,
+    NoIntersection,
+    NotConvex
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    LengthMismatch,
+    UnknownSensor(usize),
+    NotPredicted
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(IoError),
+    Parse(TomlError),
+    InvalidValue(String)
 }
 
 // this function is only here to ensure that all `std::From` trait implementations 
@@ -66,4 +90,12 @@ fn init_from() {
     
     //SensorError
     impl_from!(SensorError, Error, Error::Sensor);
+
+    // InputError
+    impl_from!(InputError, Error, Error::Input);
+
+    // ConfigError
+    impl_from!(ConfigError, Error, Error::Config);
+    impl_from!(IoError, ConfigError, ConfigError::Io);
+    impl_from!(TomlError, ConfigError, ConfigError::Parse);
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+use nalgebra as na;
+use na::{Matrix2, Vector2};
+use super::combinatorial::{self, TrackCandidate};
+
+use super::super::geometry::traits::{Plane, Transform};
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// a hit is identified by the sensor it is on and its index in that sensor's measurements
+type HitId = (usize, usize);
+
+/// Settings for the ambiguity solver
+#[derive(Debug, Clone)]
+pub struct AmbiguityOptions {
+    pub max_shared_hits: usize, // shared hits a track may keep before it is removed outright
+    pub min_hits: usize         // tracks with fewer hits than this are dropped
+}
+
+impl AmbiguityOptions {
+    pub fn new(max_shared_hits: usize, min_hits: usize) -> Self {
+        AmbiguityOptions{max_shared_hits: max_shared_hits, min_hits: min_hits}
+    }
+}
+
+/// Resolves ambiguities between track candidates that share hits. The worst track with more than
+/// `max_shared_hits` shared hits is removed until no such track is left. Any hits that are still
+/// shared afterwards are kept by the better track and removed from the others, which are then refit
+/// without them and count a hole for each removed hit. A track left with fewer than `min_hits` hits is
+/// dropped and does not claim any. The result contains no shared hits and is sorted from best to worst.
+///
+/// The measurements, noise and sensors are the ones the candidates were found with in `combinatorial::run`.
+pub fn resolve<N: na::Real, T: Transform<N> + Plane<N>>(
+    tracks: Vec<TrackCandidate<N>>,
+    measurement_noise_covariance_vector: &Vec<Vec<Matrix2<N>>>,
+    measurements_vector: &Vec<Vec<Vector2<N>>>,
+    sensor_vector: &Vec<T>,
+    options: &AmbiguityOptions
+    ) -> Vec<TrackCandidate<N>> {
+
+    let mut tracks : Vec<TrackCandidate<N>> = tracks.into_iter()
+                                        .filter(|track| hit_count(track) >= options.min_hits)
+                                        .collect();
+    tracks.sort_by(compare);
+
+    // iteratively drop the worst track that shares too many hits. Since `tracks` is sorted
+    // best to worst, the last offending track is the worst one
+    loop {
+        let usage = hit_usage(&tracks);
+
+        let worst = tracks.iter()
+                        .rposition(|track| shared_hits(track, &usage) > options.max_shared_hits);
+
+        match worst {
+            Some(index) => {tracks.remove(index);},
+            None => break
+        }
+    }
+
+    // hand every remaining shared hit to the best track using it
+    let mut claimed : HashSet<HitId> = HashSet::new();
+    let mut resolved = Vec::with_capacity(tracks.len());
+
+    for mut track in tracks.into_iter() {
+        let mut kept_hits = Vec::with_capacity(track.hits.len());
+        let mut removed_hits = 0;
+
+        for sensor in 0..track.hits.len() {
+            let measurement =
+                match track.hits[sensor] {
+                    Some(measurement) => measurement,
+                    None => continue
+                };
+
+            if claimed.contains(&(sensor, measurement)) {
+                track.hits[sensor] = None;
+                removed_hits += 1;
+            }
+            else {
+                kept_hits.push((sensor, measurement));
+            }
+        }
+
+        // a dropped track leaves its hits to the tracks after it
+        if hit_count(&track) < options.min_hits {
+            continue
+        }
+        claimed.extend(kept_hits);
+
+        if removed_hits > 0 {
+            // a sensor that lost its hit is now crossed without one
+            track.holes += removed_hits;
+            track = combinatorial::refit(&track, measurement_noise_covariance_vector, measurements_vector, sensor_vector);
+        }
+        resolved.push(track);
+    }
+
+    resolved.sort_by(compare);
+    resolved
+}
+
+/// Number of hits assigned to a track
+pub fn hit_count<N: na::Real>(track: &TrackCandidate<N>) -> usize {
+    track.hits.iter().filter(|hit| hit.is_some()).count()
+}
+
+// more hits is better, ties are broken by chi squared per degree of freedom
+fn compare<N: na::Real>(a: &TrackCandidate<N>, b: &TrackCandidate<N>) -> Ordering {
+    match hit_count(b).cmp(&hit_count(a)) {
+        Ordering::Equal => a.quality().partial_cmp(&b.quality()).unwrap_or(Ordering::Equal),
+        ordering => ordering
+    }
+}
+
+// how many tracks use each hit
+fn hit_usage<N: na::Real>(tracks: &Vec<TrackCandidate<N>>) -> HashMap<HitId, usize> {
+    let mut usage = HashMap::new();
+
+    for track in tracks.iter() {
+        for (sensor, hit) in track.hits.iter().enumerate() {
+            if let Some(measurement) = hit {
+                *usage.entry((sensor, *measurement)).or_insert(0) += 1;
+            }
+        }
+    }
+    usage
+}
+
+fn shared_hits<N: na::Real>(track: &TrackCandidate<N>, usage: &HashMap<HitId, usize>) -> usize {
+    track.hits.iter()
+        .enumerate()
+        .filter_map(|(sensor, hit)| hit.map(|measurement| (sensor, measurement)))
+        .filter(|id| usage.get(id).map_or(false, |count| *count > 1))
+        .count()
+}
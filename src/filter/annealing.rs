@@ -0,0 +1,235 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix2x5, Matrix5, Vector2, Vector5};
+use super::super::config::*;
+use super::super::error::*;
+use super::prediction;
+use super::filter_gain;
+use super::smoothing;
+use super::linear::linear_jacobian;
+use super::utils::sensor_mapping_mat;
+
+use super::super::geometry::traits::{Plane, Transform};
+
+use std::cmp::Ordering;
+
+// smoothed state vectors and covariance matrices of one pass
+type SmoothedPass<N> = (Vec<Vector5<N>>, Vec<Matrix5<N>>);
+
+/// Output of the deterministic annealing filter. `weights[i][j]` is the final assignment
+/// probability of measurement `j` on sensor `i`.
+#[derive(Debug, Clone)]
+pub struct AnnealedData<N: na::Real = Real> {
+    pub smth_state_vec: Vec<Vector5<N>>,
+    pub smth_cov_mat: Vec<Matrix5<N>>,
+    pub weights: Vec<Vec<N>>
+}
+
+/// Deterministic annealing filter. Every measurement on a sensor takes part in the fit with a weight
+/// equal to its assignment probability. The weights are recalculated from the smoothed states after
+/// every pass of the filter, using the next temperature of `temperatures` (which should decrease
+/// towards 1). A measurement further than `chi_squared_cut` from the smoothed state is pushed
+/// towards a weight of zero.
+///
+/// The seed is the state on the first sensor; the track is extrapolated along straight lines between
+/// the following sensors. Returns `ConfigError::InvalidValue` if the schedule is empty or a temperature
+/// is not positive.
+pub fn run<N: na::Real, T: Transform<N> + Plane<N>>(
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &Vec<Vec<Matrix2<N>>>,    // V of every hit on each sensor
+    measurements_vector: &Vec<Vec<Vector2<N>>>,                     // competing hits on each sensor
+    sensor_vector: &Vec<T>,                                         // the sensor of each set of hits
+    temperatures: &[N],                                             // annealing schedule
+    chi_squared_cut: N
+    ) -> Result<AnnealedData<N>, Error> {
+
+    if (measurement_noise_covariance_vector.len() != measurements_vector.len()) || (measurements_vector.len() != sensor_vector.len()) {
+        panic!("vector lengths need to be the same length")
+    }
+
+    // without a pass there are no smoothed states, and a temperature of zero divides by zero in the weights
+    if temperatures.is_empty() {
+        return Err(ConfigError::InvalidValue("the annealing schedule needs at least one temperature".to_string()).into())
+    }
+    if temperatures.iter().any(|temperature| temperature.partial_cmp(&na::zero()) != Some(Ordering::Greater)) {
+        return Err(ConfigError::InvalidValue("temperatures need to be positive".to_string()).into())
+    }
+
+    let input_length = measurements_vector.len();
+
+    let meas_map_mat = sensor_mapping_mat();
+
+    // inverse of every V, calculated once since they do not change between passes
+    let mut inv_noise_vector : Vec<Vec<Matrix2<N>>> = Vec::with_capacity(input_length);
+    for curr_noise in measurement_noise_covariance_vector.iter() {
+        let mut inverses = Vec::with_capacity(curr_noise.len());
+        for curr_v in curr_noise.iter() {
+            inverses.push(invert(curr_v)?);
+        }
+        inv_noise_vector.push(inverses);
+    }
+
+    // start with every competing measurement equally likely
+    let mut weights : Vec<Vec<N>> = measurements_vector.iter()
+                                .map(|hits| vec![N::one() / na::convert(hits.len().max(1) as f64); hits.len()])
+                                .collect();
+
+    let mut smoothed = (Vec::new(), Vec::new());
+
+    for temperature in temperatures.iter() {
+        smoothed = filter_pass(seed_state_vec, seed_cov_mat, measurements_vector, &inv_noise_vector,
+                               &weights, sensor_vector, &meas_map_mat)?;
+
+        for i in 0..input_length {
+            weights[i] = assignment_probabilities(&smoothed.0[i], &measurements_vector[i],
+                                                  &inv_noise_vector[i], &meas_map_mat,
+                                                  *temperature, chi_squared_cut);
+        }
+    }
+
+    Ok(AnnealedData{smth_state_vec: smoothed.0,
+                    smth_cov_mat: smoothed.1,
+                    weights: weights})
+}
+
+// forward filter with weighted measurements followed by a smoothing pass
+fn filter_pass<N: na::Real, T: Transform<N> + Plane<N>>(
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurements_vector: &Vec<Vec<Vector2<N>>>,
+    inv_noise_vector: &Vec<Vec<Matrix2<N>>>,
+    weights: &Vec<Vec<N>>,
+    sensor_vector: &Vec<T>,
+    meas_map_mat: &Matrix2x5<N>
+    ) -> Result<SmoothedPass<N>, MatrixError> {
+
+    let input_length = measurements_vector.len();
+
+    if input_length == 0 {
+        return Ok((Vec::new(), Vec::new()))
+    }
+
+    store_vec!{input_length;
+        jacobian_iter: Matrix5<N>,
+        pred_state_vec_iter: Vector5<N>,
+        pred_cov_mat_iter: Matrix5<N>,
+        filter_state_vec_iter: Vector5<N>,
+        filter_cov_mat_iter: Matrix5<N>
+    }
+
+    let mut previous_state_vec = seed_state_vec.clone();
+    let mut previous_covariance = seed_cov_mat.clone();
+
+    for i in 0..input_length {
+        // the seed already sits on the first sensor
+        let (jacobian, pred_state_vec) =
+            if i == 0 {
+                (linear_jacobian(), previous_state_vec)
+            }
+            else {
+                (prediction::linear_extrapolation_jacobian(&sensor_vector[i - 1], &sensor_vector[i], &previous_state_vec),
+                 prediction::linear_extrapolation(&sensor_vector[i - 1], &sensor_vector[i], &previous_state_vec))
+            };
+        let pred_cov_mat = prediction::covariance_matrix(&jacobian, &previous_covariance);
+
+        // combine every hit on the sensor into one effective measurement
+        let mut weighted_g = Matrix2::zeros();
+        let mut weighted_m = Vector2::zeros();
+        for ((m_k, g), w) in measurements_vector[i].iter().zip(inv_noise_vector[i].iter()).zip(weights[i].iter()) {
+            weighted_g += g * *w;
+            weighted_m += g * m_k * *w;
+        }
+
+        let (filter_state_vec, filter_cov_mat) =
+            match weighted_g.try_inverse() {
+                Some(effective_v) => {
+                    let effective_m = effective_v * weighted_m;
+
+                    let kalman_gain = filter_gain::kalman_gain(&pred_cov_mat, meas_map_mat, &effective_v);
+                    let filter_state_vec = filter_gain::state_vector(&pred_state_vec, &kalman_gain, &effective_m, meas_map_mat);
+                    let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, meas_map_mat, &pred_cov_mat);
+                    (filter_state_vec, filter_cov_mat)
+                },
+                // every hit on this sensor has been annealed away
+                None => (pred_state_vec.clone(), pred_cov_mat.clone())
+            };
+
+        push!{
+            jacobian => jacobian_iter,
+            pred_state_vec => pred_state_vec_iter,
+            pred_cov_mat => pred_cov_mat_iter,
+            filter_state_vec => filter_state_vec_iter,
+            filter_cov_mat => filter_cov_mat_iter
+        }
+
+        previous_state_vec = filter_state_vec;
+        previous_covariance = filter_cov_mat;
+    }
+
+    // smoothed values are stored back to front and reversed at the end
+    store_vec!{input_length;
+        smoothed_state_vec_iter: Vector5<N>,
+        smoothed_cov_mat_iter: Matrix5<N>
+    }
+
+    push!{
+        filter_state_vec_iter[input_length - 1] => smoothed_state_vec_iter,
+        filter_cov_mat_iter[input_length - 1] => smoothed_cov_mat_iter
+    }
+
+    for i in (0..input_length - 1).rev() {
+        let next_smth_state_vec = smoothed_state_vec_iter.last().unwrap();
+        let next_smth_cov_mat = smoothed_cov_mat_iter.last().unwrap();
+
+        if pred_cov_mat_iter[i + 1].try_inverse().is_none() {
+            return Err(MatrixError::NonInvertible)
+        }
+
+        let gain_matrix = smoothing::gain_matrix(&filter_cov_mat_iter[i], &jacobian_iter[i + 1], &pred_cov_mat_iter[i + 1]);
+        let smoothed_state_vec = smoothing::state_vector(&filter_state_vec_iter[i], &gain_matrix, next_smth_state_vec, &pred_state_vec_iter[i + 1]);
+        let smoothed_cov_mat = smoothing::covariance_matrix(&filter_cov_mat_iter[i], &gain_matrix, &pred_cov_mat_iter[i + 1], next_smth_cov_mat);
+
+        push!{
+            smoothed_state_vec => smoothed_state_vec_iter,
+            smoothed_cov_mat => smoothed_cov_mat_iter
+        }
+    }
+
+    smoothed_state_vec_iter.reverse();
+    smoothed_cov_mat_iter.reverse();
+
+    Ok((smoothed_state_vec_iter, smoothed_cov_mat_iter))
+}
+
+// weight of each measurement on a sensor at the current temperature
+fn assignment_probabilities<N: na::Real>(
+    smth_state_vec: &Vector5<N>,
+    measurements: &Vec<Vector2<N>>,
+    inv_noise: &Vec<Matrix2<N>>,
+    meas_map_mat: &Matrix2x5<N>,
+    temperature: N,
+    chi_squared_cut: N
+    ) -> Vec<N> {
+
+    let two : N = na::convert(2.0);
+    let likelihoods : Vec<N> = measurements.iter()
+        .zip(inv_noise.iter())
+        .map(|(m_k, g)| {
+            let residual_vec = m_k - (meas_map_mat * smth_state_vec);
+            let chi_squared = (residual_vec.transpose() * g * residual_vec)[0];
+            (-chi_squared / (two * temperature)).exp()
+        })
+        .collect();
+
+    let cutoff = (-chi_squared_cut / (two * temperature)).exp();
+    let total = likelihoods.iter().fold(N::zero(), |sum, phi| sum + *phi) + cutoff;
+
+    likelihoods.into_iter().map(|phi| phi / total).collect()
+}
+
+fn invert<N: na::Real>(matrix: &Matrix2<N>) -> Result<Matrix2<N>, MatrixError> {
+    match matrix.try_inverse() {
+        Some(inverse) => Ok(inverse),
+        None => Err(MatrixError::NonInvertible)
+    }
+}
@@ -0,0 +1,64 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::kalman::KalmanFilter;
+use super::utils::SmoothedData;
+
+use super::super::geometry::traits::{Plane, Transform};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Measurements of one track. `sensor_indices[i]` is the index into the shared geometry of the
+/// sensor that registered `measurements[i]`, in the order the sensors are crossed.
+#[derive(Debug, Clone)]
+pub struct TrackInput {
+    pub seed_state_vec: Vec5,
+    pub measurements: Vec<Vec2>,            // m_k
+    pub measurement_noise: Vec<Mat2>,       // V
+    pub sensor_indices: Vec<usize>
+}
+
+/// Fits every track against the same detector geometry. With the `rayon` feature enabled the
+/// tracks are spread across threads. Results are returned in the same order as `tracks`, and a
+/// track that cannot be fitted returns its own error without affecting the rest of the batch.
+pub fn fit_batch<T: Transform + Plane + Sync>(
+    geometry: &[T],
+    tracks: &[TrackInput],
+    options: &FitterOptions
+    ) -> Vec<Result<SmoothedData, Error>> {
+
+    #[cfg(feature = "rayon")]
+    let iter = tracks.par_iter();
+
+    #[cfg(not(feature = "rayon"))]
+    let iter = tracks.iter();
+
+    iter.map(|track| fit_track(geometry, track, options))
+        .collect()
+}
+
+/// Fits a single track of a batch
+pub fn fit_track<T: Transform + Plane>(
+    geometry: &[T],
+    track: &TrackInput,
+    options: &FitterOptions
+    ) -> Result<SmoothedData, Error> {
+
+    if (track.measurements.len() != track.measurement_noise.len()) || (track.measurements.len() != track.sensor_indices.len()) {
+        return Err(InputError::LengthMismatch.into())
+    }
+
+    let mut kf = KalmanFilter::with_options(track.seed_state_vec.clone(), options.clone());
+
+    for ((measurement, curr_v), sensor_index) in track.measurements.iter().zip(track.measurement_noise.iter()).zip(track.sensor_indices.iter()) {
+        let sensor = match geometry.get(*sensor_index) {
+            Some(sensor) => sensor,
+            None => return Err(InputError::UnknownSensor(*sensor_index).into())
+        };
+
+        kf.predict(sensor)?;
+        kf.update(measurement, curr_v)?;
+    }
+
+    Ok(kf.smooth()?)
+}
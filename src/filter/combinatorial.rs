@@ -0,0 +1,263 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Point2, Vector2, Vector5};
+use super::super::config::*;
+use super::prediction;
+use super::filter_gain;
+use super::utils::{is_finite, sensor_mapping_mat};
+
+use super::super::geometry::traits::{Plane, Transform};
+
+/// Settings that control how aggressively the combinatorial filter branches
+#[derive(Debug, Clone)]
+pub struct CombinatorialOptions<N: na::Real = Real> {
+    pub chi_squared_cut: N,     // maximum predicted chi squared for a hit to be compatible
+    pub max_branches: usize,    // number of candidates kept after each sensor
+    pub max_holes: usize,       // sensors crossed without a compatible hit before a branch is dropped
+    pub boundary_tolerance: Tolerance<N>    // allowance for predictions just outside a sensor
+}
+
+impl<N: na::Real> CombinatorialOptions<N> {
+    pub fn new(chi_squared_cut: N, max_branches: usize, max_holes: usize) -> Self {
+        CombinatorialOptions{chi_squared_cut: chi_squared_cut,
+                             max_branches: max_branches,
+                             max_holes: max_holes,
+                             boundary_tolerance: Tolerance::None}
+    }
+
+    /// Sets the boundary tolerance, usually to `FitterOptions::boundary_tolerance`
+    pub fn boundary_tolerance(mut self, boundary_tolerance: Tolerance<N>) -> Self {
+        self.boundary_tolerance = boundary_tolerance;
+        self
+    }
+}
+
+/// A track found by the combinatorial filter. `hits[i]` is the index of the measurement used
+/// on sensor `i`, or `None` if no measurement was assigned there.
+#[derive(Debug, Clone)]
+pub struct TrackCandidate<N: na::Real = Real> {
+    pub seed_state_vec: Vector5<N>,     // state on the first sensor the candidate was grown from
+    pub seed_cov_mat: Matrix5<N>,
+    pub hits: Vec<Option<usize>>,
+    pub filt_state_vec: Vec<Vector5<N>>,
+    pub filt_cov_mat: Vec<Matrix5<N>>,
+    pub chi_squared_inc: Vec<N>,
+    pub chi_squared: N,
+    pub ndf: usize,                     // number of measured coordinates (2 per hit)
+    pub holes: usize
+}
+
+impl<N: na::Real> TrackCandidate<N> {
+    fn seed(seed_state_vec: &Vector5<N>, seed_cov_mat: &Matrix5<N>, capacity: usize) -> Self {
+        TrackCandidate{seed_state_vec: seed_state_vec.clone(),
+                       seed_cov_mat: seed_cov_mat.clone(),
+                       hits: Vec::with_capacity(capacity),
+                       filt_state_vec: Vec::with_capacity(capacity),
+                       filt_cov_mat: Vec::with_capacity(capacity),
+                       chi_squared_inc: Vec::with_capacity(capacity),
+                       chi_squared: na::zero(),
+                       ndf: 0,
+                       holes: 0}
+    }
+
+    /// chi squared per degree of freedom. Candidates without hits are ranked last
+    pub fn quality(&self) -> N {
+        if self.ndf == 0 {
+            return na::convert(Real::INFINITY)
+        }
+        self.chi_squared / na::convert(self.ndf as f64)
+    }
+
+    /// Ranking used while branching. Every hole counts as a two dimensional measurement with a chi
+    /// squared of `hole_penalty`, so short branches full of holes do not beat longer ones.
+    pub fn score(&self, hole_penalty: N) -> N {
+        let ndf = self.ndf + 2 * self.holes;
+        if ndf == 0 {
+            return na::convert(Real::INFINITY)
+        }
+        (self.chi_squared + hole_penalty * na::convert(self.holes as f64)) / na::convert(ndf as f64)
+    }
+
+    // copy of this branch extended by one sensor
+    fn extend(&self, hit: Option<usize>, state_vec: Vector5<N>, cov_mat: Matrix5<N>, chi_squared_inc: N) -> Self {
+        let mut branch = self.clone();
+        branch.push(hit, state_vec, cov_mat, chi_squared_inc);
+        branch
+    }
+
+    fn push(&mut self, hit: Option<usize>, state_vec: Vector5<N>, cov_mat: Matrix5<N>, chi_squared_inc: N) {
+        self.hits.push(hit);
+        self.filt_state_vec.push(state_vec);
+        self.filt_cov_mat.push(cov_mat);
+        self.chi_squared_inc.push(chi_squared_inc);
+        self.chi_squared = filter_gain::update_chi_squared(self.chi_squared, chi_squared_inc);
+
+        if hit.is_some() {
+            self.ndf += 2;
+        }
+    }
+
+    // last state of the branch extrapolated onto sensor `i`, or the seed on the first sensor
+    fn predict<T: Transform<N> + Plane<N>>(&self, sensor_vector: &Vec<T>, i: usize) -> (Vector5<N>, Matrix5<N>) {
+        match (self.filt_state_vec.last(), self.filt_cov_mat.last()) {
+            (Some(prev_state_vec), Some(prev_cov_mat)) => {
+                let (prev_sensor, sensor) = (&sensor_vector[i - 1], &sensor_vector[i]);
+                let jacobian = prediction::linear_extrapolation_jacobian(prev_sensor, sensor, prev_state_vec);
+
+                (prediction::linear_extrapolation(prev_sensor, sensor, prev_state_vec),
+                 prediction::covariance_matrix(&jacobian, prev_cov_mat))
+            },
+            _ => (self.seed_state_vec.clone(), self.seed_cov_mat.clone())
+        }
+    }
+}
+
+// filtered state, covariance and predicted chi squared of adding a measurement to a prediction.
+// `None` if the residual covariance can not be inverted
+fn filter_step<N: na::Real>(
+    pred_state_vec: &Vector5<N>,
+    pred_cov_mat: &Matrix5<N>,
+    measurement: &Vector2<N>,
+    V: &Matrix2<N>
+    ) -> Option<(Vector5<N>, Matrix5<N>, N)> {
+
+    let meas_map_mat = sensor_mapping_mat();
+
+    let pred_residual_mat = prediction::residual_mat(V, &meas_map_mat, pred_cov_mat);
+    let pred_residual_vec = prediction::residual_vec(measurement, &meas_map_mat, pred_state_vec);
+
+    let inv_residual_mat =
+        match pred_residual_mat.try_inverse() {
+            Some(inverse) => inverse,
+            None => return None
+        };
+
+    let chi_squared_inc = (pred_residual_vec.transpose() * inv_residual_mat * pred_residual_vec)[0];
+
+    // same as `filter_gain::kalman_gain`, reusing the inverse from above
+    let kalman_gain = pred_cov_mat * meas_map_mat.transpose() * inv_residual_mat;
+    let filter_state_vec = filter_gain::state_vector(pred_state_vec, &kalman_gain, measurement, &meas_map_mat);
+    let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, pred_cov_mat);
+
+    Some((filter_state_vec, filter_cov_mat, chi_squared_inc))
+}
+
+/// Combinatorial Kalman filter for track finding. Starting from the seed, every branch is predicted
+/// onto the next sensor, all measurements inside the chi squared window are collected, and the branch
+/// is split once for each of them. After each sensor only the best `max_branches` candidates are kept,
+/// ranked by `TrackCandidate::score` with a hole costing as much as a hit at `chi_squared_cut`.
+/// The returned candidates are sorted from best to worst.
+///
+/// The seed is the state on the first sensor; branches are extrapolated along straight lines between
+/// the following sensors. A branch predicted outside a sensor, beyond `boundary_tolerance`, skips it
+/// without counting a hole. A branch that can not be extrapolated onto a sensor is dropped.
+pub fn run<N: na::Real, T: Transform<N> + Plane<N>>(
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &Vec<Vec<Matrix2<N>>>,    // V of every hit on each sensor
+    measurements_vector: &Vec<Vec<Vector2<N>>>,                     // every hit registered on each sensor
+    sensor_vector: &Vec<T>,                                         // sensors in the order they are crossed
+    options: &CombinatorialOptions<N>
+    ) -> Vec<TrackCandidate<N>> {
+
+    if (measurement_noise_covariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
+    else {
+        panic!("vector lengths need to be the same length")
+    }
+    let input_length = measurements_vector.len();
+
+    let mut branches = vec![TrackCandidate::seed(seed_state_vec, seed_cov_mat, input_length)];
+
+    for i in 0..input_length {
+        let sensor = &sensor_vector[i];
+        let curr_measurements = &measurements_vector[i];
+        let curr_noise = &measurement_noise_covariance_vector[i];
+
+        let mut new_branches = Vec::with_capacity(branches.len() * (curr_measurements.len() + 1));
+
+        for branch in branches.iter() {
+            let (pred_state_vec, pred_cov_mat) = branch.predict(sensor_vector, i);
+
+            // the track runs parallel to this sensor and can not be followed any further
+            if pred_state_vec.iter().chain(pred_cov_mat.iter()).any(|value| !is_finite(*value)) {
+                continue
+            }
+
+            // the track misses this sensor entirely, so it is neither a hit nor a hole
+            let local_cov_mat = Matrix2::new(pred_cov_mat[(0, 0)], pred_cov_mat[(0, 1)],
+                                             pred_cov_mat[(1, 0)], pred_cov_mat[(1, 1)]);
+            if !sensor.inside_with_tolerance(&Point2::new(pred_state_vec[0], pred_state_vec[1]), &local_cov_mat, &options.boundary_tolerance) {
+                new_branches.push(branch.extend(None, pred_state_vec, pred_cov_mat, na::zero()));
+                continue
+            }
+
+            let mut found_compatible = false;
+
+            for (j, (curr_m_k, curr_v)) in curr_measurements.iter().zip(curr_noise.iter()).enumerate() {
+                let (filter_state_vec, filter_cov_mat, chi_squared_inc) =
+                    match filter_step(&pred_state_vec, &pred_cov_mat, curr_m_k, curr_v) {
+                        Some(step) => step,
+                        None => continue
+                    };
+
+                if chi_squared_inc > options.chi_squared_cut {
+                    continue
+                }
+                found_compatible = true;
+
+                new_branches.push(branch.extend(Some(j), filter_state_vec, filter_cov_mat, chi_squared_inc));
+            }
+
+            // continue without a hit on this sensor if the branch can afford another hole
+            if !found_compatible && branch.holes < options.max_holes {
+                let mut hole_branch = branch.extend(None, pred_state_vec, pred_cov_mat, na::zero());
+                hole_branch.holes += 1;
+                new_branches.push(hole_branch);
+            }
+        }
+
+        sort_by_score(&mut new_branches, options.chi_squared_cut);
+        new_branches.truncate(options.max_branches);
+
+        branches = new_branches;
+    }
+
+    // a branch that never picked up a hit is not a track
+    branches.retain(|branch| branch.ndf > 0);
+    branches
+}
+
+/// Fits a candidate again from its seed using only the hits it currently has, for example after
+/// the ambiguity solver took some of them away. The inputs are the ones given to `run`.
+pub fn refit<N: na::Real, T: Transform<N> + Plane<N>>(
+    candidate: &TrackCandidate<N>,
+    measurement_noise_covariance_vector: &Vec<Vec<Matrix2<N>>>,
+    measurements_vector: &Vec<Vec<Vector2<N>>>,
+    sensor_vector: &Vec<T>
+    ) -> TrackCandidate<N> {
+
+    let mut track = TrackCandidate::seed(&candidate.seed_state_vec, &candidate.seed_cov_mat, candidate.hits.len());
+    track.holes = candidate.holes;
+
+    for (i, hit) in candidate.hits.iter().enumerate() {
+        let (pred_state_vec, pred_cov_mat) = track.predict(sensor_vector, i);
+
+        let step =
+            match hit {
+                Some(j) => filter_step(&pred_state_vec, &pred_cov_mat, &measurements_vector[i][*j], &measurement_noise_covariance_vector[i][*j]),
+                None => None
+            };
+
+        match step {
+            Some((filter_state_vec, filter_cov_mat, chi_squared_inc)) => track.push(*hit, filter_state_vec, filter_cov_mat, chi_squared_inc),
+            None => track.push(None, pred_state_vec, pred_cov_mat, na::zero())
+        }
+    }
+
+    track
+}
+
+fn sort_by_score<N: na::Real>(candidates: &mut Vec<TrackCandidate<N>>, hole_penalty: N) {
+    candidates.sort_by(|a, b| {
+        a.score(hole_penalty).partial_cmp(&b.score(hole_penalty)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
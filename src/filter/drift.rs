@@ -0,0 +1,134 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::propagation;
+
+use super::super::geometry::traits::Surface;
+
+// A straw measures the unsigned distance of closest approach between the track and its wire. The
+// state bound to a straw holds the signed radius as loc0 (see `geometry::Straw`), so before an update
+// the filter has to decide which side of the wire the track passed on.
+
+/// Drift radius measured on a straw
+#[derive(Debug, Clone)]
+pub struct DriftMeasurement {
+    pub radius: Real,
+    pub variance: Real
+}
+
+/// Side of the wire the track passed on. `Right` is a positive signed radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right
+}
+
+impl Side {
+    pub fn sign(&self) -> Real {
+        match *self {
+            Side::Left => -1.,
+            Side::Right => 1.
+        }
+    }
+}
+
+/// Result of a fit through straws
+#[derive(Debug, Clone)]
+pub struct DriftData {
+    pub filt_state_vec: Vec<Vec5>,
+    pub filt_cov_mat: Vec<Mat5>,
+    pub sides: Vec<Side>,
+    pub chi_squared: Real
+}
+
+/// Kalman update with the drift radius placed on `side`. Returns the filtered state, covariance and
+/// the chi squared increment.
+pub fn update(
+    pred_state_vec: &Vec5,
+    pred_cov_mat: &Mat5,
+    measurement: &DriftMeasurement,
+    side: Side
+    ) -> Result<(Vec5, Mat5, Real), MatrixError> {
+
+    // H = (1, 0, 0, 0, 0) so the residual covariance is a scalar
+    let residual_variance = measurement.variance + pred_cov_mat[(0, 0)];
+    if residual_variance <= 0. {
+        return Err(MatrixError::NonInvertible)
+    }
+
+    let kalman_gain = pred_cov_mat.column(0) / residual_variance;
+    let residual = side.sign() * measurement.radius - pred_state_vec[0];
+
+    let filt_state_vec = pred_state_vec + kalman_gain * residual;
+    let filt_cov_mat = pred_cov_mat - kalman_gain * pred_cov_mat.row(0);
+
+    Ok((filt_state_vec, filt_cov_mat, residual * residual / residual_variance))
+}
+
+/// Side of the wire more compatible with the prediction. Both hypotheses share the residual
+/// covariance, so this is the side with the smaller residual.
+pub fn resolve_side(
+    pred_state_vec: &Vec5,
+    measurement: &DriftMeasurement
+    ) -> Side {
+
+    if (measurement.radius - pred_state_vec[0]).abs() <= (-measurement.radius - pred_state_vec[0]).abs() {
+        Side::Right
+    }
+    else {
+        Side::Left
+    }
+}
+
+/// Update on the side picked by `resolve_side`
+pub fn ambiguous_update(
+    pred_state_vec: &Vec5,
+    pred_cov_mat: &Mat5,
+    measurement: &DriftMeasurement
+    ) -> Result<(Vec5, Mat5, Real, Side), MatrixError> {
+
+    let side = resolve_side(pred_state_vec, measurement);
+    let (filt_state_vec, filt_cov_mat, chi_squared) = update(pred_state_vec, pred_cov_mat, measurement, side)?;
+
+    Ok((filt_state_vec, filt_cov_mat, chi_squared, side))
+}
+
+/// Forward filter through a sequence of straws. The seed is the predicted state on the first straw.
+pub fn run<T: Surface>(
+    seed_state_vec: &Vec5,
+    seed_cov_mat: &Mat5,
+    measurements: &Vec<DriftMeasurement>,
+    straws: &Vec<T>
+    ) -> Result<DriftData, Error> {
+
+    if measurements.len() != straws.len() {
+        panic!("vector lengths need to be the same length")
+    }
+
+    let mut filt_state_vec = Vec::with_capacity(straws.len());
+    let mut filt_cov_mat = Vec::with_capacity(straws.len());
+    let mut sides = Vec::with_capacity(straws.len());
+    let mut chi_squared = 0.;
+
+    let mut pred_state_vec = seed_state_vec.clone();
+    let mut pred_cov_mat = seed_cov_mat.clone();
+
+    for i in 0..straws.len() {
+        if i > 0 {
+            let prediction = propagation::predict(&straws[i - 1], &straws[i], &filt_state_vec[i - 1], &filt_cov_mat[i - 1])?;
+            pred_state_vec = prediction.0;
+            pred_cov_mat = prediction.1;
+        }
+
+        let (state_vec, cov_mat, chi_squared_inc, side) = ambiguous_update(&pred_state_vec, &pred_cov_mat, &measurements[i])?;
+
+        filt_state_vec.push(state_vec);
+        filt_cov_mat.push(cov_mat);
+        sides.push(side);
+        chi_squared += chi_squared_inc;
+    }
+
+    Ok(DriftData{filt_state_vec: filt_state_vec,
+                 filt_cov_mat: filt_cov_mat,
+                 sides: sides,
+                 chi_squared: chi_squared})
+}
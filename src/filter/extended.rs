@@ -0,0 +1,132 @@
+use super::super::config::*;
+use super::super::error::*;
+
+/// Describes how the state moves from one step to the next: x_k = f(x_k-1)
+pub trait StateModel {
+    /// Propagates a state vector to the next step
+    fn transition(&self, state_vec: &DVec) -> DVec;
+
+    /// Partial derivatives of `transition` evaluated at `state_vec`
+    fn jacobian(&self, state_vec: &DVec) -> DMat;
+}
+
+/// Describes what a measurement of a given state looks like: m_k = h(x_k)
+pub trait MeasurementModel {
+    /// Predicts the measurement of a state vector
+    fn measure(&self, state_vec: &DVec) -> DVec;
+
+    /// Partial derivatives of `measure` evaluated at `state_vec`
+    fn jacobian(&self, state_vec: &DVec) -> DMat;
+}
+
+/// Extended kalman filter over user supplied models. Predictions and updates are driven one step
+/// at a time so the current estimate can be inspected after every measurement.
+pub struct ExtendedKalmanFilter<S: StateModel, M: MeasurementModel> {
+    state_model: S,
+    measurement_model: M,
+    process_noise: DMat,        // Q
+    measurement_noise: DMat,    // V
+    state_vec: DVec,            // x
+    cov_mat: DMat               // C
+}
+
+impl<S: StateModel, M: MeasurementModel> ExtendedKalmanFilter<S, M> {
+    /// Creates a filter starting from `state_vec` / `cov_mat`. Returns an error if the noise and
+    /// covariance matrices do not match the dimension of the state vector.
+    pub fn new(
+        state_model: S,
+        measurement_model: M,
+        process_noise: DMat,
+        measurement_noise: DMat,
+        state_vec: DVec,
+        cov_mat: DMat
+        ) -> Result<Self, MatrixError> {
+
+        let dim = state_vec.len();
+        if !cov_mat.is_square() || cov_mat.nrows() != dim || process_noise.shape() != (dim, dim) || !measurement_noise.is_square() {
+            return Err(MatrixError::DimensionMismatch)
+        }
+
+        Ok(ExtendedKalmanFilter{state_model: state_model,
+                                measurement_model: measurement_model,
+                                process_noise: process_noise,
+                                measurement_noise: measurement_noise,
+                                state_vec: state_vec,
+                                cov_mat: cov_mat})
+    }
+
+    /// Moves the estimate forward one step using the state model. Returns an error and leaves the
+    /// filter unchanged if the model's outputs do not match the dimension of the state.
+    pub fn predict(&mut self) -> Result<(), MatrixError> {
+        let dim = self.state_vec.len();
+
+        let jacobian = self.state_model.jacobian(&self.state_vec);
+        let pred_state_vec = self.state_model.transition(&self.state_vec);
+
+        if jacobian.shape() != (dim, dim) || pred_state_vec.len() != dim {
+            return Err(MatrixError::DimensionMismatch)
+        }
+
+        self.state_vec = pred_state_vec;
+        self.cov_mat = &jacobian * &self.cov_mat * jacobian.transpose() + &self.process_noise;
+        Ok(())
+    }
+
+    /// Corrects the estimate with a new measurement. Returns the chi squared increment of the measurement.
+    /// The measurement and the outputs of the measurement model must match the dimension of V.
+    pub fn update(&mut self, measurement: &DVec) -> Result<Real, MatrixError> {
+        let meas_dim = self.measurement_noise.nrows();
+        if measurement.len() != meas_dim {
+            return Err(MatrixError::DimensionMismatch)
+        }
+
+        let meas_map_mat = self.measurement_model.jacobian(&self.state_vec);  // H
+        let pred_measurement = self.measurement_model.measure(&self.state_vec);
+
+        if meas_map_mat.shape() != (meas_dim, self.state_vec.len()) || pred_measurement.len() != meas_dim {
+            return Err(MatrixError::DimensionMismatch)
+        }
+
+        let residual_vec = measurement - pred_measurement;
+        let residual_mat = &self.measurement_noise + &meas_map_mat * &self.cov_mat * meas_map_mat.transpose();
+
+        let inv_residual_mat = match residual_mat.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(MatrixError::NonInvertible)
+        };
+
+        let kalman_gain = &self.cov_mat * meas_map_mat.transpose() * &inv_residual_mat;
+        let identity = DMat::identity(self.state_vec.len(), self.state_vec.len());
+
+        self.state_vec += &kalman_gain * &residual_vec;
+        self.cov_mat = (identity - &kalman_gain * &meas_map_mat) * &self.cov_mat;
+
+        Ok((residual_vec.transpose() * inv_residual_mat * residual_vec)[0])
+    }
+
+    pub fn state_vec(&self) -> &DVec {
+        &self.state_vec
+    }
+
+    pub fn cov_mat(&self) -> &DMat {
+        &self.cov_mat
+    }
+
+    /// Replaces Q, for models where the process noise depends on the step size
+    pub fn set_process_noise(&mut self, process_noise: DMat) -> Result<(), MatrixError> {
+        if process_noise.shape() != self.cov_mat.shape() {
+            return Err(MatrixError::DimensionMismatch)
+        }
+        self.process_noise = process_noise;
+        Ok(())
+    }
+
+    /// Replaces V, for sensors whose resolution changes between measurements
+    pub fn set_measurement_noise(&mut self, measurement_noise: DMat) -> Result<(), MatrixError> {
+        if !measurement_noise.is_square() {
+            return Err(MatrixError::DimensionMismatch)
+        }
+        self.measurement_noise = measurement_noise;
+        Ok(())
+    }
+}
@@ -1,13 +1,13 @@
 use nalgebra as na;
-use super::super::config::*;
+use na::{Matrix2, Matrix5, Matrix2x5, Matrix5x2, Vector2, Vector5};
 
 
-pub fn state_vector( 
-    pred_state_vec: &Vec5,  //x
-    kalman_gain: &Mat5x2,   //K
-    measurement : &Vec2,    //m_k
-    sensor_mapping_mat: &Mat2x5 // H
-    ) -> Vec5 { // x
+pub fn state_vector<N: na::Real>( 
+    pred_state_vec: &Vector5<N>,  //x
+    kalman_gain: &Matrix5x2<N>,   //K
+    measurement : &Vector2<N>,    //m_k
+    sensor_mapping_mat: &Matrix2x5<N> // H
+    ) -> Vector5<N> { // x
 
     let parens = measurement - (sensor_mapping_mat * pred_state_vec);
     let kalman_product = kalman_gain * parens;
@@ -15,70 +15,97 @@ pub fn state_vector(
 }
 
 //TODO: remove `unwrap` on the inverse
-pub fn kalman_gain (                
-    pred_covariance : &Mat5,        //C
-    sensor_mapping_mat : &Mat2x5,   //H
-    V : &Mat2                       //V
-    ) -> Mat5x2 {                   // K 
+pub fn kalman_gain<N: na::Real>(                
+    pred_covariance : &Matrix5<N>,        //C
+    sensor_mapping_mat : &Matrix2x5<N>,   //H
+    V : &Matrix2<N>                       //V
+    ) -> Matrix5x2<N> {                   // K 
 
     let parens = V + ( sensor_mapping_mat * pred_covariance * sensor_mapping_mat.transpose() );
-    let kalman_gain = pred_covariance * sensor_mapping_mat.transpose() * parens.try_inverse().unwrap();
-    
-    kalman_gain
+
+    kalman_gain_inverted(pred_covariance, sensor_mapping_mat, &parens.try_inverse().unwrap())
+}
+
+
+/// Kalman gain from an already inverted residual covariance matrix `(V + H C H^T)^-1`, for callers
+/// that have checked the inverse themselves.
+pub fn kalman_gain_inverted<N: na::Real>(
+    pred_covariance : &Matrix5<N>,        //C
+    sensor_mapping_mat : &Matrix2x5<N>,   //H
+    inv_residual_mat : &Matrix2<N>        //(pred R)^-1
+    ) -> Matrix5x2<N> {                   // K
+
+    pred_covariance * sensor_mapping_mat.transpose() * inv_residual_mat
 }
 
 
-pub fn covariance_matrix( 
-    kalman_gain_mat : &Mat5x2,      //K
-    sensor_mapping_mat : &Mat2x5,   // H
-    pred_covariance : &Mat5         // pred C
-    ) -> Mat5 {                     //filt C
+/// Filtered covariance matrix `(1 - KH) C`
+pub fn covariance_matrix<N: na::Real>( 
+    kalman_gain_mat : &Matrix5x2<N>,      //K
+    sensor_mapping_mat : &Matrix2x5<N>,   // H
+    pred_covariance : &Matrix5<N>         // pred C
+    ) -> Matrix5<N> {                     //filt C
         
-    let parens = Mat5::identity() - (kalman_gain_mat*sensor_mapping_mat);
+    let parens = Matrix5::identity() - (kalman_gain_mat*sensor_mapping_mat);
+
+    return parens * pred_covariance;
+}
+
+
+/// Joseph form of the filtered covariance matrix. Equal to `covariance_matrix` in exact arithmetic
+/// but remains symmetric and positive definite when rounding errors creep in.
+pub fn covariance_matrix_joseph<N: na::Real>(
+    kalman_gain_mat : &Matrix5x2<N>,      // K
+    sensor_mapping_mat : &Matrix2x5<N>,   // H
+    pred_covariance : &Matrix5<N>,        // pred C
+    V : &Matrix2<N>                       // V
+    ) -> Matrix5<N> {                     // filt C
+
+    let parens = Matrix5::identity() - (kalman_gain_mat*sensor_mapping_mat);
 
-    return pred_covariance * parens;
+    return parens * pred_covariance * parens.transpose() + kalman_gain_mat * V * kalman_gain_mat.transpose();
 }
 
 
 //TODO ensure that `identity` is complile-time optimized
-pub fn residual_vec(
-    sensor_mapping_mat : &Mat2x5,          // H
-    kalman_gain_mat : &Mat5x2,             // K
-    pred_residual_vec : &Vec2              // pred r
-    ) -> Vec2 {                            // filt r
+pub fn residual_vec<N: na::Real>(
+    sensor_mapping_mat : &Matrix2x5<N>,          // H
+    kalman_gain_mat : &Matrix5x2<N>,             // K
+    pred_residual_vec : &Vector2<N>              // pred r
+    ) -> Vector2<N> {                            // filt r
 
-    let ident = Mat2::identity();
+    let ident = Matrix2::identity();
     let parens = ident - (sensor_mapping_mat * kalman_gain_mat);
 
     return  parens * pred_residual_vec;
 }
 
 
-pub fn residual_mat( //R
-    V : &Mat2,                      // V
-    sensor_mapping_mat : &Mat2x5,   // H
-    filt_covariance_mat : &Mat5     //filt C
-    ) -> Mat2{                      //filt R
+pub fn residual_mat<N: na::Real>( //R
+    V : &Matrix2<N>,                      // V
+    sensor_mapping_mat : &Matrix2x5<N>,   // H
+    filt_covariance_mat : &Matrix5<N>     //filt C
+    ) -> Matrix2<N>{                      //filt R
     
     let product = sensor_mapping_mat * filt_covariance_mat * sensor_mapping_mat.transpose();
     return V - product;
 }
 
 
-pub fn chi_squared_increment(
-    filt_residual_vec : &Vec2,
-    filt_residual_mat : &Mat2 
-    ) -> Real {
+pub fn chi_squared_increment<N: na::Real>(
+    filt_residual_vec : &Vector2<N>,
+    filt_residual_mat : &Matrix2<N> 
+    ) -> N {
     
     let prod = filt_residual_vec.transpose() * filt_residual_mat.try_inverse().expect("could not invert residual covairiance matrix") * filt_residual_vec;
     return prod[0]
 }
 
 
-pub fn update_chi_squared(
-    previous_chi_squaread: Real,
-    increment: Real
-    ) -> Real {
+pub fn update_chi_squared<N: na::Real>(
+    previous_chi_squaread: N,
+    increment: N
+    ) -> N {
     
     previous_chi_squaread + increment
 }
@@ -1,14 +1,14 @@
 use nalgebra as na;
-use super::super::config::*;
-
-pub fn state_vector(
-    filt_covariance_mat : &Mat5,    // filt C
-    pred_covariance_mat : &Mat5,    // pred C
-    pred_state_vec: &Vec5,          // pred x
-    sensor_mapping_mat: &Mat2x5,    // H
-    G : &Mat2,                      // inv(V)
-    measurement_vec: &Vec2          //m_k
-    ) -> Vec5{                      //x
+use na::{Matrix2, Matrix5, Matrix2x5, Vector2, Vector5};
+
+pub fn state_vector<N: na::Real>(
+    filt_covariance_mat : &Matrix5<N>,    // filt C
+    pred_covariance_mat : &Matrix5<N>,    // pred C
+    pred_state_vec: &Vector5<N>,          // pred x
+    sensor_mapping_mat: &Matrix2x5<N>,    // H
+    G : &Matrix2<N>,                      // inv(V)
+    measurement_vec: &Vector2<N>          //m_k
+    ) -> Vector5<N>{                      //x
     
     let product_one = pred_covariance_mat.try_inverse().expect("could not invert pred cov mat") * pred_state_vec;
     let product_two = sensor_mapping_mat.transpose() * G * measurement_vec;
@@ -17,11 +17,11 @@ pub fn state_vector(
 }
 
 
-pub fn covariance_matrix ( 
-    pred_covariance_mat: &Mat5,     // pred C
-    sensor_mapping_mat : &Mat2x5,   // H
-    G : &Mat2                       // inv (V)
-    ) -> Mat5 {                     // filt C
+pub fn covariance_matrix<N: na::Real>( 
+    pred_covariance_mat: &Matrix5<N>,     // pred C
+    sensor_mapping_mat : &Matrix2x5<N>,   // H
+    G : &Matrix2<N>                       // inv (V)
+    ) -> Matrix5<N> {                     // filt C
     
     let product = sensor_mapping_mat.transpose() * G *sensor_mapping_mat;
     let C_prevoius_inv = pred_covariance_mat.try_inverse().expect("could not invert previous covariance");
@@ -30,12 +30,12 @@ pub fn covariance_matrix (
 }
 
 
-pub fn chi_squared_increment(
-    residual_vec: &Vec2,
-    G: &Mat2,
-    state_vector: &Vec5,
-    extrap_state_vector: &Vec5,
-    pred_covariance_mat: &Mat5) -> Real {
+pub fn chi_squared_increment<N: na::Real>(
+    residual_vec: &Vector2<N>,
+    G: &Matrix2<N>,
+    state_vector: &Vector5<N>,
+    extrap_state_vector: &Vector5<N>,
+    pred_covariance_mat: &Matrix5<N>) -> N {
 
     let first_term = residual_vec.transpose() * G * residual_vec;
 
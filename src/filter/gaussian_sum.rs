@@ -0,0 +1,329 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Vector2, Vector5};
+use super::super::config::*;
+use super::super::error::*;
+use super::prediction;
+use super::filter_gain;
+use super::linear::linear_jacobian;
+use super::utils::{is_finite, sensor_mapping_mat};
+
+use super::super::geometry::traits::{Plane, Transform};
+
+/// One weighted gaussian of the state mixture
+#[derive(Debug, Clone)]
+pub struct Component<N: na::Real = Real> {
+    pub weight: N,
+    pub state_vec: Vector5<N>,
+    pub cov_mat: Matrix5<N>
+}
+
+/// Gaussian mixture approximating the Bethe-Heitler distribution of the fraction of energy
+/// kept by an electron after crossing a sensor. Each entry is `(weight, mean, variance)` of that fraction.
+#[derive(Debug, Clone)]
+pub struct BetheHeitlerMixture<N: na::Real = Real> {
+    pub components: Vec<(N, N, N)>
+}
+
+impl<N: na::Real> BetheHeitlerMixture<N> {
+    pub fn new(components: Vec<(N, N, N)>) -> Self {
+        BetheHeitlerMixture{components: components}
+    }
+
+    /// A mixture that leaves the momentum untouched. The filter then behaves like a regular
+    /// kalman filter applied to every component.
+    pub fn no_loss() -> Self {
+        BetheHeitlerMixture{components: vec![(N::one(), N::one(), na::zero())]}
+    }
+}
+
+/// Results of the gaussian sum filter on every sensor
+#[derive(Debug, Clone)]
+pub struct GaussianSumData<N: na::Real = Real> {
+    pub mean_state_vec: Vec<Vector5<N>>,
+    pub mean_cov_mat: Vec<Matrix5<N>>,
+    pub mode_state_vec: Vec<Vector5<N>>,
+    pub components: Vec<Vec<Component<N>>>
+}
+
+/// Gaussian sum filter for electrons. The state is a weighted mixture of gaussians. On each sensor
+/// every component is split by the Bethe-Heitler mixture, updated with the measurement and reweighted
+/// by how well it predicted the hit. The mixture is then reduced to at most `max_components` by merging
+/// the pair of components with the smallest symmetric KL distance.
+///
+/// The seed is the state on the first sensor; every component is extrapolated along a straight line
+/// onto the following sensors. Returns `ConfigError::InvalidValue` if a Bethe-Heitler component has
+/// a weight or mean that is not positive and finite.
+pub fn run<N: na::Real, T: Transform<N> + Plane<N>>(
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &Vec<Matrix2<N>>,     // V
+    measurements_vector: &Vec<Vector2<N>>,                      // m_k
+    sensor_vector: &[T],                                        // the sensor of each hit
+    bethe_heitler: &BetheHeitlerMixture<N>,
+    max_components: usize
+    ) -> Result<GaussianSumData<N>, Error> {
+
+    if (measurement_noise_covariance_vector.len() != measurements_vector.len()) || (measurements_vector.len() != sensor_vector.len()) {
+        panic!("vector lengths need to be the same length")
+    }
+
+    // log weights of zero and a division by the mean fraction of energy kept are not defined
+    if bethe_heitler.components.is_empty() {
+        return Err(ConfigError::InvalidValue("the Bethe-Heitler mixture needs at least one component".to_string()).into())
+    }
+    let positive = |value: N| is_finite(value) && value > na::zero();
+    if !bethe_heitler.components.iter().all(|(weight, mean, variance)| positive(*weight) && positive(*mean) && is_finite(*variance) && *variance >= na::zero()) {
+        return Err(ConfigError::InvalidValue("Bethe-Heitler components need a positive weight and mean".to_string()).into())
+    }
+
+    let input_length = measurements_vector.len();
+
+    let meas_map_mat = sensor_mapping_mat();
+
+    store_vec!{input_length;
+        mean_state_vec_iter: Vector5<N>,
+        mean_cov_mat_iter: Matrix5<N>,
+        mode_state_vec_iter: Vector5<N>,
+        components_iter: Vec<Component<N>>
+    }
+
+    let half : N = na::convert(0.5);
+
+    let mut mixture = vec![Component{weight: N::one(),
+                                     state_vec: seed_state_vec.clone(),
+                                     cov_mat: seed_cov_mat.clone()}];
+
+    for i in 0..input_length {
+        get_unchecked!{i;
+            measurement_noise_covariance_vector => curr_v,
+            measurements_vector => curr_m_k
+        }
+
+        let mut updated = Vec::with_capacity(mixture.len() * bethe_heitler.components.len());
+        let mut log_weights = Vec::with_capacity(updated.capacity());
+
+        for component in mixture.iter() {
+            // the seed already sits on the first sensor
+            let (jacobian, pred_state_vec) =
+                if i == 0 {
+                    (linear_jacobian(), component.state_vec)
+                }
+                else {
+                    (prediction::linear_extrapolation_jacobian(&sensor_vector[i - 1], &sensor_vector[i], &component.state_vec),
+                     prediction::linear_extrapolation(&sensor_vector[i - 1], &sensor_vector[i], &component.state_vec))
+                };
+            let pred_cov_mat = prediction::covariance_matrix(&jacobian, &component.cov_mat);
+
+            for (bh_weight, bh_mean, bh_variance) in bethe_heitler.components.iter() {
+                let (loss_state_vec, loss_cov_mat) = energy_loss(&pred_state_vec, &pred_cov_mat, *bh_mean, *bh_variance);
+
+                let pred_residual_mat = prediction::residual_mat(curr_v, &meas_map_mat, &loss_cov_mat);
+                let pred_residual_vec = prediction::residual_vec(curr_m_k, &meas_map_mat, &loss_state_vec);
+
+                let inv_residual_mat = match pred_residual_mat.try_inverse() {
+                    Some(inverse) => inverse,
+                    None => return Err(MatrixError::NonInvertible.into())
+                };
+
+                let residual_determinant = pred_residual_mat.determinant();
+                if !is_finite(residual_determinant) || residual_determinant <= na::zero() {
+                    return Err(MatrixError::NotPositiveDefinite.into())
+                }
+
+                let kalman_gain = filter_gain::kalman_gain_inverted(&loss_cov_mat, &meas_map_mat, &inv_residual_mat);
+                let filter_state_vec = filter_gain::state_vector(&loss_state_vec, &kalman_gain, curr_m_k, &meas_map_mat);
+                let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, &loss_cov_mat);
+
+                // weight is scaled by the likelihood of the measurement given this component
+                let chi_squared = (pred_residual_vec.transpose() * inv_residual_mat * pred_residual_vec)[0];
+                let log_likelihood = -half * chi_squared - half * (N::two_pi() * N::two_pi() * residual_determinant).ln();
+
+                log_weights.push((component.weight * *bh_weight).ln() + log_likelihood);
+                updated.push(Component{weight: na::zero(),
+                                       state_vec: filter_state_vec,
+                                       cov_mat: filter_cov_mat});
+            }
+        }
+
+        // normalize in log space so that far away components do not underflow all at once
+        let max_log_weight = log_weights.iter().fold(na::convert(Real::NEG_INFINITY), |max : N, w| max.max(*w));
+        let total = log_weights.iter().fold(N::zero(), |sum, w| sum + (*w - max_log_weight).exp());
+        for (component, log_weight) in updated.iter_mut().zip(log_weights.iter()) {
+            component.weight = (*log_weight - max_log_weight).exp() / total;
+        }
+
+        // components that underflowed to zero would give a log weight of -inf on the next sensor
+        updated.retain(|component| component.weight > na::zero());
+
+        mixture = reduce(updated, max_components)?;
+
+        let (mean_state_vec, mean_cov_mat) = mean(&mixture);
+        let mode_state_vec = mode(&mixture, &mean_state_vec)?;
+
+        push!{
+            mean_state_vec => mean_state_vec_iter,
+            mean_cov_mat => mean_cov_mat_iter,
+            mode_state_vec => mode_state_vec_iter,
+            mixture.clone() => components_iter
+        }
+    }
+
+    Ok(GaussianSumData{mean_state_vec: mean_state_vec_iter,
+                       mean_cov_mat: mean_cov_mat_iter,
+                       mode_state_vec: mode_state_vec_iter,
+                       components: components_iter})
+}
+
+// shift q/p by one component of the energy loss and add its variance
+fn energy_loss<N: na::Real>(
+    state_vec: &Vector5<N>,
+    cov_mat: &Matrix5<N>,
+    z_mean: N,
+    z_variance: N
+    ) -> (Vector5<N>, Matrix5<N>) {
+
+    let q_over_p = state_vec[4];
+
+    let mut new_state_vec = state_vec.clone();
+    new_state_vec[4] = q_over_p / z_mean;
+
+    let mut new_cov_mat = cov_mat.clone();
+    new_cov_mat[(4, 4)] += q_over_p * q_over_p * z_variance / z_mean.powi(4);
+
+    (new_state_vec, new_cov_mat)
+}
+
+/// Merge the closest pair of components until at most `max_components` remain
+pub fn reduce<N: na::Real>(
+    mut mixture: Vec<Component<N>>,
+    max_components: usize
+    ) -> Result<Vec<Component<N>>, MatrixError> {
+
+    while mixture.len() > max_components.max(1) {
+        let mut closest = (0, 1);
+        let mut min_distance : N = na::convert(Real::INFINITY);
+
+        for a in 0..mixture.len() {
+            for b in (a + 1)..mixture.len() {
+                let distance = kl_distance(&mixture[a], &mixture[b])?;
+                if distance < min_distance {
+                    min_distance = distance;
+                    closest = (a, b);
+                }
+            }
+        }
+
+        let second = mixture.swap_remove(closest.1);
+        let first = &mut mixture[closest.0];
+        *first = merge(first, &second);
+    }
+
+    Ok(mixture)
+}
+
+// symmetric Kullback-Leibler distance between two gaussian components
+fn kl_distance<N: na::Real>(a: &Component<N>, b: &Component<N>) -> Result<N, MatrixError> {
+    let inv_a = invert(&a.cov_mat)?;
+    let inv_b = invert(&b.cov_mat)?;
+
+    let diff = a.state_vec - b.state_vec;
+    let trace_term = (inv_a * b.cov_mat + inv_b * a.cov_mat).trace() - na::convert(10.0);
+    let mean_term = (diff.transpose() * (inv_a + inv_b) * diff)[0];
+
+    Ok((trace_term + mean_term) * na::convert(0.5))
+}
+
+// moment preserving merge of two components
+fn merge<N: na::Real>(a: &Component<N>, b: &Component<N>) -> Component<N> {
+    let weight = a.weight + b.weight;
+    let state_vec = (a.state_vec * a.weight + b.state_vec * b.weight) / weight;
+
+    let diff = a.state_vec - b.state_vec;
+    let spread = diff * diff.transpose() * (a.weight * b.weight / (weight * weight));
+    let cov_mat = (a.cov_mat * a.weight + b.cov_mat * b.weight) / weight + spread;
+
+    Component{weight: weight, state_vec: state_vec, cov_mat: cov_mat}
+}
+
+/// Mean and covariance of the whole mixture
+pub fn mean<N: na::Real>(mixture: &Vec<Component<N>>) -> (Vector5<N>, Matrix5<N>) {
+    let mut mean_state_vec = Vector5::zeros();
+    for component in mixture.iter() {
+        mean_state_vec += component.state_vec * component.weight;
+    }
+
+    let mut mean_cov_mat = Matrix5::zeros();
+    for component in mixture.iter() {
+        let diff = component.state_vec - mean_state_vec;
+        mean_cov_mat += (component.cov_mat + diff * diff.transpose()) * component.weight;
+    }
+
+    (mean_state_vec, mean_cov_mat)
+}
+
+/// Location of the highest peak of the mixture density. Found with a fixed point iteration
+/// starting from the heaviest component.
+pub fn mode<N: na::Real>(mixture: &Vec<Component<N>>, mean_state_vec: &Vector5<N>) -> Result<Vector5<N>, MatrixError> {
+    // components without weight do not contribute to the density
+    let mixture : Vec<&Component<N>> = mixture.iter().filter(|component| component.weight > na::zero()).collect();
+
+    match mixture.len() {
+        0 => return Ok(*mean_state_vec),
+        1 => return Ok(mixture[0].state_vec.clone()),
+        _ => {}
+    }
+
+    let half : N = na::convert(0.5);
+
+    let mut inverses = Vec::with_capacity(mixture.len());
+    let mut log_norms = Vec::with_capacity(mixture.len());
+    for component in mixture.iter() {
+        inverses.push(invert(&component.cov_mat)?);
+        log_norms.push(component.weight.ln() - half * component.cov_mat.determinant().ln());
+    }
+
+    let mut state_vec = mixture.iter()
+                        .max_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap_or(std::cmp::Ordering::Equal))
+                        .map(|component| component.state_vec.clone())
+                        .unwrap_or(mean_state_vec.clone());
+
+    for _ in 0..MODE_MAX_ITERATIONS {
+        let log_densities : Vec<N> = mixture.iter()
+            .zip(inverses.iter())
+            .zip(log_norms.iter())
+            .map(|((component, inv), norm)| {
+                let diff = state_vec - component.state_vec;
+                *norm - half * (diff.transpose() * inv * diff)[0]
+            })
+            .collect();
+        let max_log_density = log_densities.iter().fold(na::convert(Real::NEG_INFINITY), |max : N, density| max.max(*density));
+
+        let mut weighted_inv = Matrix5::zeros();
+        let mut weighted_state_vec = Vector5::zeros();
+        for ((component, inv), log_density) in mixture.iter().zip(inverses.iter()).zip(log_densities.iter()) {
+            let density = (*log_density - max_log_density).exp();
+            weighted_inv += inv * density;
+            weighted_state_vec += inv * component.state_vec * density;
+        }
+
+        let next_state_vec = invert(&weighted_inv)? * weighted_state_vec;
+        let step = (next_state_vec - state_vec).norm();
+        state_vec = next_state_vec;
+
+        if step < na::convert(MODE_TOLERANCE) {
+            break
+        }
+    }
+
+    Ok(state_vec)
+}
+
+const MODE_MAX_ITERATIONS : usize = 50;
+const MODE_TOLERANCE : Real = 1e-10;
+
+fn invert<N: na::Real>(matrix: &Matrix5<N>) -> Result<Matrix5<N>, MatrixError> {
+    match matrix.try_inverse() {
+        Some(inverse) => Ok(inverse),
+        None => Err(MatrixError::NonInvertible)
+    }
+}
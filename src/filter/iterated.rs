@@ -0,0 +1,68 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::kalman::KalmanFilter;
+use super::utils::SmoothedData;
+
+use super::super::geometry::traits::{Plane, Transform};
+
+/// Result of an iterated fit
+pub struct IteratedFit {
+    pub smoothed: SmoothedData,
+    pub chi_squared: Real,
+    pub iterations: usize,      // number of forward / backward passes that were run
+    pub converged: bool         // the change in chi squared fell below the tolerance before the cap
+}
+
+/// Fits a track several times, linearizing every pass around the smoothed trajectory of the pass before.
+/// The first pass is linearized around the filtered states, with the same numeric jacobian as later
+/// passes. Iterations stop once the total chi squared changes by less than `tolerance` or after
+/// `options.max_iterations` passes.
+pub fn run<T: Transform + Plane>(
+    seed_state_vec: &Vec5,
+    measurement_noise_covariance_vector: &Vec<Mat2>,    // V
+    measurements_vector: &Vec<Vec2>,                    // m_k
+    sensor_vector: &Vec<T>,
+    options: &FitterOptions,
+    tolerance: Real
+    ) -> Result<IteratedFit, Error> {
+
+    if (measurement_noise_covariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
+    else {
+        panic!("vector lengths need to be the same length")
+    }
+
+    let max_iterations = options.max_iterations.max(1);
+
+    let mut reference : Option<Vec<Vec5>> = None;
+    let mut previous_chi_squared = Real::INFINITY;
+
+    for iteration in 1..=max_iterations {
+        let mut kf = KalmanFilter::with_options(seed_state_vec.clone(), options.clone());
+
+        for i in 0..sensor_vector.len() {
+            let linearization_point =
+                match reference {
+                    Some(ref trajectory) if i > 0 => trajectory[i - 1].clone(),
+                    _ => kf.state_vec().clone()
+                };
+            kf.predict_linearized(&sensor_vector[i], &linearization_point)?;
+            kf.update(&measurements_vector[i], &measurement_noise_covariance_vector[i])?;
+        }
+
+        let smoothed = kf.smooth()?;
+        let chi_squared = kf.chi_squared();
+
+        let converged = (chi_squared - previous_chi_squared).abs() < tolerance;
+        if converged || iteration == max_iterations {
+            return Ok(IteratedFit{smoothed: smoothed,
+                                  chi_squared: chi_squared,
+                                  iterations: iteration,
+                                  converged: converged})
+        }
+
+        previous_chi_squared = chi_squared;
+        reference = Some(smoothed.state_vec().clone());
+    }
+
+    unreachable!()
+}
@@ -0,0 +1,337 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Point2, Vector2, Vector5};
+use super::super::config::*;
+use super::super::error::*;
+use super::prediction;
+use super::filter_gain;
+use super::smoothing;
+use super::linear::linear_jacobian;
+use super::unscented::{self, SigmaPoints};
+use super::utils::{sensor_mapping_mat, SmoothedData};
+
+use super::super::geometry::traits::{Plane, Transform};
+
+/// Everything the filter calculated on one sensor. `filt_*` equals `pred_*` until
+/// a measurement is added with `KalmanFilter::update`.
+#[derive(Debug, Clone)]
+pub struct FilterStep<N: na::Real = Real> {
+    pub jacobian: Matrix5<N>,
+    pub pred_state_vec: Vector5<N>,
+    pub pred_cov_mat: Matrix5<N>,
+    pub filt_state_vec: Vector5<N>,
+    pub filt_cov_mat: Matrix5<N>,
+    pub measurement: Option<(Vector2<N>, Matrix2<N>)>,  // m_k and V of the hit used on this sensor
+    pub chi_squared_inc: N,
+    pub outlier: bool                       // the measurement failed the outlier cut and was not used
+}
+
+// jacobian, predicted state and predicted covariance on the next sensor
+type Prediction<N> = (Matrix5<N>, Vector5<N>, Matrix5<N>);
+
+// sigma points used by `Propagator::Unscented`
+const UNSCENTED_SIGMA_POINTS : SigmaPoints = SigmaPoints::MerweScaled{alpha: 1., beta: 2., kappa: 0.};
+
+/// Kalman filter that is driven one sensor at a time. The current estimate can be read after
+/// every call, which allows measurements to be fed in as they arrive.
+///
+/// The seed passed to `new` is the state on the first sensor given to `predict`. The options
+/// are always `Real` and are converted to the float type of the filter where they are used.
+#[derive(Debug)]
+pub struct KalmanFilter<'a, T: Transform<N> + Plane<N>, N: na::Real = Real> {
+    state_vec: Vector5<N>,
+    cov_mat: Matrix5<N>,
+    surface: Option<&'a T>,
+    steps: Vec<FilterStep<N>>,
+    chi_squared: N,
+    options: FitterOptions
+}
+
+impl<'a, N: na::Real, T: Transform<N> + Plane<N>> KalmanFilter<'a, T, N> {
+    /// Creates a filter with the default `FitterOptions`
+    pub fn new(seed_state_vec: Vector5<N>, seed_cov_mat: Matrix5<N>) -> Self {
+        KalmanFilter{state_vec: seed_state_vec,
+                     cov_mat: seed_cov_mat,
+                     surface: None,
+                     steps: Vec::new(),
+                     chi_squared: na::zero(),
+                     options: FitterOptions::default()}
+    }
+
+    /// Creates a filter seeded with `options.seed_covariance`
+    pub fn with_options(seed_state_vec: Vector5<N>, options: FitterOptions) -> Self {
+        KalmanFilter{state_vec: seed_state_vec,
+                     cov_mat: na::convert(options.seed_covariance),
+                     surface: None,
+                     steps: Vec::new(),
+                     chi_squared: na::zero(),
+                     options: options}
+    }
+
+    /// Extrapolates the current state onto `to_surface`. If the extrapolated point falls outside of
+    /// the sensor an error is returned and the filter is left unchanged.
+    pub fn predict(&mut self, to_surface: &'a T) -> Result<(), Error> {
+        let (jacobian, pred_state_vec, pred_cov_mat) =
+            match self.surface {
+                Some(from_surface) => self.extrapolate(from_surface, to_surface)?,
+                None => (linear_jacobian(), self.state_vec.clone(), self.cov_mat.clone())
+            };
+
+        let material_state_vec = pred_state_vec.clone();
+        self.push_prediction(to_surface, jacobian, pred_state_vec, pred_cov_mat, &material_state_vec);
+        Ok(())
+    }
+
+    /// Same as `predict`, but the propagation is linearized around `reference_state_vec` (a state on the
+    /// sensor the filter is currently on) instead of around the current estimate. Material effects are
+    /// also calculated from the reference. Used to refit a track around a previous solution.
+    /// The unscented propagator does not linearize, so it ignores the reference.
+    pub fn predict_linearized(&mut self, to_surface: &'a T, reference_state_vec: &Vector5<N>) -> Result<(), Error> {
+        let from_surface =
+            match (self.surface, self.options.propagator) {
+                (Some(from_surface), Propagator::Linear) => from_surface,
+                _ => return self.predict(to_surface)
+            };
+
+        let reference_pred = prediction::linear_extrapolation(from_surface, to_surface, reference_state_vec);
+        let jacobian = prediction::linear_extrapolation_jacobian(from_surface, to_surface, reference_state_vec);
+
+        let pred_state_vec = reference_pred + jacobian * (self.state_vec - reference_state_vec);
+        let pred_cov_mat = prediction::covariance_matrix(&jacobian, &self.cov_mat);
+
+        if !self.within_bounds(to_surface, &pred_state_vec, &pred_cov_mat) {
+            return Err(SensorError::OutsideSensorBounds.into())
+        }
+
+        self.push_prediction(to_surface, jacobian, pred_state_vec, pred_cov_mat, &reference_pred);
+        Ok(())
+    }
+
+    // add material effects and store the prediction as the current state
+    fn push_prediction(
+        &mut self,
+        to_surface: &'a T,
+        jacobian: Matrix5<N>,
+        pred_state_vec: Vector5<N>,
+        mut pred_cov_mat: Matrix5<N>,
+        material_state_vec: &Vector5<N>
+        ) {
+
+        if self.options.material_effects {
+            pred_cov_mat += prediction::multiple_scattering(material_state_vec,
+                                                            na::convert(self.options.radiation_length_fraction),
+                                                            na::convert(self.options.particle_hypothesis.mass()));
+        }
+
+        self.state_vec = pred_state_vec.clone();
+        self.cov_mat = pred_cov_mat.clone();
+        self.surface = Some(to_surface);
+
+        self.steps.push(FilterStep{jacobian: jacobian,
+                                   pred_state_vec: pred_state_vec.clone(),
+                                   pred_cov_mat: pred_cov_mat.clone(),
+                                   filt_state_vec: pred_state_vec,
+                                   filt_cov_mat: pred_cov_mat,
+                                   measurement: None,
+                                   chi_squared_inc: na::zero(),
+                                   outlier: false});
+    }
+
+    // bounds check of a prediction with the tolerance of the options
+    fn within_bounds(&self, surface: &T, pred_state_vec: &Vector5<N>, pred_cov_mat: &Matrix5<N>) -> bool {
+        let local_cov_mat = Matrix2::new(pred_cov_mat[(0, 0)], pred_cov_mat[(0, 1)],
+                                         pred_cov_mat[(1, 0)], pred_cov_mat[(1, 1)]);
+        surface.inside_with_tolerance(&Point2::new(pred_state_vec[0], pred_state_vec[1]), &local_cov_mat, &self.options.boundary_tolerance.convert())
+    }
+
+    // jacobian, predicted state and predicted covariance on `to_surface`
+    fn extrapolate(&self, from_surface: &T, to_surface: &T) -> Result<Prediction<N>, Error> {
+        match self.options.propagator {
+            Propagator::Linear => {
+                let jacobian = prediction::linear_extrapolation_jacobian(from_surface, to_surface, &self.state_vec);
+                let pred_cov_mat = prediction::covariance_matrix(&jacobian, &self.cov_mat);
+                let pred_state_vec = prediction::linear_state_vector(from_surface, to_surface, &self.state_vec,
+                                                                     &pred_cov_mat, &self.options.boundary_tolerance.convert())?;
+
+                Ok((jacobian, pred_state_vec, pred_cov_mat))
+            },
+            Propagator::Unscented => {
+                let (pred_state_vec, pred_cov_mat, cross_cov_mat) =
+                    unscented::unscented_prediction(from_surface, to_surface, &self.state_vec, &self.cov_mat, &UNSCENTED_SIGMA_POINTS)?;
+
+                if !self.within_bounds(to_surface, &pred_state_vec, &pred_cov_mat) {
+                    return Err(SensorError::OutsideSensorBounds.into())
+                }
+
+                // statistical linearization of the propagation so that smoothing can use the usual gain
+                let jacobian = match self.cov_mat.try_inverse() {
+                    Some(inverse) => cross_cov_mat.transpose() * inverse,
+                    None => return Err(MatrixError::NonInvertible.into())
+                };
+
+                Ok((jacobian, pred_state_vec, pred_cov_mat))
+            }
+        }
+    }
+
+    /// Adds a measurement on the sensor the filter was last predicted to. Returns the chi squared
+    /// increment of the measurement. A measurement above `FitterOptions::outlier_chi_squared` is
+    /// recorded as an outlier and does not change the state. A second measurement on the same
+    /// sensor replaces the first. Returns `InputError::NotPredicted` if `predict` was never called.
+    pub fn update(&mut self, measurement: &Vector2<N>, V: &Matrix2<N>) -> Result<N, Error> {
+        let meas_map_mat = sensor_mapping_mat();
+        let outlier_chi_squared : N = na::convert(self.options.outlier_chi_squared);
+        let covariance_update = self.options.covariance_update;
+
+        let step = match self.steps.last_mut() {
+            Some(step) => step,
+            None => return Err(InputError::NotPredicted.into())
+        };
+
+        let pred_residual_mat = prediction::residual_mat(V, &meas_map_mat, &step.pred_cov_mat);
+        let pred_residual_vec = prediction::residual_vec(measurement, &meas_map_mat, &step.pred_state_vec);
+
+        let inv_residual_mat = match pred_residual_mat.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(MatrixError::NonInvertible.into())
+        };
+
+        let chi_squared_inc = (pred_residual_vec.transpose() * inv_residual_mat * pred_residual_vec)[0];
+
+        // undo an earlier measurement on this sensor
+        if step.measurement.is_some() && !step.outlier {
+            self.chi_squared -= step.chi_squared_inc;
+            step.filt_state_vec = step.pred_state_vec.clone();
+            step.filt_cov_mat = step.pred_cov_mat.clone();
+            self.state_vec = step.pred_state_vec.clone();
+            self.cov_mat = step.pred_cov_mat.clone();
+        }
+
+        step.measurement = Some((measurement.clone(), V.clone()));
+        step.chi_squared_inc = chi_squared_inc;
+
+        if chi_squared_inc > outlier_chi_squared {
+            step.outlier = true;
+            return Ok(chi_squared_inc)
+        }
+
+        let kalman_gain = filter_gain::kalman_gain_inverted(&step.pred_cov_mat, &meas_map_mat, &inv_residual_mat);
+        let filter_state_vec = filter_gain::state_vector(&step.pred_state_vec, &kalman_gain, measurement, &meas_map_mat);
+        let filter_cov_mat =
+            match covariance_update {
+                CovarianceUpdate::Standard => filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, &step.pred_cov_mat),
+                CovarianceUpdate::Joseph => filter_gain::covariance_matrix_joseph(&kalman_gain, &meas_map_mat, &step.pred_cov_mat, V)
+            };
+
+        step.outlier = false;
+        step.filt_state_vec = filter_state_vec.clone();
+        step.filt_cov_mat = filter_cov_mat.clone();
+
+        self.state_vec = filter_state_vec;
+        self.cov_mat = filter_cov_mat;
+        self.chi_squared = filter_gain::update_chi_squared(self.chi_squared, chi_squared_inc);
+
+        Ok(chi_squared_inc)
+    }
+
+    /// Runs the smoother backwards over every sensor seen so far. Sensors without a
+    /// measurement get a zero residual. With `Smoother::Disabled` the filtered states are returned.
+    pub fn smooth(&self) -> Result<SmoothedData<N>, MatrixError> {
+        let input_length = self.steps.len();
+        let meas_map_mat = sensor_mapping_mat();
+
+        store_vec!{input_length;
+            smoothed_state_vec_iter: Vector5<N>,
+            smoothed_cov_mat_iter: Matrix5<N>,
+            smoothed_res_mat_iter: Matrix2<N>,
+            smoothed_res_vec_iter: Vector2<N>
+        }
+
+        if input_length == 0 {
+            return Ok(SmoothedData::new(smoothed_state_vec_iter, smoothed_cov_mat_iter,
+                                        smoothed_res_mat_iter, smoothed_res_vec_iter))
+        }
+
+        let last = &self.steps[input_length - 1];
+        push!{
+            last.filt_state_vec.clone() => smoothed_state_vec_iter,
+            last.filt_cov_mat.clone() => smoothed_cov_mat_iter
+        }
+
+        for i in (0..input_length - 1).rev() {
+            let curr = &self.steps[i];
+
+            if self.options.smoother == Smoother::Disabled {
+                push!{
+                    curr.filt_state_vec.clone() => smoothed_state_vec_iter,
+                    curr.filt_cov_mat.clone() => smoothed_cov_mat_iter
+                }
+                continue
+            }
+
+            let next = &self.steps[i + 1];
+            let next_smth_state_vec = smoothed_state_vec_iter.last().unwrap();
+            let next_smth_cov_mat = smoothed_cov_mat_iter.last().unwrap();
+
+            let inv_pred_cov_mat = match next.pred_cov_mat.try_inverse() {
+                Some(inverse) => inverse,
+                None => return Err(MatrixError::NonInvertible)
+            };
+
+            let gain_matrix = smoothing::gain_matrix_inverted(&curr.filt_cov_mat, &next.jacobian, &inv_pred_cov_mat);
+            let smoothed_state_vec = smoothing::state_vector(&curr.filt_state_vec, &gain_matrix, next_smth_state_vec, &next.pred_state_vec);
+            let smoothed_cov_mat = smoothing::covariance_matrix(&curr.filt_cov_mat, &gain_matrix, &next.pred_cov_mat, next_smth_cov_mat);
+
+            push!{
+                smoothed_state_vec => smoothed_state_vec_iter,
+                smoothed_cov_mat => smoothed_cov_mat_iter
+            }
+        }
+
+        smoothed_state_vec_iter.reverse();
+        smoothed_cov_mat_iter.reverse();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let (smoothed_res_mat, smoothed_res_vec) =
+                match step.measurement {
+                    Some((ref m_k, ref curr_v)) => {
+                        (smoothing::residual_mat(curr_v, &meas_map_mat, &smoothed_cov_mat_iter[i]),
+                         smoothing::residual_vec(m_k, &meas_map_mat, &smoothed_state_vec_iter[i]))
+                    },
+                    None => (Matrix2::zeros(), Vector2::zeros())
+                };
+
+            push!{
+                smoothed_res_mat => smoothed_res_mat_iter,
+                smoothed_res_vec => smoothed_res_vec_iter
+            }
+        }
+
+        Ok(SmoothedData::new(smoothed_state_vec_iter,
+                             smoothed_cov_mat_iter,
+                             smoothed_res_mat_iter,
+                             smoothed_res_vec_iter))
+    }
+
+    /// Current state vector: filtered if the last sensor had a measurement, predicted otherwise
+    pub fn state_vec(&self) -> &Vector5<N> {
+        &self.state_vec
+    }
+
+    pub fn cov_mat(&self) -> &Matrix5<N> {
+        &self.cov_mat
+    }
+
+    /// Total chi squared of every measurement added so far
+    pub fn chi_squared(&self) -> N {
+        self.chi_squared
+    }
+
+    pub fn options(&self) -> &FitterOptions {
+        &self.options
+    }
+
+    /// Intermediate results of every sensor seen so far
+    pub fn steps(&self) -> &Vec<FilterStep<N>> {
+        &self.steps
+    }
+}
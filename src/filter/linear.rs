@@ -1,4 +1,5 @@
 use nalgebra as na;
+use na::{Matrix2, Matrix5, Matrix2x5, Vector2, Vector5};
 use super::super::config::*;
 use super::prediction;
 use super::filter_gain;
@@ -10,175 +11,165 @@ use super::super::geometry::Rectangle;
 use super::super::geometry::traits::{Plane, Transform};
 
 use super::super::error::*;
-use super::utils::SmoothedData;
+use super::utils::{SmoothedData, sensor_mapping_mat};
+use super::workspace::FitWorkspace;
 
 #[macro_use]
 use super::macros;
 
-/// Monolithic function to handle linear KF calculations
+/// Monolithic function to handle linear KF calculations. The seed is taken as the state on the
+/// first sensor and is extrapolated in a straight line onto each following sensor. The smoothed
+/// values are returned in the order of the sensors, starting with the first one crossed.
+/// Returns `MatrixError::NonInvertible` if a residual or predicted covariance can not be inverted.
 #[allow(dead_code)] 
 pub fn run(
     measurement_noise_coarariance_vector: &Vec<Mat2>,  // vector of V from fruhwirth paper
     measurements_vector: &Vec<Vec2>,            // vector of all the measurements that were registered
     sensor_vector: &Vec<Rectangle>,             // the geometric sensors that correspond to each hit 
-    )  -> SmoothedData{
+    )  -> Result<SmoothedData, Error> {
 
-    let meas_map_mat = Mat2x5::new(1.0, 0. , 0. , 0. , 0. ,
-                                   0. , 0. , 0. , 0. , 0. );
-    
     if (measurement_noise_coarariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
     else {
         panic!("vector lengths need to be the same length")
     }
-    let input_length = measurements_vector.len();
 
+    // calculate some seeded values (seeding improvement suggestions welcome)
+    let seed_state_vec = super::utils::seed_state_vec();
+    let seed_covariance = super::utils::seed_covariance();
 
-    store_vec!{
-        input_length; // since we have n sensors, we should have n filtered values
-        
-        jacobian_iter: Mat5,
+    let mut workspace = FitWorkspace::with_capacity(measurements_vector.len());
+    fit(&mut workspace, &seed_state_vec, &seed_covariance, measurement_noise_coarariance_vector, measurements_vector, sensor_vector)?;
 
-        // storage for filtered values
-        filter_res_mat_iter: Mat2,
-        filter_res_vec_iter: Vec2,
-        chi_squared_iter: Real,
+    // put all data into a struct that will contain all the methods to return 
+    // the data back to c++
+    Ok(workspace.smoothed_data())
+}
 
-        // storage for smoothed values
-        smoothed_state_vec_iter : Vec5,
-        smoothed_cov_mat_iter : Mat5,
-        smoothed_res_mat_iter : Mat2,
-        smoothed_res_vec_iter: Vec2
-    }
-    store_vec!{input_length+1; // these vectors require initial seeded values so we initialize to len+1
-        filter_state_vec_iter: Vec5,
-        filter_cov_mat_iter: Mat5
+/// Same calculation as `run` with a given seed, writing every intermediate and final value into
+/// `workspace` instead of allocating. Reusing one workspace for many tracks keeps the hot loop
+/// free of heap allocations. Results are read back with `workspace.state_vec()` etc.
+/// On error the workspace does not hold a complete fit.
+pub fn run_in<N: na::Real, T: Transform<N> + Plane<N>>(
+    workspace: &mut FitWorkspace<N>,
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &[Matrix2<N>],    // V
+    measurements_vector: &[Vector2<N>],                    // m_k
+    sensor_vector: &[T]
+    ) -> Result<(), Error> {
+
+    if (measurement_noise_covariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
+    else {
+        panic!("vector lengths need to be the same length")
     }
 
-    // calculate some seeded values (seeding improvement suggestions welcome)
-    let mut previous_state_vec = super::utils::seed_state_vec();
-    let mut previous_covariance = super::utils::seed_covariance();
+    fit(workspace, seed_state_vec, seed_cov_mat, measurement_noise_covariance_vector, measurements_vector, sensor_vector)
+}
 
-    // Store the seeded values in their respective iterators
-    push!(
-        previous_covariance => filter_cov_mat_iter, 
-        previous_state_vec => filter_state_vec_iter
-    );
+fn fit<N: na::Real, T: Transform<N> + Plane<N>>(
+    workspace: &mut FitWorkspace<N>,
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &[Matrix2<N>],
+    measurements_vector: &[Vector2<N>],
+    sensor_vector: &[T]
+    ) -> Result<(), Error> {
 
+    let meas_map_mat : &Matrix2x5<N> = &sensor_mapping_mat();
 
-    for i in 0..input_length{
-        let jacobian = linear_jacobian();
+    let input_length = measurements_vector.len();
+    workspace.reset(input_length);
+
+    if input_length == 0 {
+        return Ok(())
+    }
+
+    let mut previous_state_vec = *seed_state_vec;
+    let mut previous_covariance = *seed_cov_mat;
 
-        // fetch the next values of H / V / m_k
+    for i in 0..input_length{
+        // fetch the next values of V / m_k
         get_unchecked!{i;
-            measurement_noise_coarariance_vector => curr_v,
-            measurements_vector=> curr_m_k 
+            measurement_noise_covariance_vector => curr_v,
+            measurements_vector => curr_m_k
         }
 
-        //predictions
-        let pred_state_vec = prediction::state_vector(&jacobian, &previous_state_vec);
+        //predictions: the seed already sits on the first sensor
+        let (jacobian, pred_state_vec) =
+            if i == 0 {
+                (linear_jacobian(), previous_state_vec)
+            }
+            else {
+                (prediction::linear_extrapolation_jacobian(&sensor_vector[i - 1], &sensor_vector[i], &previous_state_vec),
+                 prediction::linear_extrapolation(&sensor_vector[i - 1], &sensor_vector[i], &previous_state_vec))
+            };
         let pred_cov_mat = prediction::covariance_matrix(&jacobian, &previous_covariance);
-        let pred_residual_mat = prediction::residual_mat(curr_v, &meas_map_mat, &pred_cov_mat);
-        let pred_residual_vec = prediction::residual_vec(&curr_m_k, &meas_map_mat, &pred_state_vec);
+        let pred_residual_vec = prediction::residual_vec(curr_m_k, meas_map_mat, &pred_state_vec);
+
+        let inv_residual_mat = invert(&prediction::residual_mat(curr_v, meas_map_mat, &pred_cov_mat))?;
 
-      
         //filtering
-        let kalman_gain = filter_gain::kalman_gain(&pred_cov_mat, &meas_map_mat, curr_v);
-        let filter_state_vec = filter_gain::state_vector(&pred_state_vec, &kalman_gain, curr_m_k, &meas_map_mat);
-        let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, &pred_cov_mat);
-        let filter_residual_vec = filter_gain::residual_vec(&meas_map_mat, &kalman_gain, &pred_residual_vec);
-        let filter_residual_mat = filter_gain::residual_mat(curr_v, &meas_map_mat, &filter_cov_mat);
-        let chi_squared_inc = filter_gain::chi_squared_increment(&filter_residual_vec, &filter_residual_mat);
-
-        // store all the filtered values in their respective iterators
-        push!{
-            filter_state_vec =>filter_state_vec_iter,
-            jacobian => jacobian_iter,
-            filter_cov_mat => filter_cov_mat_iter,
-            filter_residual_mat => filter_res_mat_iter,
-            filter_residual_vec => filter_res_vec_iter,
-            chi_squared_inc => chi_squared_iter
-        }
-        
+        let kalman_gain = filter_gain::kalman_gain_inverted(&pred_cov_mat, meas_map_mat, &inv_residual_mat);
+        let filter_state_vec = filter_gain::state_vector(&pred_state_vec, &kalman_gain, curr_m_k, meas_map_mat);
+        let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, meas_map_mat, &pred_cov_mat);
+        let filter_residual_vec = filter_gain::residual_vec(meas_map_mat, &kalman_gain, &pred_residual_vec);
+        let filter_residual_mat = filter_gain::residual_mat(curr_v, meas_map_mat, &filter_cov_mat);
+        let chi_squared_inc = (filter_residual_vec.transpose() * invert(&filter_residual_mat)? * filter_residual_vec)[0];
+
+        // store all the filtered values in the workspace
+        workspace.jacobian.push(jacobian);
+        workspace.pred_state_vec.push(pred_state_vec);
+        workspace.pred_cov_mat.push(pred_cov_mat);
+        workspace.filt_state_vec.push(filter_state_vec);
+        workspace.filt_cov_mat.push(filter_cov_mat);
+        workspace.chi_squared_inc.push(chi_squared_inc);
+
         // store current filtered values as the "previous" to be used in the
         // prediction calculations in the next iteration
         previous_covariance = filter_cov_mat;
         previous_state_vec = filter_state_vec;
     }
 
-    // remove the last value from the filter vector and push it to the smoothed version
-    push!{remove: input_length-1;
-        filter_state_vec_iter => smoothed_state_vec_iter,
-        filter_cov_mat_iter => smoothed_cov_mat_iter,
-        filter_res_mat_iter => smoothed_res_mat_iter,
-        filter_res_vec_iter => smoothed_res_vec_iter
-    }
+    // smoothed values are written in place from the last sensor to the first, so nothing
+    // needs to be moved or reversed afterwards
+    workspace.smth_state_vec.resize(input_length, Vector5::zeros());
+    workspace.smth_cov_mat.resize(input_length, Matrix5::zeros());
 
+    workspace.smth_state_vec[input_length - 1] = workspace.filt_state_vec[input_length - 1];
+    workspace.smth_cov_mat[input_length - 1] = workspace.filt_cov_mat[input_length - 1];
 
     for i in (0..input_length-1).rev(){
-        
-        //
-        // initializing variables
-        // 
-        
-        // fetch the current variables 
-        get_unchecked!{i;
-            filter_state_vec_iter => curr_filt_state_vec,
-            filter_cov_mat_iter => curr_filt_cov_mat,
-            measurement_noise_coarariance_vector => curr_v,
-            measurements_vector =>curr_measurement,
-            jacobian_iter => curr_jacobian
-        }
-
-        // since we move backwards, previous variables are at i+1
-        get_unchecked!{i+1;
-            filter_state_vec_iter => prev_filt_state_vec,
-            filter_cov_mat_iter => prev_filt_cov_mat
-        }
+        let inv_pred_cov_mat = invert(&workspace.pred_cov_mat[i + 1])?;
+        let gain_matrix = smoothing::gain_matrix_inverted(&workspace.filt_cov_mat[i], &workspace.jacobian[i + 1], &inv_pred_cov_mat);
+        let smoothed_state_vec = smoothing::state_vector(&workspace.filt_state_vec[i], &gain_matrix, &workspace.smth_state_vec[i + 1], &workspace.pred_state_vec[i + 1]);
+        let smoothed_cov_mat = smoothing::covariance_matrix(&workspace.filt_cov_mat[i], &gain_matrix, &workspace.pred_cov_mat[i + 1], &workspace.smth_cov_mat[i + 1]);
 
-        // grab variables pushed in the last iteration
-        // (i+2) since input_length is based on the function argument lengths
-        // and we also .remove() one value from each vec
-        get_unchecked!{input_length -(i+2);                         //TODO: double check this indexing
-            smoothed_state_vec_iter => prev_smth_state_vec,
-            smoothed_cov_mat_iter => prev_smth_cov_mat
-        }
+        workspace.smth_state_vec[i] = smoothed_state_vec;
+        workspace.smth_cov_mat[i] = smoothed_cov_mat;
+    }
 
-        // 
-        // smoothing calculations
-        //
-
-        // NOTE: the next calculations assume that x^n references the next state vector and x^k references the previous 
-        // state vector. I am uncertain as to what the actual answer is as andi still has not gotten back to me about it.
-        let gain_matrix = smoothing::gain_matrix(curr_filt_cov_mat, curr_jacobian, prev_filt_cov_mat); 
-        let smoothed_state_vec = smoothing::state_vector(curr_filt_state_vec, &gain_matrix, prev_smth_state_vec, prev_filt_state_vec);
-        let smoothed_cov_mat = smoothing::covariance_matrix(curr_filt_cov_mat, &gain_matrix, prev_filt_cov_mat, prev_smth_cov_mat);
-        let smoothed_res_mat = smoothing::residual_mat(curr_v, &meas_map_mat, &smoothed_cov_mat);
-        let smoothed_res_vec = smoothing::residual_vec(curr_measurement, &meas_map_mat, &smoothed_state_vec);
-
-        //
-        //  group push variables to vectors
-        //
-        push!{
-            smoothed_state_vec => smoothed_state_vec_iter,
-            smoothed_cov_mat => smoothed_cov_mat_iter,
-            smoothed_res_mat => smoothed_res_mat_iter,
-            smoothed_res_vec => smoothed_res_vec_iter
-        }
+    for i in 0..input_length {
+        let smoothed_res_mat = smoothing::residual_mat(&measurement_noise_covariance_vector[i], meas_map_mat, &workspace.smth_cov_mat[i]);
+        let smoothed_res_vec = smoothing::residual_vec(&measurements_vector[i], meas_map_mat, &workspace.smth_state_vec[i]);
 
+        workspace.smth_res_mat.push(smoothed_res_mat);
+        workspace.smth_res_vec.push(smoothed_res_vec);
     }
-    
-    // put all data into a struct that will contain all the methods to return 
-    // the data back to c++
-    return SmoothedData::new(smoothed_state_vec_iter,
-                                smoothed_cov_mat_iter,
-                                smoothed_res_mat_iter,
-                                smoothed_res_vec_iter)
 
-    // 
-    // unimplemented!()
+    Ok(())
+}
+
+// inverse of a square matrix, or the error that `run` reports for a degenerate track
+fn invert<N: na::Real, D: na::DimName>(matrix: &na::MatrixN<N, D>) -> Result<na::MatrixN<N, D>, MatrixError>
+    where na::DefaultAllocator: na::allocator::Allocator<N, D, D> {
+
+    match matrix.clone().try_inverse() {
+        Some(inverse) => Ok(inverse),
+        None => Err(MatrixError::NonInvertible)
+    }
 }
 
 // TODO: figure out partial derivatives for jacobian calculation
-fn linear_jacobian() -> Mat5 {
-    return Mat5::identity()
+pub fn linear_jacobian<N: na::Real>() -> Matrix5<N> {
+    return Matrix5::identity()
 }
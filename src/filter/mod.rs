@@ -2,7 +2,23 @@
 pub mod macros;
 
 pub mod linear;
+pub mod kalman;
+pub mod iterated;
+pub mod batch;
+pub mod bundle;
+pub mod combinatorial;
+pub mod ambiguity;
+pub mod annealing;
+pub mod gaussian_sum;
+pub mod extended;
+pub mod unscented;
+pub mod timing;
+pub mod parameters;
+pub mod perigee;
+pub mod propagation;
+pub mod drift;
 pub mod utils;
+pub mod workspace;
 
 pub mod prediction;
 pub mod filter_gain;
@@ -0,0 +1,272 @@
+use nalgebra as na;
+use na::{U1, U3};
+use super::super::config::*;
+
+use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::intersection;
+use super::super::geometry::utils;
+
+// Three ways of describing the same track state:
+//
+// bound:       (loc0, loc1, theta, phi, q/p) in the local frame of a sensor
+// curvilinear: bound parameters in a frame perpendicular to the track direction
+// free:        (x, y, z, tx, ty, tz, q/p) with global position and unit direction
+//
+// The direction is (cos(phi)cos(theta), cos(phi)sin(theta), sin(phi)) as in `prediction::linear_extrapolation`.
+
+/// Parameters bound to the local frame of a sensor
+#[derive(Debug, Clone)]
+pub struct BoundParameters {
+    pub state_vec: Vec5,
+    pub cov_mat: Mat5
+}
+
+/// Bound parameters in the curvilinear frame at `position`
+#[derive(Debug, Clone)]
+pub struct CurvilinearParameters {
+    pub position: P3,
+    pub state_vec: Vec5,
+    pub cov_mat: Mat5
+}
+
+/// Parameters in global coordinates
+#[derive(Debug, Clone)]
+pub struct FreeParameters {
+    pub state_vec: Vec7,
+    pub cov_mat: Mat7
+}
+
+impl BoundParameters {
+    pub fn new(state_vec: Vec5, cov_mat: Mat5) -> Self {
+        BoundParameters{state_vec: state_vec, cov_mat: cov_mat}
+    }
+
+    pub fn to_free<T: Transform>(&self, surface: &T) -> FreeParameters {
+        let position = surface.to_global(P3::new(self.state_vec[0], self.state_vec[1], 0.));
+        let direction = direction(self.state_vec[2], self.state_vec[3]);
+
+        let state_vec = Vec7::from_column_slice(&[position.x, position.y, position.z,
+                                                  direction.x, direction.y, direction.z,
+                                                  self.state_vec[4]]);
+
+        let jacobian = bound_to_free_jacobian(surface, &self.state_vec);
+
+        FreeParameters{state_vec: state_vec,
+                       cov_mat: jacobian * self.cov_mat * jacobian.transpose()}
+    }
+
+    pub fn to_curvilinear<T: Transform>(&self, surface: &T) -> CurvilinearParameters {
+        self.to_free(surface).to_curvilinear()
+    }
+}
+
+impl CurvilinearParameters {
+    /// Frame the parameters are bound to
+    pub fn frame(&self) -> CurvilinearFrame {
+        let direction = direction(self.state_vec[2], self.state_vec[3]);
+        CurvilinearFrame::new(&self.position, &direction)
+    }
+
+    pub fn to_free(&self) -> FreeParameters {
+        BoundParameters::new(self.state_vec, self.cov_mat).to_free(&self.frame())
+    }
+
+    pub fn to_bound<T: Transform + Plane>(&self, surface: &T) -> BoundParameters {
+        self.to_free().to_bound(surface)
+    }
+}
+
+impl FreeParameters {
+    pub fn new(state_vec: Vec7, cov_mat: Mat7) -> Self {
+        FreeParameters{state_vec: state_vec, cov_mat: cov_mat}
+    }
+
+    pub fn position(&self) -> P3 {
+        P3::new(self.state_vec[0], self.state_vec[1], self.state_vec[2])
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        Vec3::new(self.state_vec[3], self.state_vec[4], self.state_vec[5])
+    }
+
+    pub fn q_over_p(&self) -> Real {
+        self.state_vec[6]
+    }
+
+    /// Parameters on `surface`. The position is moved onto the surface along the direction first,
+    /// so it does not need to lie on it exactly.
+    pub fn to_bound<T: Transform + Plane>(&self, surface: &T) -> BoundParameters {
+        let position = self.position();
+        let direction = self.direction().normalize();
+
+        let global_point = match intersection::plane_intersection(surface, &position, &direction) {
+            Some(crossing) => crossing.point,
+            // parallel to the surface: project the position as it is
+            None => position
+        };
+        let local_point = surface.to_local(global_point);
+
+        let (theta, phi) = angles(&direction);
+        let state_vec = Vec5::new(local_point.x, local_point.y, theta, phi, self.q_over_p());
+
+        let jacobian = free_to_bound_jacobian(surface, &self.state_vec);
+
+        BoundParameters{state_vec: state_vec,
+                        cov_mat: jacobian * self.cov_mat * jacobian.transpose()}
+    }
+
+    pub fn to_curvilinear(&self) -> CurvilinearParameters {
+        let position = self.position();
+        let frame = CurvilinearFrame::new(&position, &self.direction().normalize());
+        let bound = self.to_bound(&frame);
+
+        CurvilinearParameters{position: position,
+                              state_vec: bound.state_vec,
+                              cov_mat: bound.cov_mat}
+    }
+}
+
+/// Plane through a point perpendicular to the track direction. The local x axis is perpendicular to
+/// both the direction and the global z axis (or the x axis for tracks along z).
+#[derive(Debug, Clone)]
+pub struct CurvilinearFrame {
+    normal: Vec3,
+    plane_constant: Real,
+    to_global: Aff3,
+    to_local: Aff3
+}
+
+impl CurvilinearFrame {
+    pub fn new(position: &P3, direction: &Vec3) -> Self {
+        let reference = if direction.z.abs() < 0.99 {Vec3::z()} else {Vec3::x()};
+        let axis_u = reference.cross(direction).normalize();
+        let axis_v = direction.cross(&axis_u);
+
+        let to_global = Aff3::from_matrix_unchecked(
+            Mat4::new(axis_u.x, axis_v.x, direction.x, position.x,
+                      axis_u.y, axis_v.y, direction.y, position.y,
+                      axis_u.z, axis_v.z, direction.z, position.z,
+                      0.,       0.,       0.,          1.));
+
+        CurvilinearFrame{normal: direction.clone(),
+                         plane_constant: -direction.dot(&position.coords),
+                         to_global: to_global,
+                         to_local: to_global.inverse()}
+    }
+}
+
+impl Transform for CurvilinearFrame {
+    fn to_global(&self, input_point: P3) -> P3 {
+        self.to_global * input_point
+    }
+
+    fn to_local(&self, input_point: P3) -> P2 {
+        let local = self.to_local * input_point;
+        P2::new(local.x, local.y)
+    }
+
+    // the frame is unbounded
+    fn inside(&self, _input: &P2) -> bool {
+        true
+    }
+
+    fn boundary_distance(&self, _input: &P2) -> Real {
+        Real::NEG_INFINITY
+    }
+}
+
+impl Plane for CurvilinearFrame {
+    fn on_plane(&self, input_point: &P3) -> bool {
+        let origin = self.to_global(P3::origin());
+        self.normal.dot(&(input_point - origin)).abs() <= DOT_PRODUCT_EPSILON
+    }
+
+    fn plane_normal_vec(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    fn plane_constant(&self) -> Real {
+        self.plane_constant
+    }
+}
+
+/// Unit direction vector of the angles theta and phi
+pub fn direction(theta: Real, phi: Real) -> Vec3 {
+    Vec3::new(phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin())
+}
+
+/// Angles (theta, phi) of a unit direction vector
+pub fn angles(direction: &Vec3) -> (Real, Real) {
+    let transverse = (direction.x * direction.x + direction.y * direction.y).sqrt();
+    (direction.y.atan2(direction.x), direction.z.atan2(transverse))
+}
+
+/// Jacobian of the free parameters with respect to the bound parameters on `surface`
+pub fn bound_to_free_jacobian<T: Transform>(
+    surface: &T,
+    bound_state_vec: &Vec5
+    ) -> Mat7x5 {
+
+    let (_, axis_0, axis_1) = utils::local_axes(|point| surface.to_global(point));
+
+    let theta = bound_state_vec[2];
+    let phi = bound_state_vec[3];
+    let d_direction_d_theta = Vec3::new(-phi.cos() * theta.sin(), phi.cos() * theta.cos(), 0.);
+    let d_direction_d_phi = Vec3::new(-phi.sin() * theta.cos(), -phi.sin() * theta.sin(), phi.cos());
+
+    let mut jacobian = Mat7x5::zeros();
+    jacobian.fixed_slice_mut::<U3, U1>(0, 0).copy_from(&axis_0);
+    jacobian.fixed_slice_mut::<U3, U1>(0, 1).copy_from(&axis_1);
+    jacobian.fixed_slice_mut::<U3, U1>(3, 2).copy_from(&d_direction_d_theta);
+    jacobian.fixed_slice_mut::<U3, U1>(3, 3).copy_from(&d_direction_d_phi);
+    jacobian[(6, 4)] = 1.;
+
+    jacobian
+}
+
+/// Jacobian of the bound parameters on `surface` with respect to the free parameters. The direction
+/// is expected to be a unit vector. A change of position is moved back onto the surface along the
+/// direction, which is why the jacobian depends on the orientation of the track relative to the surface.
+pub fn free_to_bound_jacobian<T: Transform>(
+    surface: &T,
+    free_state_vec: &Vec7
+    ) -> Mat5x7 {
+
+    let position = P3::new(free_state_vec[0], free_state_vec[1], free_state_vec[2]);
+    let direction = Vec3::new(free_state_vec[3], free_state_vec[4], free_state_vec[5]);
+
+    let (origin, normal, _) = utils::plane_from_transform(|point| surface.to_global(point));
+    let path_length = normal.dot(&(origin - position)) / normal.dot(&direction);
+
+    // d (point on surface) / d position
+    let projection = Mat3::identity() - direction * normal.transpose() / normal.dot(&direction);
+
+    // linear part of `to_local`
+    let local_origin = surface.to_local(P3::origin());
+    let to_local_linear = |vector: Vec3| surface.to_local(P3::new(vector.x, vector.y, vector.z)) - local_origin;
+
+    let mut jacobian = Mat5x7::zeros();
+    for i in 0..3 {
+        let d_local_d_position = to_local_linear(projection.column(i).into_owned());
+        jacobian[(0, i)] = d_local_d_position.x;
+        jacobian[(1, i)] = d_local_d_position.y;
+
+        // the point on the surface also moves with the direction when the position is off the surface
+        jacobian[(0, 3 + i)] = d_local_d_position.x * path_length;
+        jacobian[(1, 3 + i)] = d_local_d_position.y * path_length;
+    }
+
+    let transverse_squared = direction.x * direction.x + direction.y * direction.y;
+    let transverse = transverse_squared.sqrt();
+
+    jacobian[(2, 3)] = -direction.y / transverse_squared;
+    jacobian[(2, 4)] = direction.x / transverse_squared;
+
+    jacobian[(3, 3)] = -direction.z * direction.x / transverse;
+    jacobian[(3, 4)] = -direction.z * direction.y / transverse;
+    jacobian[(3, 5)] = transverse;
+
+    jacobian[(4, 6)] = 1.;
+
+    jacobian
+}
@@ -0,0 +1,181 @@
+use std::f64::consts::PI;
+
+use super::super::config::*;
+use super::parameters::{self, BoundParameters};
+use super::prediction::JACOBIAN_STEP;
+
+use super::super::geometry::PerigeeSurface;
+use super::super::geometry::traits::Transform;
+
+// Perigee parameters follow the usual convention:
+// (d0, z0, phi, theta, q/p)
+// where d0 is the signed transverse distance to the beam line, z0 the longitudinal position of the
+// point of closest approach, phi the azimuthal angle and theta the polar angle of the momentum there.
+// Note that phi and theta swap meaning compared with the sensor bound parameters.
+
+/// Track model used to reach the beam line
+#[derive(Debug, Clone, Copy)]
+pub enum PerigeePropagation {
+    StraightLine,
+    /// Helix in a solenoid field along z of `b_field` Tesla
+    Helix{b_field: Real}
+}
+
+/// Track parameters at the point of closest approach to the beam line
+#[derive(Debug, Clone)]
+pub struct PerigeeParameters {
+    pub state_vec: Vec5,
+    pub cov_mat: Mat5
+}
+
+impl PerigeeParameters {
+    pub fn d0(&self) -> Real {
+        self.state_vec[0]
+    }
+
+    pub fn z0(&self) -> Real {
+        self.state_vec[1]
+    }
+}
+
+/// Extrapolates parameters bound to `sensor` (usually the smoothed state on the first sensor) to the
+/// perigee. The covariance is propagated with a jacobian found by central differences.
+pub fn extrapolate<T: Transform>(
+    sensor: &T,
+    bound: &BoundParameters,
+    perigee: &PerigeeSurface,
+    propagation: &PerigeePropagation
+    ) -> PerigeeParameters {
+
+    let state_vec = perigee_state_vec(sensor, &bound.state_vec, perigee, propagation);
+
+    let mut jacobian = Mat5::zeros();
+    for j in 0..5 {
+        let step = JACOBIAN_STEP * bound.state_vec[j].abs().max(1.);
+
+        let mut forward = bound.state_vec.clone();
+        forward[j] += step;
+        let mut backward = bound.state_vec.clone();
+        backward[j] -= step;
+
+        let mut column = (perigee_state_vec(sensor, &forward, perigee, propagation) - perigee_state_vec(sensor, &backward, perigee, propagation)) / (2. * step);
+        column[2] = wrap_angle(column[2] * 2. * step) / (2. * step);
+        jacobian.set_column(j, &column);
+    }
+
+    PerigeeParameters{state_vec: state_vec,
+                      cov_mat: jacobian * bound.cov_mat * jacobian.transpose()}
+}
+
+/// Perigee parameters of a state bound to `sensor`
+pub fn perigee_state_vec<T: Transform>(
+    sensor: &T,
+    bound_state_vec: &Vec5,
+    perigee: &PerigeeSurface,
+    propagation: &PerigeePropagation
+    ) -> Vec5 {
+
+    let position = sensor.to_global(P3::new(bound_state_vec[0], bound_state_vec[1], 0.));
+    let direction = parameters::direction(bound_state_vec[2], bound_state_vec[3]);
+
+    global_perigee_state_vec(&position, &direction, bound_state_vec[4], perigee, propagation)
+}
+
+/// Perigee parameters of a track passing through the global `position` with `direction`
+pub fn global_perigee_state_vec(
+    position: &P3,
+    direction: &Vec3,
+    q_over_p: Real,
+    perigee: &PerigeeSurface,
+    propagation: &PerigeePropagation
+    ) -> Vec5 {
+
+    let beam = &perigee.beam_position;
+
+    let (closest_point, closest_direction) =
+        match *propagation {
+            PerigeePropagation::Helix{b_field} if q_over_p != 0. && b_field != 0. => {
+                helix_closest_approach(position, direction, q_over_p, b_field, beam)
+            },
+            _ => straight_line_closest_approach(position, direction, beam)
+        };
+
+    let transverse = (closest_direction.x * closest_direction.x + closest_direction.y * closest_direction.y).sqrt();
+    let phi = closest_direction.y.atan2(closest_direction.x);
+    let theta = transverse.atan2(closest_direction.z);
+
+    let d0 = (closest_point.y - beam.y) * phi.cos() - (closest_point.x - beam.x) * phi.sin();
+    let z0 = closest_point.z - beam.z;
+
+    Vec5::new(d0, z0, phi, theta, q_over_p)
+}
+
+/// Point and direction of a straight line where it passes closest to the beam line
+pub fn straight_line_closest_approach(
+    position: &P3,
+    direction: &Vec3,
+    beam: &P3
+    ) -> (P3, Vec3) {
+
+    let transverse_squared = direction.x * direction.x + direction.y * direction.y;
+    let path_length = -((position.x - beam.x) * direction.x + (position.y - beam.y) * direction.y) / transverse_squared;
+
+    (position + direction * path_length, direction.clone())
+}
+
+/// Point and direction of a helix in a field along z where it passes closest to the beam line. The
+/// helix is followed forwards or backwards, whichever reaches the beam line with the smaller turn.
+pub fn helix_closest_approach(
+    position: &P3,
+    direction: &Vec3,
+    q_over_p: Real,
+    b_field: Real,
+    beam: &P3
+    ) -> (P3, Vec3) {
+
+    let transverse = (direction.x * direction.x + direction.y * direction.y).sqrt();
+    let transverse_momentum = transverse / q_over_p.abs();
+    let radius = transverse_momentum / (B_FIELD_CONSTANT * b_field.abs());
+
+    // +1 when the particle turns counter clockwise seen from +z
+    let rotation = -(q_over_p.signum() * b_field.signum());
+
+    let unit_x = direction.x / transverse;
+    let unit_y = direction.y / transverse;
+    let center_x = position.x - rotation * radius * unit_y;
+    let center_y = position.y + rotation * radius * unit_x;
+
+    let arm_x = position.x - center_x;
+    let arm_y = position.y - center_y;
+    let to_beam_x = beam.x - center_x;
+    let to_beam_y = beam.y - center_y;
+    let to_beam = (to_beam_x * to_beam_x + to_beam_y * to_beam_y).sqrt();
+
+    // counter clockwise angle from the current position to the closest point, seen from the center
+    let turn = (arm_x * to_beam_y - arm_y * to_beam_x).atan2(arm_x * to_beam_x + arm_y * to_beam_y);
+
+    // signed transverse path length, negative when the closest point lies behind the track
+    let transverse_path = rotation * turn * radius;
+
+    let closest_point = P3::new(center_x + radius * to_beam_x / to_beam,
+                                center_y + radius * to_beam_y / to_beam,
+                                position.z + transverse_path * direction.z / transverse);
+
+    let closest_direction = Vec3::new((unit_x * turn.cos() - unit_y * turn.sin()) * transverse,
+                                      (unit_x * turn.sin() + unit_y * turn.cos()) * transverse,
+                                      direction.z);
+
+    (closest_point, closest_direction)
+}
+
+/// Maps an angle difference into (-pi, pi]
+pub fn wrap_angle(angle: Real) -> Real {
+    let mut wrapped = angle % (2. * PI);
+    if wrapped > PI {
+        wrapped -= 2. * PI;
+    }
+    else if wrapped <= -PI {
+        wrapped += 2. * PI;
+    }
+    wrapped
+}
@@ -1,42 +1,45 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Matrix2x5, Vector2, Vector3, Vector5, Point2, Point3, U2};
 use super::super::config::*;
 use super::super::error::*;
 use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::intersection;
 
 // extrapolating state vector
 // NOTE: this can only be used for linear systems
-pub fn state_vector (
-    jacobian: &Mat5,               // J or F_k-1
-    prev_filt_state_vec: &Vec5     // prev filt x
-    ) -> Vec5 {                    // pred x
+pub fn state_vector<N: na::Real>(
+    jacobian: &Matrix5<N>,               // J or F_k-1
+    prev_filt_state_vec: &Vector5<N>     // prev filt x
+    ) -> Vector5<N> {                    // pred x
 
     return jacobian * prev_filt_state_vec
 }
 
 // prediction of covariance matrix C
-pub fn covariance_matrix(
-    jacobian: &Mat5,                    // J or F_k-1
-    prev_filt_covariance_mat: &Mat5     // prev filt C
-    )-> Mat5{                           // pred C
+pub fn covariance_matrix<N: na::Real>(
+    jacobian: &Matrix5<N>,                    // J or F_k-1
+    prev_filt_covariance_mat: &Matrix5<N>     // prev filt C
+    )-> Matrix5<N>{                           // pred C
 
     return jacobian * prev_filt_covariance_mat * jacobian.transpose()
 }
 
 // just below eq. 7
 // residual covariance of predicted results
-pub fn residual_mat(
-    V: &Mat2,                       // V
-    sensor_mapping_mat: &Mat2x5,    // H
-    pred_covariance_mat: &Mat5      // pred C
-    ) -> Mat2 {                     // pred R
+pub fn residual_mat<N: na::Real>(
+    V: &Matrix2<N>,                       // V
+    sensor_mapping_mat: &Matrix2x5<N>,    // H
+    pred_covariance_mat: &Matrix5<N>      // pred C
+    ) -> Matrix2<N> {                     // pred R
         
     return V + (sensor_mapping_mat*pred_covariance_mat * sensor_mapping_mat.transpose())
 }
 
-pub fn residual_vec(
-    measurement_vec: &Vec2,         // m_k
-    sensor_mapping_mat: &Mat2x5,    // H
-    pred_state_vec: &Vec5           // pred x
-    ) -> Vec2 {                     // pred r
+pub fn residual_vec<N: na::Real>(
+    measurement_vec: &Vector2<N>,         // m_k
+    sensor_mapping_mat: &Matrix2x5<N>,    // H
+    pred_state_vec: &Vector5<N>           // pred x
+    ) -> Vector2<N> {                     // pred r
 
     let prod = sensor_mapping_mat * pred_state_vec;
     let diff = measurement_vec - prod;
@@ -44,13 +47,68 @@ pub fn residual_vec(
     return diff;
 }
 
-/// Calculates the predicted location of the hit on the following sensor
-// based on this equation set https://i.imgur.com/mWC0qkj.png
-pub fn linear_state_vector<T: Transform + Plane>(
+/// Covariance added to the direction angles by multiple scattering in a sensor of thickness
+/// `radiation_length_fraction` (x / X_0), following the Highland formula. Momentum is in GeV.
+pub fn multiple_scattering<N: na::Real>(
+    state_vec: &Vector5<N>,                   // x
+    radiation_length_fraction: N,       // x / X_0
+    mass: N                             // GeV
+    ) -> Matrix5<N> {                         // Q
+
+    let mut noise = Matrix5::zeros();
+
+    let q_over_p = state_vec[4];
+    if radiation_length_fraction <= na::zero() || q_over_p == na::zero() {
+        return noise
+    }
+
+    let momentum = q_over_p.abs().recip();
+    let beta = momentum / (momentum * momentum + mass * mass).sqrt();
+
+    let highland_scale : N = na::convert(0.0136);
+    let log_coefficient : N = na::convert(0.038);
+    let theta_0 = highland_scale / (beta * momentum) * radiation_length_fraction.sqrt() * (N::one() + log_coefficient * radiation_length_fraction.ln());
+    let variance = theta_0 * theta_0;
+
+    // phi is the angle out of the x-y plane, so deflections of theta are scaled by 1 / cos(phi)
+    let cos_phi = state_vec[3].cos();
+    noise[(2, 2)] = variance / (cos_phi * cos_phi);
+    noise[(3, 3)] = variance;
+
+    return noise
+}
+
+/// Calculates the predicted location of the hit on the following sensor. A prediction just outside the
+/// sensor is still accepted if it lies within `tolerance` of the bounds, judged with the local position
+/// block of the predicted covariance.
+pub fn linear_state_vector<N: na::Real, T: Transform<N> + Plane<N>>(
     start_sensor: &T, 
     end_sensor: &T, 
-    prev_filt_state_vec: &Vec5,
-    ) -> Result<Vec5, SensorError> {
+    prev_filt_state_vec: &Vector5<N>,
+    pred_cov_mat: &Matrix5<N>,
+    tolerance: &Tolerance<N>
+    ) -> Result<Vector5<N>, SensorError> {
+
+    let new_state_vec = linear_extrapolation(start_sensor, end_sensor, prev_filt_state_vec);
+    let local_cov_mat = pred_cov_mat.fixed_slice::<U2, U2>(0, 0).into_owned();
+
+    // check if the predicted point is on the sensor
+    if end_sensor.inside_with_tolerance(&Point2::new(new_state_vec[0], new_state_vec[1]), &local_cov_mat, tolerance) {
+        Ok(new_state_vec)
+    }
+    else {
+        Err(SensorError::OutsideSensorBounds)
+    }
+}
+
+/// Straight line extrapolation of the state vector onto the plane of the following sensor. Unlike
+/// `linear_state_vector` the result is not checked against the bounds of the sensor. If the track runs
+/// parallel to the plane the predicted position is NaN, which no bounds check will accept.
+pub fn linear_extrapolation<N: na::Real, T: Transform<N> + Plane<N>>(
+    start_sensor: &T, 
+    end_sensor: &T, 
+    prev_filt_state_vec: &Vector5<N>,
+    ) -> Vector5<N> {
     
     get_unchecked!{
         prev_filt_state_vec[0] => start_local_x_hit,
@@ -59,49 +117,50 @@ pub fn linear_state_vector<T: Transform + Plane>(
         prev_filt_state_vec[3] => phi
     }
 
-    let start_local_point = P3::new(*start_local_x_hit, *start_local_y_hit, 0.0);
+    let start_local_point = Point3::new(*start_local_x_hit, *start_local_y_hit, na::zero());
     let start_global_point = start_sensor.to_global(start_local_point);
 
     let cos_phi = phi.cos();
-    let x_slope = cos_phi * theta.cos();
-    let y_slope = cos_phi * theta.sin();
-    let z_slope = phi.sin();
+    let direction = Vector3::new(cos_phi * theta.cos(), cos_phi * theta.sin(), phi.sin());
 
-    // used so we can be generic over planar sensors
-    let normal = end_sensor.plane_normal_vec();
+    let local_pred_point = match intersection::plane_intersection(end_sensor, &start_global_point, &direction) {
+        Some(crossing) => end_sensor.to_local(crossing.point),
+        None => Point2::new(na::convert(Real::NAN), na::convert(Real::NAN))
+    };
 
-    // calculate a generic numerator used repetitively later
-    let gen_num_1 = normal.x * start_global_point.x;
-    let gen_num_2 = normal.y * start_global_point.y;
-    let gen_num_3 = normal.z * start_global_point.z;
-    let gen_num = gen_num_1 + gen_num_2 + gen_num_3;
+    // might be able to avoid cloning here
+    let mut new_state_vec = prev_filt_state_vec.clone();
+    new_state_vec[0] =local_pred_point.x;
+    new_state_vec[1] =local_pred_point.y; 
 
-    // generic denominator 
-    let gen_den_1 = normal.x * x_slope;
-    let gen_den_2 = normal.y * y_slope;
-    let gen_den_3 = normal.z * z_slope;
-    let gen_den = gen_den_1 + gen_den_2 + gen_den_3;
+    new_state_vec
+}
 
-    let gen_division = gen_num / gen_den;
+/// Jacobian of `linear_extrapolation` evaluated at `prev_filt_state_vec`, found with central differences
+pub fn linear_extrapolation_jacobian<N: na::Real, T: Transform<N> + Plane<N>>(
+    start_sensor: &T,
+    end_sensor: &T,
+    prev_filt_state_vec: &Vector5<N>
+    ) -> Matrix5<N> {                     // J
 
-    // calculate predicted points of intersection on ending plane
-    let pred_x = start_global_point.x - (x_slope * gen_division);
-    let pred_y = start_global_point.y - (y_slope * gen_division);
-    let pred_z = start_global_point.z - (z_slope * gen_division);
+    let mut jacobian = Matrix5::zeros();
 
-    let global_pred_point = P3::new(pred_x, pred_y, pred_z);
-    let local_pred_point  = end_sensor.to_local(global_pred_point);
+    // a step below the square root of the float epsilon is lost to rounding in single precision
+    let base_step = na::convert::<_, N>(JACOBIAN_STEP).max(N::default_epsilon().sqrt());
 
-    // check if the predicted point is on the sensor
-    if end_sensor.inside(&local_pred_point) {
-        // might be able to avoid cloning here
-        let mut new_state_vec = prev_filt_state_vec.clone();
-        new_state_vec[0] =local_pred_point.x;
-        new_state_vec[1] =local_pred_point.y; 
+    for j in 0..5 {
+        let step = base_step * prev_filt_state_vec[j].abs().max(N::one());
 
-        Ok(new_state_vec)
-    }
-    else {
-        Err(SensorError::OutsideSensorBounds)
+        let mut forward = prev_filt_state_vec.clone();
+        forward[j] += step;
+        let mut backward = prev_filt_state_vec.clone();
+        backward[j] -= step;
+
+        let column = (linear_extrapolation(start_sensor, end_sensor, &forward) - linear_extrapolation(start_sensor, end_sensor, &backward)) / (step + step);
+        jacobian.set_column(j, &column);
     }
-}
\ No newline at end of file
+
+    return jacobian
+}
+
+pub(crate) const JACOBIAN_STEP : Real = 1e-6;
@@ -0,0 +1,72 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::parameters;
+use super::prediction::JACOBIAN_STEP;
+
+use super::super::geometry::traits::Surface;
+
+// Straight line propagation between surfaces of any shape. The state is the usual
+// (loc0, loc1, theta, phi, q/p) with local coordinates of the surface it is bound to and the
+// direction angles of `parameters::direction`.
+
+/// Extrapolates the state bound to `start` onto `end`. Fails if the track does not reach `end`.
+pub fn surface_extrapolation<S: Surface, T: Surface>(
+    start: &S,
+    end: &T,
+    prev_filt_state_vec: &Vec5
+    ) -> Result<Vec5, SensorError> {
+
+    let direction = parameters::direction(prev_filt_state_vec[2], prev_filt_state_vec[3]);
+    let position = start.to_global_directed(P3::new(prev_filt_state_vec[0], prev_filt_state_vec[1], 0.), &direction);
+
+    let intersection = match end.intersect(&position, &direction) {
+        Some(intersection) => intersection,
+        None => return Err(SensorError::NoIntersection)
+    };
+
+    let local_point = end.to_local_directed(intersection.point, &direction);
+
+    let mut new_state_vec = prev_filt_state_vec.clone();
+    new_state_vec[0] = local_point.x;
+    new_state_vec[1] = local_point.y;
+
+    Ok(new_state_vec)
+}
+
+/// Jacobian of `surface_extrapolation` evaluated at `prev_filt_state_vec`, found with central differences
+pub fn surface_extrapolation_jacobian<S: Surface, T: Surface>(
+    start: &S,
+    end: &T,
+    prev_filt_state_vec: &Vec5
+    ) -> Result<Mat5, SensorError> {   // J
+
+    let mut jacobian = Mat5::zeros();
+
+    for j in 0..5 {
+        let step = JACOBIAN_STEP * prev_filt_state_vec[j].abs().max(1.);
+
+        let mut forward = prev_filt_state_vec.clone();
+        forward[j] += step;
+        let mut backward = prev_filt_state_vec.clone();
+        backward[j] -= step;
+
+        let column = (surface_extrapolation(start, end, &forward)? - surface_extrapolation(start, end, &backward)?) / (2. * step);
+        jacobian.set_column(j, &column);
+    }
+
+    Ok(jacobian)
+}
+
+/// Predicted state and covariance on `end`
+pub fn predict<S: Surface, T: Surface>(
+    start: &S,
+    end: &T,
+    prev_filt_state_vec: &Vec5,
+    prev_filt_cov_mat: &Mat5
+    ) -> Result<(Vec5, Mat5), SensorError> {
+
+    let pred_state_vec = surface_extrapolation(start, end, prev_filt_state_vec)?;
+    let jacobian = surface_extrapolation_jacobian(start, end, prev_filt_state_vec)?;
+
+    Ok((pred_state_vec, jacobian * prev_filt_cov_mat * jacobian.transpose()))
+}
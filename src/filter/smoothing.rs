@@ -1,21 +1,32 @@
-use super::super::config::*;
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Matrix2x5, Vector2, Vector5};
 
-pub fn gain_matrix(
-    curr_filt_cov_mat: &Mat5,   //filt C
-    jacobian: &Mat5,            // F_k or J
-    prev_filt_cov_mat: &Mat5    // prev filt C
-    ) -> Mat5 {                 // A
+pub fn gain_matrix<N: na::Real>(
+    curr_filt_cov_mat: &Matrix5<N>,   //filt C
+    jacobian: &Matrix5<N>,            // F_k or J
+    prev_filt_cov_mat: &Matrix5<N>    // prev filt C
+    ) -> Matrix5<N> {                 // A
 
     let inv_cov = prev_filt_cov_mat.try_inverse().expect("could not invert in gain matrix");
-    curr_filt_cov_mat * jacobian.transpose() * inv_cov
+    gain_matrix_inverted(curr_filt_cov_mat, jacobian, &inv_cov)
 }  
 
-pub fn state_vector(
-    curr_filt_state_vec: &Vec5,     // curr filt x
-    gain_mat: &Mat5,                // A
-    prev_smth_state_vec: &Vec5,     // prev smth x
-    prev_filt_state_vec: &Vec5      // prev filt x
-    ) -> Vec5 {                     // smth x
+/// Smoother gain from an already inverted predicted covariance matrix
+pub fn gain_matrix_inverted<N: na::Real>(
+    curr_filt_cov_mat: &Matrix5<N>,   //filt C
+    jacobian: &Matrix5<N>,            // F_k or J
+    inv_prev_cov_mat: &Matrix5<N>     // (prev C)^-1
+    ) -> Matrix5<N> {                 // A
+
+    curr_filt_cov_mat * jacobian.transpose() * inv_prev_cov_mat
+}
+
+pub fn state_vector<N: na::Real>(
+    curr_filt_state_vec: &Vector5<N>,     // curr filt x
+    gain_mat: &Matrix5<N>,                // A
+    prev_smth_state_vec: &Vector5<N>,     // prev smth x
+    prev_filt_state_vec: &Vector5<N>      // prev filt x
+    ) -> Vector5<N> {                     // smth x
     
     let parens = prev_smth_state_vec - prev_filt_state_vec;
     let prod = gain_mat * parens;
@@ -24,12 +35,12 @@ pub fn state_vector(
     return sum
 }
 
-pub fn covariance_matrix(
-    curr_filt_cov_mat: &Mat5,   // curr filt C  
-    gain_mat: &Mat5,            // A
-    prev_filt_cov_mat: &Mat5,   // prev filt C
-    prev_smth_cov_mat: &Mat5    // prev smth C
-    ) -> Mat5 {                 // smth C
+pub fn covariance_matrix<N: na::Real>(
+    curr_filt_cov_mat: &Matrix5<N>,   // curr filt C  
+    gain_mat: &Matrix5<N>,            // A
+    prev_filt_cov_mat: &Matrix5<N>,   // prev filt C
+    prev_smth_cov_mat: &Matrix5<N>    // prev smth C
+    ) -> Matrix5<N> {                 // smth C
 
     let parens = prev_smth_cov_mat - prev_filt_cov_mat;
     let prod = gain_mat * parens * gain_mat.transpose();
@@ -39,11 +50,11 @@ pub fn covariance_matrix(
 }
 
 
-pub fn residual_mat(
-    V: &Mat2,                       // V
-    sensor_mapping_mat: &Mat2x5,    // H
-    curr_smth_cov_mat: &Mat5        // curr smth C
-    ) -> Mat2 {                     // smth R
+pub fn residual_mat<N: na::Real>(
+    V: &Matrix2<N>,                       // V
+    sensor_mapping_mat: &Matrix2x5<N>,    // H
+    curr_smth_cov_mat: &Matrix5<N>        // curr smth C
+    ) -> Matrix2<N> {                     // smth R
 
     let prod = sensor_mapping_mat * curr_smth_cov_mat * sensor_mapping_mat.transpose();
     let diff = V - prod;
@@ -51,11 +62,11 @@ pub fn residual_mat(
     return diff;
 }
 
-pub fn residual_vec(
-    measurement_vec: &Vec2,         // m_k
-    sensor_mapping_mat: &Mat2x5,    // H
-    curr_smth_state_vec: &Vec5      // curr smth x
-    ) -> Vec2 {                     // smth r
+pub fn residual_vec<N: na::Real>(
+    measurement_vec: &Vector2<N>,         // m_k
+    sensor_mapping_mat: &Matrix2x5<N>,    // H
+    curr_smth_state_vec: &Vector5<N>      // curr smth x
+    ) -> Vector2<N> {                     // smth r
     
     let prod = sensor_mapping_mat * curr_smth_state_vec;
     let sum = measurement_vec + prod;
@@ -0,0 +1,245 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::prediction::JACOBIAN_STEP;
+
+use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::intersection;
+
+// The state is extended with the time the particle crosses the sensor:
+// (loc0, loc1, theta, phi, q/p, t)
+
+/// A hit on a sensor. Timing detectors may register the time alone or together with the position.
+#[derive(Debug, Clone)]
+pub enum Measurement {
+    Space{position: Vec2, noise: Mat2},
+    Time{time: Real, variance: Real},
+    /// `noise` is ordered (loc0, loc1, t)
+    SpaceTime{position: Vec2, time: Real, noise: Mat3}
+}
+
+impl Measurement {
+    /// Measurement vector, its covariance V and the matrix H mapping the state onto it
+    fn projection(&self) -> (DVec, DMat, DMat) {
+        match *self {
+            Measurement::Space{ref position, ref noise} => {
+                (DVec::from_column_slice(position.as_slice()),
+                 DMat::from_column_slice(2, 2, noise.as_slice()),
+                 mapping_mat(&[0, 1]))
+            },
+            Measurement::Time{time, variance} => {
+                (DVec::from_element(1, time),
+                 DMat::from_element(1, 1, variance),
+                 mapping_mat(&[5]))
+            },
+            Measurement::SpaceTime{ref position, time, ref noise} => {
+                (DVec::from_column_slice(&[position.x, position.y, time]),
+                 DMat::from_column_slice(3, 3, noise.as_slice()),
+                 mapping_mat(&[0, 1, 5]))
+            }
+        }
+    }
+}
+
+// H selecting the given parameters out of the state
+fn mapping_mat(parameters: &[usize]) -> DMat {
+    let mut mapping = DMat::zeros(parameters.len(), 6);
+    for (row, parameter) in parameters.iter().enumerate() {
+        mapping[(row, *parameter)] = 1.;
+    }
+    mapping
+}
+
+/// Result of a fit with time
+pub struct TimedData {
+    pub smth_state_vec: Vec<Vec6>,
+    pub smth_cov_mat: Vec<Mat6>,
+    pub chi_squared: Real
+}
+
+/// Speed of a particle of `mass` (GeV) with the given q/p (1 / GeV)
+pub fn velocity(q_over_p: Real, mass: Real) -> Real {
+    if q_over_p == 0. {
+        return SPEED_OF_LIGHT
+    }
+
+    let momentum = 1. / q_over_p.abs();
+    SPEED_OF_LIGHT * momentum / (momentum * momentum + mass * mass).sqrt()
+}
+
+/// Straight line extrapolation of the state onto the plane of `end_sensor`. The time is advanced by the
+/// path length divided by the velocity of the particle.
+pub fn time_extrapolation<T: Transform + Plane>(
+    start_sensor: &T,
+    end_sensor: &T,
+    prev_filt_state_vec: &Vec6,
+    mass: Real
+    ) -> Vec6 {
+
+    let start_global_point = start_sensor.to_global(P3::new(prev_filt_state_vec[0], prev_filt_state_vec[1], 0.));
+
+    let theta = prev_filt_state_vec[2];
+    let phi = prev_filt_state_vec[3];
+    let direction = Vec3::new(phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin());
+
+    let (local_pred_point, path_length) = match intersection::plane_intersection(end_sensor, &start_global_point, &direction) {
+        Some(crossing) => (end_sensor.to_local(crossing.point), crossing.path_length),
+        None => (P2::new(Real::NAN, Real::NAN), Real::NAN)
+    };
+
+    let mut new_state_vec = prev_filt_state_vec.clone();
+    new_state_vec[0] = local_pred_point.x;
+    new_state_vec[1] = local_pred_point.y;
+    new_state_vec[5] += path_length / velocity(prev_filt_state_vec[4], mass);
+
+    new_state_vec
+}
+
+/// Jacobian of `time_extrapolation` evaluated at `prev_filt_state_vec`, found with central differences
+pub fn time_extrapolation_jacobian<T: Transform + Plane>(
+    start_sensor: &T,
+    end_sensor: &T,
+    prev_filt_state_vec: &Vec6,
+    mass: Real
+    ) -> Mat6 {                     // J
+
+    let mut jacobian = Mat6::zeros();
+
+    for j in 0..6 {
+        let step = JACOBIAN_STEP * prev_filt_state_vec[j].abs().max(1.);
+
+        let mut forward = prev_filt_state_vec.clone();
+        forward[j] += step;
+        let mut backward = prev_filt_state_vec.clone();
+        backward[j] -= step;
+
+        let column = (time_extrapolation(start_sensor, end_sensor, &forward, mass) - time_extrapolation(start_sensor, end_sensor, &backward, mass)) / (2. * step);
+        jacobian.set_column(j, &column);
+    }
+
+    jacobian
+}
+
+/// Kalman update of a six parameter state with a measurement of any kind. Returns the filtered
+/// state vector, covariance and the chi squared increment.
+pub fn update(
+    pred_state_vec: &Vec6,      // pred x
+    pred_cov_mat: &Mat6,        // pred C
+    measurement: &Measurement
+    ) -> Result<(Vec6, Mat6, Real), MatrixError> {
+
+    let (m_k, curr_v, meas_map_mat) = measurement.projection();
+
+    let pred_x = DVec::from_column_slice(pred_state_vec.as_slice());
+    let pred_c = DMat::from_column_slice(6, 6, pred_cov_mat.as_slice());
+
+    let residual_vec = m_k - &meas_map_mat * &pred_x;
+    let residual_mat = curr_v + &meas_map_mat * &pred_c * meas_map_mat.transpose();
+
+    let inv_residual_mat = match residual_mat.try_inverse() {
+        Some(inverse) => inverse,
+        None => return Err(MatrixError::NonInvertible)
+    };
+
+    let kalman_gain = &pred_c * meas_map_mat.transpose() * &inv_residual_mat;
+    let filt_x = pred_x + &kalman_gain * &residual_vec;
+    let filt_c = (DMat::identity(6, 6) - &kalman_gain * &meas_map_mat) * &pred_c;
+    let chi_squared_inc = (residual_vec.transpose() * &inv_residual_mat * &residual_vec)[0];
+
+    Ok((Vec6::from_column_slice(filt_x.as_slice()),
+        Mat6::from_column_slice(filt_c.as_slice()),
+        chi_squared_inc))
+}
+
+/// Fits position and time together. Each sensor may register a position, a time or both. The seed is
+/// taken as the state on the first sensor and `mass` (GeV) sets the velocity used to propagate the time.
+pub fn run<T: Transform + Plane>(
+    seed_state_vec: &Vec6,
+    seed_cov_mat: &Mat6,
+    measurements_vector: &Vec<Measurement>,
+    sensor_vector: &Vec<T>,
+    mass: Real
+    ) -> Result<TimedData, MatrixError> {
+
+    if measurements_vector.len() != sensor_vector.len() {
+        panic!("vector lengths need to be the same length")
+    }
+    let input_length = measurements_vector.len();
+
+    if input_length == 0 {
+        return Ok(TimedData{smth_state_vec: Vec::new(),
+                            smth_cov_mat: Vec::new(),
+                            chi_squared: 0.})
+    }
+
+    store_vec!{input_length;
+        jacobian_iter: Mat6,
+        pred_state_vec_iter: Vec6,
+        pred_cov_mat_iter: Mat6,
+        filter_state_vec_iter: Vec6,
+        filter_cov_mat_iter: Mat6
+    }
+    let mut chi_squared = 0.;
+
+    for i in 0..input_length {
+        let (jacobian, pred_state_vec, pred_cov_mat) =
+            if i == 0 {
+                (Mat6::identity(), seed_state_vec.clone(), seed_cov_mat.clone())
+            }
+            else {
+                let prev_state_vec = filter_state_vec_iter.last().unwrap();
+                let prev_cov_mat = filter_cov_mat_iter.last().unwrap();
+
+                let jacobian = time_extrapolation_jacobian(&sensor_vector[i - 1], &sensor_vector[i], prev_state_vec, mass);
+                let pred_state_vec = time_extrapolation(&sensor_vector[i - 1], &sensor_vector[i], prev_state_vec, mass);
+                let pred_cov_mat = jacobian * prev_cov_mat * jacobian.transpose();
+                (jacobian, pred_state_vec, pred_cov_mat)
+            };
+
+        let (filter_state_vec, filter_cov_mat, chi_squared_inc) = update(&pred_state_vec, &pred_cov_mat, &measurements_vector[i])?;
+        chi_squared += chi_squared_inc;
+
+        push!{
+            jacobian => jacobian_iter,
+            pred_state_vec => pred_state_vec_iter,
+            pred_cov_mat => pred_cov_mat_iter,
+            filter_state_vec => filter_state_vec_iter,
+            filter_cov_mat => filter_cov_mat_iter
+        }
+    }
+
+    store_vec!{input_length;
+        smoothed_state_vec_iter: Vec6,
+        smoothed_cov_mat_iter: Mat6
+    }
+
+    push!{
+        filter_state_vec_iter[input_length - 1] => smoothed_state_vec_iter,
+        filter_cov_mat_iter[input_length - 1] => smoothed_cov_mat_iter
+    }
+
+    for i in (0..input_length - 1).rev() {
+        let next_smth_state_vec = smoothed_state_vec_iter.last().unwrap();
+        let next_smth_cov_mat = smoothed_cov_mat_iter.last().unwrap();
+
+        let inv_pred_cov_mat = match pred_cov_mat_iter[i + 1].try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(MatrixError::NonInvertible)
+        };
+
+        let gain_matrix = filter_cov_mat_iter[i] * jacobian_iter[i + 1].transpose() * inv_pred_cov_mat;
+        let smoothed_state_vec = filter_state_vec_iter[i] + gain_matrix * (next_smth_state_vec - pred_state_vec_iter[i + 1]);
+        let smoothed_cov_mat = filter_cov_mat_iter[i] + gain_matrix * (next_smth_cov_mat - pred_cov_mat_iter[i + 1]) * gain_matrix.transpose();
+
+        push!{
+            smoothed_state_vec => smoothed_state_vec_iter,
+            smoothed_cov_mat => smoothed_cov_mat_iter
+        }
+    }
+
+    smoothed_state_vec_iter.reverse();
+    smoothed_cov_mat_iter.reverse();
+
+    Ok(TimedData{smth_state_vec: smoothed_state_vec_iter,
+                 smth_cov_mat: smoothed_cov_mat_iter,
+                 chi_squared: chi_squared})
+}
@@ -0,0 +1,229 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Vector2, Vector5};
+use super::super::config::*;
+use super::super::error::*;
+use super::prediction;
+use super::filter_gain;
+use super::smoothing;
+use super::utils::{sensor_mapping_mat, SmoothedData};
+
+use super::super::geometry::traits::{Plane, Transform};
+
+// sigma points with their weights for the mean and for the covariance
+type WeightedPoints<N> = (Vec<Vector5<N>>, Vec<N>, Vec<N>);
+
+// predicted state, covariance, and cross covariance with the previous state
+type Prediction<N> = (Vector5<N>, Matrix5<N>, Matrix5<N>);
+
+/// Scheme used to place the sigma points around the current estimate
+#[derive(Debug, Clone, Copy)]
+pub enum SigmaPoints {
+    /// Original scheme of Julier and Uhlmann. `kappa = 3 - n` matches the fourth moment of a gaussian
+    Julier{kappa: Real},
+    /// Scaled scheme of van der Merwe. `alpha` sets the spread of the points, `beta = 2` is optimal
+    /// for gaussian distributions
+    MerweScaled{alpha: Real, beta: Real, kappa: Real}
+}
+
+impl SigmaPoints {
+    /// Returns the 2n+1 sigma points of the distribution along with their weights for calculating
+    /// the mean and the covariance. Fails if `cov_mat` is not positive definite.
+    pub fn generate<N: na::Real>(
+        &self,
+        state_vec: &Vector5<N>,
+        cov_mat: &Matrix5<N>
+        ) -> Result<WeightedPoints<N>, MatrixError> {
+
+        let n : N = na::convert(5.0);
+
+        let (lambda, first_cov_weight) =
+            match *self {
+                SigmaPoints::Julier{kappa} => {
+                    let kappa : N = na::convert(kappa);
+                    (kappa, kappa / (n + kappa))
+                },
+                SigmaPoints::MerweScaled{alpha, beta, kappa} => {
+                    let (alpha, beta, kappa) : (N, N, N) = (na::convert(alpha), na::convert(beta), na::convert(kappa));
+                    let lambda = alpha * alpha * (n + kappa) - n;
+                    (lambda, lambda / (n + lambda) + (N::one() - alpha * alpha + beta))
+                }
+            };
+
+        let sqrt_cov = match na::Cholesky::new(cov_mat * (n + lambda)) {
+            Some(cholesky) => cholesky.l(),
+            None => return Err(MatrixError::NotPositiveDefinite)
+        };
+
+        let mut points = Vec::with_capacity(11);
+        points.push(state_vec.clone());
+        for i in 0..5 {
+            points.push(state_vec + sqrt_cov.column(i));
+        }
+        for i in 0..5 {
+            points.push(state_vec - sqrt_cov.column(i));
+        }
+
+        let two : N = na::convert(2.0);
+        let outer_weight = N::one() / (two * (n + lambda));
+
+        let mut mean_weights = vec![outer_weight; 11];
+        mean_weights[0] = lambda / (n + lambda);
+
+        let mut cov_weights = vec![outer_weight; 11];
+        cov_weights[0] = first_cov_weight;
+
+        Ok((points, mean_weights, cov_weights))
+    }
+}
+
+/// Unscented kalman filter. Sigma points of the filtered state on one sensor are extrapolated onto the
+/// next with the same straight line propagation as `prediction::linear_extrapolation`, instead of
+/// linearizing it. The seed is taken as the state on the first sensor. Smoothing uses the
+/// cross covariance of the sigma points in place of the jacobian.
+pub fn run<N: na::Real, T: Transform<N> + Plane<N>>(
+    seed_state_vec: &Vector5<N>,
+    seed_cov_mat: &Matrix5<N>,
+    measurement_noise_covariance_vector: &Vec<Matrix2<N>>,    // V
+    measurements_vector: &Vec<Vector2<N>>,                    // m_k
+    sensor_vector: &Vec<T>,
+    sigma_points: &SigmaPoints
+    ) -> Result<SmoothedData<N>, MatrixError> {
+
+    if (measurement_noise_covariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
+    else {
+        panic!("vector lengths need to be the same length")
+    }
+    let input_length = measurements_vector.len();
+
+    if input_length == 0 {
+        return Ok(SmoothedData::new(Vec::new(), Vec::new(), Vec::new(), Vec::new()))
+    }
+
+    let meas_map_mat = sensor_mapping_mat();
+
+    store_vec!{input_length;
+        pred_state_vec_iter: Vector5<N>,
+        pred_cov_mat_iter: Matrix5<N>,
+        cross_cov_mat_iter: Matrix5<N>,       // covariance between the state on sensor i-1 and the prediction on i
+        filter_state_vec_iter: Vector5<N>,
+        filter_cov_mat_iter: Matrix5<N>
+    }
+
+    for i in 0..input_length {
+        get_unchecked!{i;
+            measurement_noise_covariance_vector => curr_v,
+            measurements_vector => curr_m_k
+        }
+
+        let (pred_state_vec, pred_cov_mat, cross_cov_mat) =
+            if i == 0 {
+                (seed_state_vec.clone(), seed_cov_mat.clone(), Matrix5::zeros())
+            }
+            else {
+                let prev_state_vec = filter_state_vec_iter.last().unwrap();
+                let prev_cov_mat = filter_cov_mat_iter.last().unwrap();
+
+                unscented_prediction(&sensor_vector[i - 1], &sensor_vector[i], prev_state_vec, prev_cov_mat, sigma_points)?
+            };
+
+        if prediction::residual_mat(curr_v, &meas_map_mat, &pred_cov_mat).try_inverse().is_none() {
+            return Err(MatrixError::NonInvertible)
+        }
+
+        // the measurement is linear in the state so the usual update is exact
+        let kalman_gain = filter_gain::kalman_gain(&pred_cov_mat, &meas_map_mat, curr_v);
+        let filter_state_vec = filter_gain::state_vector(&pred_state_vec, &kalman_gain, curr_m_k, &meas_map_mat);
+        let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, &pred_cov_mat);
+
+        push!{
+            pred_state_vec => pred_state_vec_iter,
+            pred_cov_mat => pred_cov_mat_iter,
+            cross_cov_mat => cross_cov_mat_iter,
+            filter_state_vec => filter_state_vec_iter,
+            filter_cov_mat => filter_cov_mat_iter
+        }
+    }
+
+    store_vec!{input_length;
+        smoothed_state_vec_iter: Vector5<N>,
+        smoothed_cov_mat_iter: Matrix5<N>,
+        smoothed_res_mat_iter: Matrix2<N>,
+        smoothed_res_vec_iter: Vector2<N>
+    }
+
+    push!{
+        filter_state_vec_iter[input_length - 1] => smoothed_state_vec_iter,
+        filter_cov_mat_iter[input_length - 1] => smoothed_cov_mat_iter
+    }
+
+    for i in (0..input_length - 1).rev() {
+        let next_smth_state_vec = smoothed_state_vec_iter.last().unwrap();
+        let next_smth_cov_mat = smoothed_cov_mat_iter.last().unwrap();
+
+        let inv_pred_cov_mat = match pred_cov_mat_iter[i + 1].try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(MatrixError::NonInvertible)
+        };
+
+        let gain_matrix = cross_cov_mat_iter[i + 1] * inv_pred_cov_mat;
+        let smoothed_state_vec = smoothing::state_vector(&filter_state_vec_iter[i], &gain_matrix, next_smth_state_vec, &pred_state_vec_iter[i + 1]);
+        let smoothed_cov_mat = smoothing::covariance_matrix(&filter_cov_mat_iter[i], &gain_matrix, &pred_cov_mat_iter[i + 1], next_smth_cov_mat);
+
+        push!{
+            smoothed_state_vec => smoothed_state_vec_iter,
+            smoothed_cov_mat => smoothed_cov_mat_iter
+        }
+    }
+
+    smoothed_state_vec_iter.reverse();
+    smoothed_cov_mat_iter.reverse();
+
+    for i in 0..input_length {
+        let smoothed_res_mat = smoothing::residual_mat(&measurement_noise_covariance_vector[i], &meas_map_mat, &smoothed_cov_mat_iter[i]);
+        let smoothed_res_vec = smoothing::residual_vec(&measurements_vector[i], &meas_map_mat, &smoothed_state_vec_iter[i]);
+
+        push!{
+            smoothed_res_mat => smoothed_res_mat_iter,
+            smoothed_res_vec => smoothed_res_vec_iter
+        }
+    }
+
+    Ok(SmoothedData::new(smoothed_state_vec_iter,
+                         smoothed_cov_mat_iter,
+                         smoothed_res_mat_iter,
+                         smoothed_res_vec_iter))
+}
+
+/// Predicted state, covariance, and cross covariance with the previous state found by pushing
+/// sigma points from `start_sensor` to `end_sensor`
+pub fn unscented_prediction<N: na::Real, T: Transform<N> + Plane<N>>(
+    start_sensor: &T,
+    end_sensor: &T,
+    prev_filt_state_vec: &Vector5<N>,
+    prev_filt_cov_mat: &Matrix5<N>,
+    sigma_points: &SigmaPoints
+    ) -> Result<Prediction<N>, MatrixError> {
+
+    let (points, mean_weights, cov_weights) = sigma_points.generate(prev_filt_state_vec, prev_filt_cov_mat)?;
+
+    let propagated : Vec<Vector5<N>> = points.iter()
+        .map(|point| prediction::linear_extrapolation(start_sensor, end_sensor, point))
+        .collect();
+
+    let mut pred_state_vec = Vector5::zeros();
+    for (point, weight) in propagated.iter().zip(mean_weights.iter()) {
+        pred_state_vec += point * *weight;
+    }
+
+    let mut pred_cov_mat = Matrix5::zeros();
+    let mut cross_cov_mat = Matrix5::zeros();
+    for ((point, prev_point), weight) in propagated.iter().zip(points.iter()).zip(cov_weights.iter()) {
+        let diff = point - pred_state_vec;
+        let prev_diff = prev_point - prev_filt_state_vec;
+
+        pred_cov_mat += diff * diff.transpose() * *weight;
+        cross_cov_mat += prev_diff * diff.transpose() * *weight;
+    }
+
+    Ok((pred_state_vec, pred_cov_mat, cross_cov_mat))
+}
@@ -12,6 +12,17 @@ pub fn seed_state_vec() -> Vec5 {
     return Vec5::new_random_generic(na::U5, na::U1)
 }
 
+/// Maps the state vector onto the two local coordinates measured by a sensor (H)
+pub fn sensor_mapping_mat<N: na::Real>() -> na::Matrix2x5<N> {
+    return na::Matrix2x5::new(N::one(), na::zero(), na::zero(), na::zero(), na::zero(),
+                              na::zero(), N::one(), na::zero(), na::zero(), na::zero())
+}
+
+/// Checks that a value is neither infinite nor NaN, which `nalgebra::Real` does not provide
+pub fn is_finite<N: na::Real>(value: N) -> bool {
+    value.abs() <= N::max_value()
+}
+
 /// Creates a vector of `num` length with Mat5 components
 pub fn vec_of_mat(num: usize) -> Vec<Mat5> {
     
@@ -33,24 +44,42 @@ pub fn vec_of_vec(num: usize) -> Vec<Vec5> {
 }
 
 
-pub struct SmoothedData {
-    state_vec: Vec<Vec5>,
-    cov_mat: Vec<Mat5>,
-    res_mat: Vec<Mat2>,
-    res_vec: Vec<Vec2>
+pub struct SmoothedData<N: na::Real = Real> {
+    state_vec: Vec<na::Vector5<N>>,
+    cov_mat: Vec<na::Matrix5<N>>,
+    res_mat: Vec<na::Matrix2<N>>,
+    res_vec: Vec<na::Vector2<N>>
 }
 
-impl SmoothedData{
-    pub fn new(state_vec: Vec<Vec5>,
-            cov_mat: Vec<Mat5>,
-            res_mat: Vec<Mat2>,
-            res_vec: Vec<Vec2>) -> Self {
+impl<N: na::Real> SmoothedData<N> {
+    pub fn new(state_vec: Vec<na::Vector5<N>>,
+            cov_mat: Vec<na::Matrix5<N>>,
+            res_mat: Vec<na::Matrix2<N>>,
+            res_vec: Vec<na::Vector2<N>>) -> Self {
 
         return SmoothedData{state_vec: state_vec, 
                             cov_mat: cov_mat, 
                             res_mat: res_mat, 
                             res_vec:res_vec}
     }
+
+    /// Smoothed state vectors, one per sensor in the order the sensors were passed in
+    pub fn state_vec(&self) -> &Vec<na::Vector5<N>> {
+        &self.state_vec
+    }
+
+    pub fn cov_mat(&self) -> &Vec<na::Matrix5<N>> {
+        &self.cov_mat
+    }
+
+    pub fn res_mat(&self) -> &Vec<na::Matrix2<N>> {
+        &self.res_mat
+    }
+
+    pub fn res_vec(&self) -> &Vec<na::Vector2<N>> {
+        &self.res_vec
+    }
+
     pub fn FFI_return() {
         unimplemented!()
     }
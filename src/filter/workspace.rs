@@ -0,0 +1,106 @@
+use nalgebra as na;
+use na::{Matrix2, Matrix5, Vector2, Vector5};
+use super::super::config::*;
+use super::utils::SmoothedData;
+
+/// Preallocated buffers for a track fit. Passing the same workspace into repeated fits reuses
+/// its buffers, so once it has grown to the longest track no further heap allocation happens.
+/// Results of the last fit are read back as slices in sensor order.
+#[derive(Debug, Clone)]
+pub struct FitWorkspace<N: na::Real = Real> {
+    pub(crate) jacobian: Vec<Matrix5<N>>,
+    pub(crate) pred_state_vec: Vec<Vector5<N>>,
+    pub(crate) pred_cov_mat: Vec<Matrix5<N>>,
+    pub(crate) filt_state_vec: Vec<Vector5<N>>,
+    pub(crate) filt_cov_mat: Vec<Matrix5<N>>,
+    pub(crate) chi_squared_inc: Vec<N>,
+
+    pub(crate) smth_state_vec: Vec<Vector5<N>>,
+    pub(crate) smth_cov_mat: Vec<Matrix5<N>>,
+    pub(crate) smth_res_mat: Vec<Matrix2<N>>,
+    pub(crate) smth_res_vec: Vec<Vector2<N>>
+}
+
+impl<N: na::Real> FitWorkspace<N> {
+    pub fn new() -> Self {
+        FitWorkspace{jacobian: Vec::new(),
+                     pred_state_vec: Vec::new(),
+                     pred_cov_mat: Vec::new(),
+                     filt_state_vec: Vec::new(),
+                     filt_cov_mat: Vec::new(),
+                     chi_squared_inc: Vec::new(),
+                     smth_state_vec: Vec::new(),
+                     smth_cov_mat: Vec::new(),
+                     smth_res_mat: Vec::new(),
+                     smth_res_vec: Vec::new()}
+    }
+
+    /// Workspace that can fit tracks of up to `capacity` sensors without allocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut workspace = FitWorkspace::new();
+        workspace.reset(capacity);
+        workspace
+    }
+
+    /// Empties every buffer and makes sure each can hold `len` elements. Capacity is kept
+    /// between fits so this only allocates when a longer track than before comes along.
+    pub(crate) fn reset(&mut self, len: usize) {
+        macro_rules! reset {
+            ($($buffer:ident),+) => {
+                $(
+                    self.$buffer.clear();
+                    self.$buffer.reserve(len);
+                )+
+            };
+        }
+
+        reset!{jacobian, pred_state_vec, pred_cov_mat, filt_state_vec, filt_cov_mat, chi_squared_inc,
+               smth_state_vec, smth_cov_mat, smth_res_mat, smth_res_vec}
+    }
+
+    /// Number of sensors in the last fit
+    pub fn len(&self) -> usize {
+        self.smth_state_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.smth_state_vec.is_empty()
+    }
+
+    pub fn state_vec(&self) -> &[Vector5<N>] {
+        &self.smth_state_vec
+    }
+
+    pub fn cov_mat(&self) -> &[Matrix5<N>] {
+        &self.smth_cov_mat
+    }
+
+    pub fn res_mat(&self) -> &[Matrix2<N>] {
+        &self.smth_res_mat
+    }
+
+    pub fn res_vec(&self) -> &[Vector2<N>] {
+        &self.smth_res_vec
+    }
+
+    pub fn filt_state_vec(&self) -> &[Vector5<N>] {
+        &self.filt_state_vec
+    }
+
+    pub fn filt_cov_mat(&self) -> &[Matrix5<N>] {
+        &self.filt_cov_mat
+    }
+
+    /// Total chi squared of the filter pass
+    pub fn chi_squared(&self) -> N {
+        self.chi_squared_inc.iter().fold(na::zero(), |sum, inc| sum + *inc)
+    }
+
+    /// Copies the smoothed results out of the workspace, in the order the sensors were crossed
+    pub fn smoothed_data(&self) -> SmoothedData<N> {
+        SmoothedData::new(self.smth_state_vec.clone(),
+                          self.smth_cov_mat.clone(),
+                          self.smth_res_mat.clone(),
+                          self.smth_res_vec.clone())
+    }
+}
@@ -0,0 +1,107 @@
+use nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
+use super::traits::{Transform, Surface};
+use super::intersection::Intersection;
+use super::super::config::*;
+use super::super::error::*;
+
+/// A cylinder around its local z axis, for barrel layers and the beam pipe. Local points are
+/// (r * phi, z) with phi in (-pi, pi] measured from the local x axis. Since the surface is not planar
+/// it implements `Surface` but not `Plane`.
+#[derive(Debug)]
+pub struct Cylinder<N: na::Real = Real> {
+    pub radius: N,
+    half_length: N,
+
+    to_global: Affine3<N>,
+    to_local: Affine3<N>,
+}
+
+impl<N: na::Real> Cylinder<N> {
+    /// Cylinder of `radius` and `length` centered on the local origin. The transform should be a
+    /// rotation and translation so the radius is kept.
+    pub fn new(
+        radius: N,
+        length: N,
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Cylinder<N>, Error> {
+
+        if radius <= na::zero() || length <= na::zero() {
+            return Err(Error::Sensor(SensorError::InvalidBounds))
+        }
+
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
+
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(Error::Matrix(MatrixError::NonInvertible))
+        };
+
+        let two : N = na::convert(2.0);
+        Ok(Cylinder{radius: radius,
+                    half_length: length / two,
+                    to_global: to_global_transform,
+                    to_local: to_local_transform})
+    }
+}
+
+impl<N: na::Real> Transform<N> for Cylinder<N> {
+    /// The z component of the input is ignored, the point is placed on the cylinder
+    fn to_global(&self, input_point: Point3<N>) -> Point3<N> {
+        let phi = input_point.x / self.radius;
+        let local = Point3::new(self.radius * phi.cos(), self.radius * phi.sin(), input_point.y);
+        self.to_global * local
+    }
+
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N> {
+        let local = self.to_local * input_point;
+        Point2::new(self.radius * local.y.atan2(local.x), local.z)
+    }
+
+    fn inside(&self, input: &Point2<N>) -> bool {
+        input.y.abs() <= self.half_length
+    }
+
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        input.y.abs() - self.half_length
+    }
+}
+
+impl<N: na::Real> Surface<N> for Cylinder<N> {
+    fn normal_at(&self, input_point: &Point3<N>) -> Vector3<N> {
+        let local = self.to_local * input_point;
+        let radial = Vector3::new(local.x, local.y, na::zero());
+        (self.to_global * radial).normalize()
+    }
+
+    fn intersect(&self, position: &Point3<N>, direction: &Vector3<N>) -> Option<Intersection<N>> {
+        let local_position = self.to_local * position;
+        let local_direction = self.to_local * direction;
+
+        // |p + s d|^2 = r^2 in the plane transverse to the axis
+        let a = local_direction.x * local_direction.x + local_direction.y * local_direction.y;
+        if a <= na::convert(DOT_PRODUCT_EPSILON) {
+            return None
+        }
+        let b = local_position.x * local_direction.x + local_position.y * local_direction.y;
+        let c = local_position.x * local_position.x + local_position.y * local_position.y - self.radius * self.radius;
+
+        let discriminant = b * b - a * c;
+        if discriminant < na::zero() {
+            return None
+        }
+
+        let root = discriminant.sqrt();
+        let near = (-b - root) / a;
+        let far = (-b + root) / a;
+
+        let tolerance : N = na::convert(DOT_PRODUCT_EPSILON);
+        let path_length =
+            if near >= -tolerance {near}
+            else if far >= -tolerance {far}
+            else {return None};
+
+        let point = position + direction * path_length;
+        Some(Intersection::new(position, direction, path_length, &self.normal_at(&point)))
+    }
+}
@@ -0,0 +1,148 @@
+use nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
+use super::traits::{Transform, Plane};
+use super::super::config::*;
+use super::super::error::*;
+use super::utils;
+
+/// A struct for disc sensors bounded by two radii and a range of the polar angle, as used in
+/// endcaps. The local frame is still cartesian with the disc centered on its origin; the bounds
+/// are checked in polar coordinates (r, phi) where phi is measured from the local x axis.
+#[derive(Debug)]
+pub struct Disc<N: na::Real = Real> {
+    pub global_center: Point3<N>,
+    pub normal: Vector3<N>,
+    pub plane_constant: N,
+
+    inner_radius: N,
+    outer_radius: N,
+    phi_min: N,
+    phi_width: N,   // counter clockwise from `phi_min`
+
+    to_global: Affine3<N>,
+    to_local: Affine3<N>,
+}
+
+impl<N: na::Real> Disc<N> {
+    /// Annulus sector between `inner_radius` and `outer_radius` covering the polar angles from
+    /// `phi_min` counter clockwise to `phi_max`. A range of 2 pi or more gives a full annulus.
+    pub fn new(
+        inner_radius: N,
+        outer_radius: N,
+        phi_min: N,
+        phi_max: N,
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Disc<N>, Error> {
+
+        if inner_radius < na::zero() || outer_radius <= inner_radius || phi_max <= phi_min {
+            return Err(Error::Sensor(SensorError::InvalidBounds))
+        }
+
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
+
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(Error::Matrix(MatrixError::NonInvertible))
+        };
+
+        let (global_center, normal, plane_constant) = utils::plane_from_transform(|point| to_global_transform * point);
+
+        Ok(Disc{global_center: global_center,
+                normal: normal,
+                plane_constant: plane_constant,
+                inner_radius: inner_radius,
+                outer_radius: outer_radius,
+                phi_min: phi_min,
+                phi_width: (phi_max - phi_min).min(N::two_pi()),
+                to_global: to_global_transform,
+                to_local: to_local_transform})
+    }
+
+    /// Full annulus between the two radii
+    pub fn new_annulus(
+        inner_radius: N,
+        outer_radius: N,
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Disc<N>, Error> {
+
+        Disc::new(inner_radius, outer_radius, na::zero(), N::two_pi(), to_global_tfm_matrix)
+    }
+}
+
+/// Polar coordinates (r, phi) of a local point, with phi in (-pi, pi]
+pub fn to_polar<N: na::Real>(input: &Point2<N>) -> (N, N) {
+    (input.coords.norm(), input.y.atan2(input.x))
+}
+
+impl<N: na::Real> Transform<N> for Disc<N> {
+    fn to_global(&self, input_point: Point3<N>) -> Point3<N> {
+        self.to_global * input_point
+    }
+
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N> {
+        let local = self.to_local * input_point;
+        Point2::new(local.x, local.y)
+    }
+
+    fn inside(&self, input: &Point2<N>) -> bool {
+        let (radius, _) = to_polar(input);
+        radius >= self.inner_radius && radius <= self.outer_radius && self.in_phi_range(input)
+    }
+
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        let (radius, _) = to_polar(input);
+        let in_phi_range = self.in_phi_range(input);
+
+        // the arcs are only closest where the point lies within the phi range, otherwise one of the
+        // straight edges (or their ends, the corners) is
+        let mut distance = N::max_value();
+        if in_phi_range {
+            distance = (radius - self.outer_radius).abs();
+            if self.inner_radius > na::zero() {
+                distance = distance.min((radius - self.inner_radius).abs());
+            }
+        }
+
+        if self.phi_width < N::two_pi() {
+            for edge_phi in [self.phi_min, self.phi_min + self.phi_width].iter() {
+                let (sin, cos) = edge_phi.sin_cos();
+                let inner = Point2::new(self.inner_radius * cos, self.inner_radius * sin);
+                let outer = Point2::new(self.outer_radius * cos, self.outer_radius * sin);
+                distance = distance.min(utils::segment_distance(&inner, &outer, input));
+            }
+        }
+
+        if self.inside(input) {-distance} else {distance}
+    }
+}
+
+impl<N: na::Real> Disc<N> {
+    // checks the polar angle of a local point against the sector
+    fn in_phi_range(&self, input: &Point2<N>) -> bool {
+        if self.phi_width >= N::two_pi() {
+            return true
+        }
+
+        // angle from the start of the sector, rotated into [0, 2 pi)
+        let (sin, cos) = self.phi_min.sin_cos();
+        let mut offset = (input.y * cos - input.x * sin).atan2(input.x * cos + input.y * sin);
+        if offset < na::zero() {
+            offset += N::two_pi();
+        }
+
+        offset <= self.phi_width
+    }
+}
+
+impl<N: na::Real> Plane<N> for Disc<N> {
+    fn on_plane(&self, input_point: &Point3<N>) -> bool {
+        utils::on_plane(&self.normal, self.plane_constant, input_point)
+    }
+
+    fn plane_normal_vec(&self) -> &Vector3<N> {
+        &self.normal
+    }
+    fn plane_constant(&self) -> N {
+        self.plane_constant
+    }
+}
@@ -0,0 +1,43 @@
+use nalgebra as na;
+use na::{Point3, Vector3};
+use super::traits::Plane;
+use super::super::config::*;
+
+/// Where a straight line crosses a surface
+#[derive(Debug, Clone)]
+pub struct Intersection<N: na::Real = Real> {
+    pub point: Point3<N>,
+    pub path_length: N,         // along the direction, negative behind the start
+    pub incidence_angle: N      // between the direction and the normal, zero for a perpendicular crossing
+}
+
+impl<N: na::Real> Intersection<N> {
+    /// Intersection `path_length` along the unit `direction` from `position`, on a surface with `normal` there
+    pub fn new(position: &Point3<N>, direction: &Vector3<N>, path_length: N, normal: &Vector3<N>) -> Self {
+        let cos_incidence = normal.normalize().dot(direction).abs().min(N::one());
+
+        Intersection{point: position + direction * path_length,
+                     path_length: path_length,
+                     incidence_angle: cos_incidence.acos()}
+    }
+}
+
+/// Crossing of a straight line with the plane of a planar sensor, in front of or behind `position`.
+/// `None` if the line is parallel to the plane within `DOT_PRODUCT_EPSILON`.
+pub fn plane_intersection<N: na::Real, T: Plane<N>>(
+    sensor: &T,
+    position: &Point3<N>,
+    direction: &Vector3<N>
+    ) -> Option<Intersection<N>> {
+
+    // the plane is cached on the sensor, this is called for every extrapolation
+    let normal = sensor.plane_normal_vec();
+
+    let incidence = normal.dot(direction);
+    if incidence.abs() <= na::convert(DOT_PRODUCT_EPSILON) {
+        return None
+    }
+
+    let path_length = -(normal.dot(&position.coords) + sensor.plane_constant()) / incidence;
+    Some(Intersection::new(position, direction, path_length, normal))
+}
@@ -1,7 +1,19 @@
 pub mod trapezoid;
 pub mod rectangle;
+pub mod disc;
+pub mod polygon;
+pub mod cylinder;
+pub mod straw;
+pub mod perigee;
+pub mod intersection;
 pub mod traits;
 pub mod utils;
 
 pub use trapezoid::Trapezoid;
-pub use rectangle::Rectangle;
\ No newline at end of file
+pub use rectangle::Rectangle;
+pub use disc::Disc;
+pub use polygon::Polygon;
+pub use cylinder::Cylinder;
+pub use straw::Straw;
+pub use perigee::PerigeeSurface;
+pub use intersection::Intersection;
\ No newline at end of file
@@ -0,0 +1,19 @@
+use super::super::config::*;
+
+/// The beam line, parallel to the global z axis through `beam_position`. Track parameters at the point of
+/// closest approach to it are the perigee parameters (see `filter::perigee`).
+#[derive(Debug, Clone)]
+pub struct PerigeeSurface {
+    pub beam_position: P3
+}
+
+impl PerigeeSurface {
+    pub fn new(beam_position: P3) -> Self {
+        PerigeeSurface{beam_position: beam_position}
+    }
+
+    /// Beam line along the global z axis
+    pub fn origin() -> Self {
+        PerigeeSurface{beam_position: P3::origin()}
+    }
+}
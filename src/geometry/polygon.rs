@@ -0,0 +1,96 @@
+use nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
+use super::traits::{Transform, Plane};
+use super::super::config::*;
+use super::super::error::*;
+use super::utils;
+
+/// A struct for sensors shaped as any convex polygon, given by its corners in the local frame
+#[derive(Debug)]
+pub struct Polygon<N: na::Real = Real> {
+    pub global_center: Point3<N>,
+    pub normal: Vector3<N>,
+    pub plane_constant: N,
+
+    vertices: Vec<Point2<N>>,   // counter clockwise
+
+    to_global: Affine3<N>,
+    to_local: Affine3<N>,
+}
+
+impl<N: na::Real> Polygon<N> {
+    /// The vertices may run either way around but must form a convex polygon, otherwise
+    /// `SensorError::NotConvex` is returned.
+    pub fn new(
+        vertices: Vec<Point2<N>>,
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Polygon<N>, Error> {
+
+        if !utils::polygon_is_convex(&vertices) {
+            return Err(Error::Sensor(SensorError::NotConvex))
+        }
+
+        let mut vertices = vertices;
+        if utils::polygon_signed_area(&vertices) < na::zero() {
+            vertices.reverse();
+        }
+
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
+
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(Error::Matrix(MatrixError::NonInvertible))
+        };
+
+        let (global_center, normal, plane_constant) = utils::plane_from_transform(|point| to_global_transform * point);
+
+        Ok(Polygon{global_center: global_center,
+                   normal: normal,
+                   plane_constant: plane_constant,
+                   vertices: vertices,
+                   to_global: to_global_transform,
+                   to_local: to_local_transform})
+    }
+
+    /// Corners in counter clockwise order
+    pub fn vertices(&self) -> &[Point2<N>] {
+        &self.vertices
+    }
+
+    /// Distance from a local point to the nearest edge
+    pub fn edge_distance(&self, input: &Point2<N>) -> N {
+        utils::polygon_edge_distance(&self.vertices, input)
+    }
+}
+
+impl<N: na::Real> Transform<N> for Polygon<N> {
+    fn to_global(&self, input_point: Point3<N>) -> Point3<N> {
+        self.to_global * input_point
+    }
+
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N> {
+        let local = self.to_local * input_point;
+        Point2::new(local.x, local.y)
+    }
+
+    fn inside(&self, input: &Point2<N>) -> bool {
+        utils::polygon_contains(&self.vertices, input)
+    }
+
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        utils::polygon_signed_distance(&self.vertices, input)
+    }
+}
+
+impl<N: na::Real> Plane<N> for Polygon<N> {
+    fn on_plane(&self, input_point: &Point3<N>) -> bool {
+        utils::on_plane(&self.normal, self.plane_constant, input_point)
+    }
+
+    fn plane_normal_vec(&self) -> &Vector3<N> {
+        &self.normal
+    }
+    fn plane_constant(&self) -> N {
+        self.plane_constant
+    }
+}
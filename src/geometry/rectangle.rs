@@ -1,4 +1,5 @@
 use nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
 use super::traits::{Transform, Plane};
 use super::super::config::*;
 use super::super::error::*;
@@ -6,19 +7,19 @@ use super::utils;
 
 /// A struct for sensors of rectangular geometry
 #[derive(Debug)]
-pub struct Rectangle {
-    pub gloabl_center: P3,  //center of the sensor (not used in bound checks)
-    pub normal : Vec3,      // normal vector of plane
-    pub plane_constant: Real, // D in Ax +By + Cz +D =0 
+pub struct Rectangle<N: na::Real = Real> {
+    pub global_center: Point3<N>,  //center of the sensor (not used in bound checks)
+    pub normal : Vector3<N>,      // unit normal vector of plane
+    pub plane_constant: N, // D in Ax +By + Cz +D =0 
 
-    half_base: Real,
-    half_height: Real,
+    half_base: N,
+    half_height: N,
 
-    to_global: Aff3,    // L => G for point
-    to_local: Aff3,     // G => L for point
+    to_global: Affine3<N>,    // L => G for point
+    to_local: Affine3<N>,     // G => L for point
 }
 
-impl Rectangle {
+impl<N: na::Real> Rectangle<N> {
     /*
     /// This is the constructor for the rectangular geometry. It expects a 4x4 `nalgebra::Matrix4<f32>` that is invertible 
     /// and a 4 element array of `nalgebra::Point3<f32>`. If the matrix is not invertible it will return `Err(kalman_rs::Error)`.
@@ -40,31 +41,28 @@ impl Rectangle {
     /// ```
     /// */
     pub fn new(
-        base: Real, 
-        height: Real, 
-        to_global_tfm_matrix: Mat4
-        ) -> Result<Rectangle, MatrixError>{
+        base: N, 
+        height: N, 
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Rectangle<N>, MatrixError>{
     
-        let to_global_transform = Aff3::from_matrix_unchecked(to_global_tfm_matrix);
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
 
         match to_global_transform.try_inverse(){
             
             Some(to_local_transform) => {
 
-                let half_base = base/(2.0);
-                let half_height = height/(2.0);
-
-                let orig = P3::new(0.0, 0.0, 0.0);
-                let p1 = P3::new(half_base, 0.0,0.0);
-                let p2 = P3::new(0.0, half_height, 0.0);
-                let normal_vector = utils::plane_normal_vector(half_base, half_height);
+                let two : N = na::convert(2.0);
+                let half_base = base/two;
+                let half_height = height/two;
 
+                let (center, normal_vector, plane_constant) = utils::plane_from_transform(|point| to_global_transform * point);
 
                 let rect = Rectangle{half_base: half_base, 
                              half_height: half_height,
                              normal: normal_vector,
-                             plane_constant: 0.,         //TODO fix this one
-                             gloabl_center: orig,
+                             plane_constant: plane_constant,
+                             global_center: center,
                              to_global: to_global_transform,
                              to_local: to_local_transform};
                              
@@ -77,7 +75,7 @@ impl Rectangle {
     }
 
 }
-impl Transform for Rectangle{
+impl<N: na::Real> Transform<N> for Rectangle<N>{
     /*
     /// Converts a point in the global reference frame to a point in the local reference frame of the sensor.
     /// 
@@ -96,7 +94,7 @@ impl Transform for Rectangle{
     /// 
     /// let global_point = rectangle_sensor.to_global(na::Point3::new(1.0, 2.0, 0.0));
     /// ```*/
-    fn to_global(&self, input_point: P3)-> P3{
+    fn to_global(&self, input_point: Point3<N>)-> Point3<N>{
         self.to_global * input_point
     }
     
@@ -118,9 +116,9 @@ impl Transform for Rectangle{
     /// 
     /// let global_point = rectangle_sensor.to_local(na::Point3::new(6.0, 3.0, 5.0));
     /// ```*/
-    fn to_local(&self, input_point: P3) -> P2{
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N>{
         let local = self.to_local * input_point;
-        return P2::new(local.x, local.y)
+        return Point2::new(local.x, local.y)
     }
 
     /*
@@ -140,7 +138,7 @@ impl Transform for Rectangle{
     /// 
     /// let is_point_on_sensor = rectangle_sensor.contains_from_local(&na::Point2::new(1.0, 6.0));
     /// ```*/
-    fn inside(&self, input: &P2) -> bool {
+    fn inside(&self, input: &Point2<N>) -> bool {
         
         if (input.x.abs() < self.half_base.abs()) && (input.y.abs() < self.half_height.abs()) {
             true
@@ -149,10 +147,14 @@ impl Transform for Rectangle{
             false
         }
     }
+
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        utils::box_signed_distance(self.half_base.abs(), self.half_height.abs(), input)
+    }
 }
 
 
-impl Plane for Rectangle{
+impl<N: na::Real> Plane<N> for Rectangle<N>{
 
     /*
     /// Check if a given point is located on the same plane as the sensor
@@ -172,18 +174,14 @@ impl Plane for Rectangle{
     /// 
     /// let on_plane = rectangle_sensor.on_plane(&na::Point3::new(1.0, 3.0, 0.0)); //true
     /// ```*/
-    fn on_plane(&self, input_point: &P3) -> bool {
-        let pv : Vec3= P3::new(0.0, 0.0, 0.0) - input_point;
-       
-        if self.normal.dot(&pv).abs() <= DOT_PRODUCT_EPSILON{
-            true
-        }
-        else{
-            false
-        }
+    fn on_plane(&self, input_point: &Point3<N>) -> bool {
+        utils::on_plane(&self.normal, self.plane_constant, input_point)
     }
 
-    fn plane_normal_vec(&self) -> &Vec3 {
+    fn plane_normal_vec(&self) -> &Vector3<N> {
         return &self.normal
     }
+    fn plane_constant(&self) -> N {
+        self.plane_constant
+    }
 }
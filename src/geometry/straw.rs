@@ -0,0 +1,122 @@
+use nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
+use super::traits::{Transform, Surface};
+use super::intersection::Intersection;
+use super::super::config::*;
+use super::super::error::*;
+use super::utils;
+
+/// A straw tube (or any line surface) around a wire along its local z axis. A track is bound to it at
+/// the point of closest approach to the wire, with local coordinates (signed drift radius, z along
+/// the wire). The radius is positive when the track passes on the side of `wire x direction`, so the
+/// local frame depends on the track direction (see `Surface::to_local_directed`).
+///
+/// Without a direction, `Transform` uses the unsigned distance to the wire and places local points
+/// along the local x axis.
+#[derive(Debug)]
+pub struct Straw<N: na::Real = Real> {
+    pub radius: N,
+    half_length: N,
+
+    to_global: Affine3<N>,
+    to_local: Affine3<N>,
+}
+
+impl<N: na::Real> Straw<N> {
+    pub fn new(
+        radius: N,
+        length: N,
+        to_global_tfm_matrix: Matrix4<N>
+        ) -> Result<Straw<N>, Error> {
+
+        if radius <= na::zero() || length <= na::zero() {
+            return Err(Error::Sensor(SensorError::InvalidBounds))
+        }
+
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
+
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(inverse) => inverse,
+            None => return Err(Error::Matrix(MatrixError::NonInvertible))
+        };
+
+        let two : N = na::convert(2.0);
+        Ok(Straw{radius: radius,
+                 half_length: length / two,
+                 to_global: to_global_transform,
+                 to_local: to_local_transform})
+    }
+
+    /// Global position of the center of the wire
+    pub fn wire_center(&self) -> Point3<N> {
+        self.to_global * Point3::origin()
+    }
+
+    /// Unit vector along the wire
+    pub fn wire_direction(&self) -> Vector3<N> {
+        (self.to_global * Vector3::z()).normalize()
+    }
+
+    /// Unit vector of positive drift radii for a track with `direction`
+    pub fn radial_direction(&self, direction: &Vector3<N>) -> Vector3<N> {
+        self.wire_direction().cross(direction).normalize()
+    }
+}
+
+impl<N: na::Real> Transform<N> for Straw<N> {
+    fn to_global(&self, input_point: Point3<N>) -> Point3<N> {
+        self.to_global * Point3::new(input_point.x, na::zero(), input_point.y)
+    }
+
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N> {
+        let local = self.to_local * input_point;
+        Point2::new((local.x * local.x + local.y * local.y).sqrt(), local.z)
+    }
+
+    fn inside(&self, input: &Point2<N>) -> bool {
+        input.x.abs() <= self.radius && input.y.abs() <= self.half_length
+    }
+
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        utils::box_signed_distance(self.radius, self.half_length, input)
+    }
+}
+
+impl<N: na::Real> Surface<N> for Straw<N> {
+    fn normal_at(&self, input_point: &Point3<N>) -> Vector3<N> {
+        let local = self.to_local * input_point;
+        let radial = Vector3::new(local.x, local.y, na::zero());
+        (self.to_global * radial).normalize()
+    }
+
+    /// The point of closest approach to the wire. The incidence angle is measured from the plane
+    /// perpendicular to the wire, so it is zero for a track crossing the wire at a right angle.
+    fn intersect(&self, position: &Point3<N>, direction: &Vector3<N>) -> Option<Intersection<N>> {
+        let wire = self.wire_direction();
+        let offset = position - self.wire_center();
+
+        let cos_angle = wire.dot(direction);
+        let sin_squared = N::one() - cos_angle * cos_angle;
+        if sin_squared <= na::convert(DOT_PRODUCT_EPSILON) {
+            return None
+        }
+
+        let path_length = (cos_angle * wire.dot(&offset) - direction.dot(&offset)) / sin_squared;
+        if path_length < -na::convert::<f64, N>(DOT_PRODUCT_EPSILON) {
+            return None
+        }
+
+        // the direction with its component along the wire removed
+        let crossing_normal = direction - wire * cos_angle;
+        Some(Intersection::new(position, direction, path_length, &crossing_normal))
+    }
+
+    fn to_global_directed(&self, input_point: Point3<N>, direction: &Vector3<N>) -> Point3<N> {
+        self.wire_center() + self.wire_direction() * input_point.y + self.radial_direction(direction) * input_point.x
+    }
+
+    fn to_local_directed(&self, input_point: Point3<N>, direction: &Vector3<N>) -> Point2<N> {
+        let offset = input_point - self.wire_center();
+        Point2::new(offset.dot(&self.radial_direction(direction)), offset.dot(&self.wire_direction()))
+    }
+}
@@ -1,30 +1,123 @@
+use nalgebra as na;
+use na::{Point2, Point3, Vector2, Vector3, Matrix2};
 use super::super::config::*;
+use super::{Rectangle, Trapezoid, Disc, Polygon};
+use super::intersection::{self, Intersection};
 
 /// Finding the attributes of a generic sensor's plane
-pub trait Plane {
+pub trait Plane<N: na::Real = Real> {
     // needs to be & mut since it will call plane() which expects 
     // mutable access
     /// Checks that a given point is located on a plane
-    fn on_plane(&self, input_point: &P3) -> bool;
+    fn on_plane(&self, input_point: &Point3<N>) -> bool;
 
-    fn plane_normal_vec(&self) -> &Vec3;
+    fn plane_normal_vec(&self) -> &Vector3<N>;
+
+    /// D in Ax + By + Cz + D = 0 with the unit normal (A, B, C)
+    fn plane_constant(&self) -> N;
 }
 
 /// Transformations between global and local reference frames. Additionally, It can be used to check if a 
 /// given local or global point is within the bounds of the sensor.
-pub trait Transform{
+pub trait Transform<N: na::Real = Real>{
     /// Converts a point in the global reference frame to a point in the local reference frame of the sensor.
-    fn to_global(&self, input_point: P3) -> P3;
+    fn to_global(&self, input_point: Point3<N>) -> Point3<N>;
 
     /// Converts a point in the local refernce frame of the sensor to the global reference frame.
-    fn to_local(&self, input_point: P3) -> P2;
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N>;
 
     /// Checks if a global point is contained within the localized bounds of a sensor.
-    fn inside_global(&self, input_point: P3) -> bool {
+    fn inside_global(&self, input_point: Point3<N>) -> bool {
         let local_point = Self::to_local(&self, input_point);
         Self::inside(&self,&local_point)
     }
 
     /// Checks if a local point is contained within the bounds of a sensor.
-    fn inside(&self, input: &P2) -> bool;
+    fn inside(&self, input: &Point2<N>) -> bool;
+
+    /// Signed distance of a local point to the boundary of the sensor, negative inside. Sensors that
+    /// do not provide it are treated as infinitely far from their boundary, so a tolerance never
+    /// changes the result of `inside`.
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        if self.inside(input) {-N::max_value()} else {N::max_value()}
+    }
+
+    /// Checks if a local point lies within `tolerance` of the bounds. `cov_mat` is the covariance of the
+    /// local point, only used with `Tolerance::Sigmas`.
+    fn inside_with_tolerance(&self, input: &Point2<N>, cov_mat: &Matrix2<N>, tolerance: &Tolerance<N>) -> bool {
+        let distance = self.boundary_distance(input);
+
+        match *tolerance {
+            Tolerance::None => self.inside(input),
+            Tolerance::Absolute(allowed) => distance <= allowed,
+            Tolerance::Sigmas(sigmas) => {
+                if distance <= na::zero() {
+                    return true
+                }
+
+                // spread of the point along the direction in which the distance grows
+                let step = na::convert::<f64, N>(1e-6) * input.coords.amax().max(N::one());
+                let two : N = na::convert(2.0);
+                let gradient = Vector2::new(
+                    self.boundary_distance(&Point2::new(input.x + step, input.y)) - self.boundary_distance(&Point2::new(input.x - step, input.y)),
+                    self.boundary_distance(&Point2::new(input.x, input.y + step)) - self.boundary_distance(&Point2::new(input.x, input.y - step))
+                    ) / (two * step);
+
+                let variance =
+                    if gradient.norm() > na::zero() {
+                        let unit = gradient.normalize();
+                        (unit.transpose() * cov_mat * unit)[0]
+                    }
+                    else {
+                        cov_mat[(0, 0)].max(cov_mat[(1, 1)])
+                    };
+
+                distance <= sigmas * variance.sqrt()
+            }
+        }
+    }
+}
+
+/// Any surface a track can cross, planar or not. Local points are two dimensional; for planar sensors
+/// they are the local x and y, other surfaces define their own coordinates.
+pub trait Surface<N: na::Real = Real>: Transform<N> {
+    /// Unit vector normal to the surface at a global point on it
+    fn normal_at(&self, input_point: &Point3<N>) -> Vector3<N>;
+
+    /// First crossing of the straight line from `position` along `direction` (a unit vector) with the
+    /// surface, or `None` if the line never reaches it going forwards
+    fn intersect(&self, position: &Point3<N>, direction: &Vector3<N>) -> Option<Intersection<N>>;
+
+    /// Global point of local coordinates for a track with `direction`. Only surfaces whose local
+    /// frame depends on the track, like straws, need to override this.
+    fn to_global_directed(&self, input_point: Point3<N>, _direction: &Vector3<N>) -> Point3<N> {
+        self.to_global(input_point)
+    }
+
+    /// Local coordinates of a global point on the surface for a track with `direction`
+    fn to_local_directed(&self, input_point: Point3<N>, _direction: &Vector3<N>) -> Point2<N> {
+        self.to_local(input_point)
+    }
 }
+
+// planar sensors find their plane from `to_global`, so any placement is handled
+macro_rules! impl_planar_surface {
+    ($($sensor:ident),+) => {
+        $(
+            impl<N: na::Real> Surface<N> for $sensor<N> {
+                fn normal_at(&self, _input_point: &Point3<N>) -> Vector3<N> {
+                    *self.plane_normal_vec()
+                }
+
+                fn intersect(&self, position: &Point3<N>, direction: &Vector3<N>) -> Option<Intersection<N>> {
+                    match intersection::plane_intersection(self, position, direction) {
+                        Some(ref crossing) if crossing.path_length < -na::convert::<f64, N>(DOT_PRODUCT_EPSILON) => None,
+                        crossing => crossing
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_planar_surface!(Rectangle, Trapezoid, Disc, Polygon);
@@ -1,8 +1,11 @@
 extern crate nalgebra as na;
+use na::{Point2, Point3, Vector3, Matrix4, Affine3};
 
 use super::traits::{Transform, Plane};
 use super::utils;
 
+use std::cmp::Ordering;
+
 use super::super::config::*;
 use super::super::error::*;
 
@@ -10,54 +13,53 @@ use super::super::error::*;
 // This is used instead of a closure since closures require 
 // heap allocation w/ trait objects (dynamic dispatch)
 #[derive(Debug)]
-pub struct Line {
-    pub yint: Real,
-    pub slope: Real
+pub struct Line<N: na::Real = Real> {
+    pub yint: N,
+    pub slope: N
 }
-impl Line {//
-    pub fn new_from_points(p1: &P2, p2: &P2) -> Self{
+impl<N: na::Real> Line<N> {//
+    pub fn new_from_points(p1: &Point2<N>, p2: &Point2<N>) -> Self{
         let slope = (p2.y - p1.y)/ (p2.x - p1.x);
 
         // y = m*x + [-m*x_0 + y_0] <- yint
-        let prod = -1 as Real *(p1.x*slope);
+        let prod = -(p1.x*slope);
         let yint = prod + p1.y;
 
         Line{yint: yint, slope:slope}
     }
 
     // a known y intercept / slope
-    pub fn new_from_values(yint: Real, slope: Real) -> Self {
+    pub fn new_from_values(yint: N, slope: N) -> Self {
         Line{yint: yint, slope: slope}
     }
 
-    pub fn new_from_y_axis_reflection(line: &Line) -> Self{
-        let new_slope = (-1 as Real) * line.slope;
+    pub fn new_from_y_axis_reflection(line: &Line<N>) -> Self{
+        let new_slope = -line.slope;
         Line{yint: line.yint , slope: new_slope }
     }
 
-    pub fn call(&self, point: &P2) -> P2 {
+    pub fn call(&self, point: &Point2<N>) -> Point2<N> {
         // "the maximum height we can have with that x value"
         let y = (self.slope * point.x) + self.yint;
         // "the maximum x value we can have when we have that y value"
         let x = (point.y - self.yint)/self.slope;
-        P2::new(x, y)
+        Point2::new(x, y)
     }
 }
 
 /// A struct for sensors of trapezoidal geometry
 #[derive(Debug)]
-pub struct Trapezoid{
-    half_height: Real,
-    normal: Vec3,
-    to_global: Aff3,
-    to_local : Aff3,
-    left_line: Line,    // equation of line used for bounds checking 
-    right_line: Line,  //   ''
-    max_half_width: Real,
-    min_half_width: Real
+pub struct Trapezoid<N: na::Real = Real>{
+    pub global_center: Point3<N>,
+    pub normal: Vector3<N>,       // unit normal vector of plane
+    pub plane_constant: N,        // D in Ax +By + Cz +D =0
+
+    to_global: Affine3<N>,
+    to_local : Affine3<N>,
+    corners: [Point2<N>; 4]  // counter clockwise, used for bounds checking
 }
 
-impl Trapezoid{
+impl<N: na::Real> Trapezoid<N>{
     /*
     /// This is the constructor for the rectangular geometry. It expects a 4x4 `nalgebra::Matrix4<f64>` that is invertible 
     /// and a 4 element array of `nalgebra::Point3<f64>`. If the matrix is not invertible it will return `Err(&str)`.
@@ -74,54 +76,56 @@ impl Trapezoid{
     /// let tfm_matrix : na::Matrix4<f64>= na::Matrix4::new(1.0,5.0,7.0,2.0,  3.0,5.0,7.0,4.0,  8.0,4.0,1.0,9.0, 2.0,6.0,4.0,8.0);
     /// let mut trap_sensor = kalman_rs::Trapezoid::new(trapezoid_points, tfm_matrix).unwrap();
     /// ```*/
-    pub fn new(base_top: Real, 
-            base_bot: Real, 
-            to_global_tfm_matrix: Mat4, 
-            height: Real) -> Result<Trapezoid, MatrixError> {
+    /// Both bases and the height must be positive, otherwise the corners do not form a convex
+    /// polygon and `SensorError::NotConvex` is returned like for a `Polygon`.
+    pub fn new(base_top: N, 
+            base_bot: N, 
+            to_global_tfm_matrix: Matrix4<N>, 
+            height: N) -> Result<Trapezoid<N>, Error> {
+
+        // also catches NaN
+        if [base_top, base_bot, height].iter().any(|length| length.partial_cmp(&na::zero()) != Some(Ordering::Greater)) {
+            return Err(Error::Sensor(SensorError::NotConvex))
+        }
         
-        let to_global_transform = Aff3::from_matrix_unchecked(to_global_tfm_matrix);
+        let to_global_transform = Affine3::from_matrix_unchecked(to_global_tfm_matrix);
 
         match to_global_transform.try_inverse(){
             
-            //TODO: find noraml vector calculation of the trapezoid
-
             Some(to_local_transform) => {
 
                 // calculate half lengths
-                let half_b1 = base_top/(2 as Real);
-                let half_b2 = base_bot/(2 as Real);
-                let half_height = height / (2 as Real);
+                let two : N = na::convert(2.0);
+                let half_b1 = base_top/two;
+                let half_b2 = base_bot/two;
+                let half_height = height / two;
 
-                // normal vector calculation
-                let normal_vector = utils::plane_normal_vector(half_b1, half_height);
+                let (center, normal_vector, plane_constant) = utils::plane_from_transform(|point| to_global_transform * point);
                 
-                // equations of lines along slope of trapezoid for bounding checks
-                let top_right_corner = P2::new(half_b1, half_height);
-                let bottom_right_corner = P2::new(half_b2, -half_height);
-                let right_line_eq = Line::new_from_points(&top_right_corner, &bottom_right_corner);
-                let left_line_eq = Line::new_from_y_axis_reflection(&right_line_eq);
+                let corners = [Point2::new(half_b2, -half_height),
+                               Point2::new(half_b1, half_height),
+                               Point2::new(-half_b1, half_height),
+                               Point2::new(-half_b2, -half_height)];
 
                 let trap = Trapezoid{
-                            half_height: half_height,
+                            global_center: center,
                             normal: normal_vector,
+                            plane_constant: plane_constant,
                             to_global: to_global_transform,
                             to_local: to_local_transform,
-                            left_line: left_line_eq,
-                            right_line: right_line_eq,
-                            max_half_width: half_b1.max(half_b2),
-                            min_half_width: half_b1.min(half_b2)};
+                            corners: corners};
                              
                 Ok(trap)
 
                 },
-            None => return Err(MatrixError::NonInvertible)
+            None => return Err(Error::Matrix(MatrixError::NonInvertible))
 
         }
     }
 }
 
 
-impl Transform for Trapezoid{
+impl<N: na::Real> Transform<N> for Trapezoid<N>{
     /*
     /// Converts a point in the global reference frame to a point in the local reference frame of the sensor.
     /// 
@@ -139,7 +143,7 @@ impl Transform for Trapezoid{
     /// 
     /// let global_point = trap_sensor.to_global(na::Point3::new(1.0, 2.0, 0.0));
     /// ```*/
-    fn to_global(&self, input_point: P3)-> P3{
+    fn to_global(&self, input_point: Point3<N>)-> Point3<N>{
         self.to_global * input_point
     }
     
@@ -163,9 +167,9 @@ impl Transform for Trapezoid{
     /// 
     /// let local_point = trap_sensor.to_local(na::Point3::new(4.0, 5.0, 6.0));
     /// ```*/
-    fn to_local(&self, input_point: P3) -> P2{
+    fn to_local(&self, input_point: Point3<N>) -> Point2<N>{
         let local = self.to_local * input_point;
-        return P2::new(local.x, local.y)
+        return Point2::new(local.x, local.y)
     }
 
     
@@ -188,41 +192,16 @@ impl Transform for Trapezoid{
     /// 
     /// let is_point_on_sensor = trap_sensor.contains_from_local(&na::Point2::new(1.0, 6.0));
     /// ```*/
-    fn inside(&self, input: &P2) -> bool {
-
-        let line = 
-            if (0 as Real) <= input.x {&self.right_line}
-            else {&self.left_line};
-
-        let new_point = line.call(&input);
-
-        let max_height = new_point.y.abs().min(self.half_height);
-        let x_abs = new_point.x.abs();
-
-        let max_width =  
-            if x_abs <= self.min_half_width {self.min_half_width}
-            else if x_abs >= self.max_half_width{self.max_half_width}
-            else if (x_abs >= self.min_half_width) && (x_abs <= self.max_half_width) {x_abs}
-            else{panic!("problem with bounds checking trapezoid")}; //TODO: fix this panic 
-
-        // let dbg_h = format!{"max height:  {}   actual   {}", &max_height, &$point.y};
-        // let dbg_w = format!{"max width:   {}   min width   {} x_abs value:   {}   input value:   {} \
-        //             choosen maxium   {}", $max_w, $min_w, x_abs, &$point.x, max_width};
-        // dbg!{dbg_h};
-        // dbg!{dbg_w};
-
-        if input.y.abs() <= max_height {
-            if input.x.abs() <= max_width {return true}
-            else{return false}
-        }
-        else{
-            return false
-        }
+    fn inside(&self, input: &Point2<N>) -> bool {
+        utils::polygon_contains(&self.corners, input)
+    }
 
+    fn boundary_distance(&self, input: &Point2<N>) -> N {
+        utils::polygon_signed_distance(&self.corners, input)
     }
 }
 
-impl Plane for Trapezoid{
+impl<N: na::Real> Plane<N> for Trapezoid<N>{
 
     /*
     /// Check if a given point is located on the same plane as the sensor
@@ -242,19 +221,14 @@ impl Plane for Trapezoid{
     /// 
     /// let on_sensor_plane = trap_sensor.on_plane(&Point3::new(1.0, 1.0, 0.0)); //true
     /// ```*/
-    fn on_plane(&self, input_point: &P3) -> bool {
-        let pv = P3::new(0.0, 0.0, 0.0) - input_point;
-
-        if self.normal.dot(&pv).abs() <= DOT_PRODUCT_EPSILON {
-            true
-        }
-        else{
-            false
-        }
-
+    fn on_plane(&self, input_point: &Point3<N>) -> bool {
+        utils::on_plane(&self.normal, self.plane_constant, input_point)
     }
 
-    fn plane_normal_vec(&self) -> &Vec3 {
+    fn plane_normal_vec(&self) -> &Vector3<N> {
         return &self.normal
     }
+    fn plane_constant(&self) -> N {
+        self.plane_constant
+    }
 }
@@ -1,6 +1,6 @@
 
 use nalgebra as na;
-use nalgebra :: {Point3, Vector3};
+use nalgebra :: {Point2, Point3, Vector3};
 use super::super::config::*;
 
 // NOTE: since the (slow) math beind this function is 100% certain for symmetric quadralaterals
@@ -9,7 +9,7 @@ use super::super::config::*;
 /// Checks if an input point is contained within the within the XY bounds of the sensor. A point with 
 /// any nonzero Z value needs to also use `traits::Plane::on_plane` to ensure that the point falls
 /// on the same plane as the sensor. 
-pub fn quadralateral_contains(points: &[P3;4], check_point: &P3)->bool{
+pub fn quadralateral_contains<N: na::Real>(points: &[Point3<N>;4], check_point: &Point3<N>)->bool{
     // subtract points so we can make position vectors
     let am_vec = points[0] - check_point;
     let ab_vec = points[0] - points[1];
@@ -21,12 +21,12 @@ pub fn quadralateral_contains(points: &[P3;4], check_point: &P3)->bool{
     // A and D are opposite corners, M is the input point
     // if true: the point is inside a quadralateral
     let am_dot = am_vec.dot(&ab_vec);
-    if 0.0 < am_vec.dot(&ab_vec){
+    if na::zero::<N>() < am_vec.dot(&ab_vec){
         if am_dot < ab_vec.dot(&ab_vec){
 
             let am_dot = am_vec.dot(&ad_vec);
 
-            if 0.0 < am_vec.dot(&ad_vec){
+            if na::zero::<N>() < am_vec.dot(&ad_vec){
                 if am_dot < ad_vec.dot(&ad_vec){
                     return true
                 }
@@ -37,16 +37,131 @@ pub fn quadralateral_contains(points: &[P3;4], check_point: &P3)->bool{
 }
 
 
-/// Calculates the vector normal to three input points
-pub fn plane_normal_vector(side_1: Real, side_2: Real) -> Vec3 {
-    let point_1 = P3::new(side_1, 0.0, 0.0);
-    let point_2 = P3::new(0.0, side_2, 0.0);
-    let point_3 = P3::new(0.0, 0.0, 0.0);
+/// Global origin and local x and y axes of a sensor whose local x-y plane is placed by `to_global`,
+/// either an affine transformation (`|point| tfm * point`) or `Transform::to_global` of a sensor
+pub fn local_axes<N: na::Real, F: Fn(Point3<N>) -> Point3<N>>(to_global: F) -> (Point3<N>, Vector3<N>, Vector3<N>) {
+    let origin = to_global(Point3::origin());
+    let axis_0 = to_global(Point3::new(N::one(), na::zero(), na::zero())) - origin;
+    let axis_1 = to_global(Point3::new(na::zero(), N::one(), na::zero())) - origin;
 
-    let v1 = point_1 -point_2;
-    let v2 = point_1 - point_3;
+    (origin, axis_0, axis_1)
+}
+
+/// Global center, unit normal and plane constant (D in Ax + By + Cz + D = 0) of a planar sensor
+/// whose local x-y plane is placed by `to_global`
+pub fn plane_from_transform<N: na::Real, F: Fn(Point3<N>) -> Point3<N>>(to_global: F) -> (Point3<N>, Vector3<N>, N) {
+    let (center, axis_0, axis_1) = local_axes(to_global);
+    let normal = axis_0.cross(&axis_1).normalize();
+    let plane_constant = -normal.dot(&center.coords);
+
+    (center, normal, plane_constant)
+}
+
+/// Checks that a global point lies on the plane with `normal` (unit length) and `plane_constant`
+pub fn on_plane<N: na::Real>(normal: &Vector3<N>, plane_constant: N, input_point: &Point3<N>) -> bool {
+    (normal.dot(&input_point.coords) + plane_constant).abs() <= na::convert(DOT_PRODUCT_EPSILON)
+}
+
+
+// The polygon functions below expect the vertices of a convex polygon in counter clockwise order,
+// as checked by `polygon_is_convex` and `polygon_signed_area`.
+
+// z component of the cross product of (b - a) and (c - a)
+fn turn<N: na::Real>(a: &Point2<N>, b: &Point2<N>, c: &Point2<N>) -> N {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// Twice the signed area of a polygon, positive when the vertices run counter clockwise
+pub fn polygon_signed_area<N: na::Real>(vertices: &[Point2<N>]) -> N {
+    let mut area = na::zero::<N>();
+    for i in 0..vertices.len() {
+        let next = &vertices[(i + 1) % vertices.len()];
+        area += vertices[i].x * next.y - next.x * vertices[i].y;
+    }
+    area
+}
+
+/// Checks that the vertices form a simple convex polygon in either direction. Consecutive collinear
+/// edges are allowed but repeated vertices and polygons without area are not.
+pub fn polygon_is_convex<N: na::Real>(vertices: &[Point2<N>]) -> bool {
+    let count = vertices.len();
+    if count < 3 {
+        return false
+    }
+
+    let mut positive = false;
+    let mut negative = false;
+    let mut total_turn = na::zero::<N>();
+
+    for i in 0..count {
+        let a = &vertices[i];
+        let b = &vertices[(i + 1) % count];
+        let c = &vertices[(i + 2) % count];
+
+        let edge = b - a;
+        let next_edge = c - b;
+        if edge.norm() == na::zero() {
+            return false
+        }
 
-    v1.cross(&v2) //cross product yields normal vector of the plane
+        let cross = turn(a, b, c);
+        if cross > na::zero() {positive = true}
+        if cross < na::zero() {negative = true}
+
+        total_turn += cross.atan2(edge.dot(&next_edge));
+    }
+
+    // a star has turns of one sign but winds around more than once
+    let winding_tolerance : N = na::convert(1e-6);
+    !(positive && negative) && (total_turn.abs() - N::two_pi()).abs() < winding_tolerance
+        && polygon_signed_area(vertices).abs() > na::zero()
+}
+
+/// Checks if a point lies inside (or on the edge of) a convex polygon
+pub fn polygon_contains<N: na::Real>(vertices: &[Point2<N>], point: &Point2<N>) -> bool {
+    for i in 0..vertices.len() {
+        let next = &vertices[(i + 1) % vertices.len()];
+        if turn(&vertices[i], next, point) < na::zero() {
+            return false
+        }
+    }
+    true
+}
+
+/// Distance from a point to the segment between `start` and `end`
+pub fn segment_distance<N: na::Real>(start: &Point2<N>, end: &Point2<N>, point: &Point2<N>) -> N {
+    let edge = end - start;
+    let length_squared = edge.norm_squared();
+    if length_squared == na::zero() {
+        return (point - start).norm()
+    }
+
+    let fraction = (edge.dot(&(point - start)) / length_squared).max(na::zero()).min(N::one());
+    (point - (start + edge * fraction)).norm()
+}
+
+/// Signed distance from a point to the boundary of a convex polygon, negative inside
+pub fn polygon_signed_distance<N: na::Real>(vertices: &[Point2<N>], point: &Point2<N>) -> N {
+    let distance = polygon_edge_distance(vertices, point);
+    if polygon_contains(vertices, point) {-distance} else {distance}
+}
+
+/// Signed distance from a point to the boundary of a box centered on the origin, negative inside
+pub fn box_signed_distance<N: na::Real>(half_x: N, half_y: N, point: &Point2<N>) -> N {
+    let dx = point.x.abs() - half_x;
+    let dy = point.y.abs() - half_y;
+
+    let outside = (dx.max(na::zero()) * dx.max(na::zero()) + dy.max(na::zero()) * dy.max(na::zero())).sqrt();
+    outside + dx.max(dy).min(na::zero())
+}
+
+/// Distance from a point (inside or outside) to the nearest edge of a polygon
+pub fn polygon_edge_distance<N: na::Real>(vertices: &[Point2<N>], point: &Point2<N>) -> N {
+    let mut distance = segment_distance(&vertices[vertices.len() - 1], &vertices[0], point);
+    for i in 0..vertices.len() - 1 {
+        distance = distance.min(segment_distance(&vertices[i], &vertices[i + 1], point));
+    }
+    distance
 }
 
 
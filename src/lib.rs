@@ -7,6 +7,7 @@ pub mod config;
 pub mod geometry;
 pub mod filter;
 pub mod error;
+pub mod vertex;
 
 pub use geometry::rectangle::Rectangle;
 pub use geometry::trapezoid::Trapezoid;
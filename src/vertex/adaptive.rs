@@ -0,0 +1,179 @@
+use super::super::config::*;
+use super::super::error::*;
+
+use super::super::filter::perigee::PerigeeParameters;
+use super::kalman::{self, Vertex, VertexFitOptions};
+
+// Adaptive multi-vertex fit: every track takes part in the fit of every vertex with a weight
+//
+//      w_ik = exp(-chi2_ik / 2T) / (exp(-chi2_cut / 2T) + sum_j exp(-chi2_ij / 2T))
+//
+// where chi2_ik is the compatibility of track i with vertex k. Vertices compete for tracks and a
+// track incompatible with all of them is pushed towards zero weight. The temperature T follows an
+// annealing schedule that should decrease towards 1, as in `filter::annealing`.
+
+/// Settings of the vertex finder
+#[derive(Debug, Clone)]
+pub struct VertexFinderOptions {
+    pub fit: VertexFitOptions,              // the prior should leave the vertex free along z
+    pub temperatures: Vec<Real>,            // annealing schedule
+    pub chi_squared_cut: Real,
+    pub seed_window: Real,                  // z0 distance (mm) of tracks clustered into a seed
+    pub min_tracks: usize,                  // compatible tracks needed to keep a vertex
+    pub max_vertices: usize
+}
+
+impl VertexFinderOptions {
+    pub fn new(fit: VertexFitOptions) -> Self {
+        VertexFinderOptions{fit: fit,
+                            temperatures: vec![256., 64., 16., 4., 2., 1.],
+                            chi_squared_cut: 9.,
+                            seed_window: 1.,
+                            min_tracks: 2,
+                            max_vertices: 100}
+    }
+}
+
+/// Adaptive fit of a single vertex starting from the prior of `options`. Outliers end up with a
+/// small weight instead of pulling the vertex.
+pub fn fit(
+    tracks: &[PerigeeParameters],
+    options: &VertexFitOptions,
+    temperatures: &[Real],
+    chi_squared_cut: Real
+    ) -> Result<Vertex, Error> {
+
+    let mut vertices = multi_fit(tracks, &[options.prior_position], options, temperatures, chi_squared_cut)?;
+    Ok(vertices.remove(0))
+}
+
+/// Adaptive fit of one vertex per seed position. Every vertex lists all tracks, with the weight each
+/// one was given in that vertex. Returns `ConfigError::InvalidValue` if a temperature is not positive.
+pub fn multi_fit(
+    tracks: &[PerigeeParameters],
+    seeds: &[P3],
+    options: &VertexFitOptions,
+    temperatures: &[Real],
+    chi_squared_cut: Real
+    ) -> Result<Vec<Vertex>, Error> {
+
+    // a temperature of zero divides by zero in the assignment weights
+    if temperatures.iter().any(|temperature| temperature.is_nan() || *temperature <= 0.) {
+        return Err(ConfigError::InvalidValue("temperatures need to be positive".to_string()).into())
+    }
+
+    // tracks without weight leave each vertex at its seed, which gives the starting compatibilities
+    let no_weights = vec![0.; tracks.len()];
+    let mut vertices = Vec::with_capacity(seeds.len());
+    for seed in seeds.iter() {
+        vertices.push(kalman::fit_weighted(tracks, &no_weights, &seeded_options(options, seed))?);
+    }
+
+    for temperature in temperatures.iter() {
+        let weights = assignment_weights(&vertices, tracks.len(), *temperature, chi_squared_cut);
+
+        for k in 0..vertices.len() {
+            let vertex_options = seeded_options(options, &vertices[k].position);
+            vertices[k] = kalman::fit_weighted(tracks, &weights[k], &vertex_options)?;
+        }
+    }
+
+    Ok(vertices)
+}
+
+/// Iterative vertex finder. A seed is placed at the densest cluster of z0 among the tracks not yet
+/// compatible with any vertex, after which all vertices are refitted together. The new vertex is kept
+/// if at least `min_tracks` tracks end up with a weight above one half.
+pub fn find(
+    tracks: &[PerigeeParameters],
+    options: &VertexFinderOptions
+    ) -> Result<Vec<Vertex>, Error> {
+
+    let mut vertices : Vec<Vertex> = Vec::new();
+    let mut seed_candidates = vec![true; tracks.len()];
+
+    while vertices.len() < options.max_vertices {
+        let cluster = match densest_z0_cluster(tracks, &seed_candidates, options.seed_window) {
+            Some(cluster) if cluster.len() >= options.min_tracks => cluster,
+            _ => break
+        };
+
+        let seed_z = cluster.iter().map(|i| tracks[*i].z0()).sum::<Real>() / cluster.len() as Real;
+        let mut seeds : Vec<P3> = vertices.iter().map(|vertex| vertex.position).collect();
+        seeds.push(P3::new(options.fit.prior_position.x,
+                           options.fit.prior_position.y,
+                           options.fit.perigee.beam_position.z + seed_z));
+
+        let candidates = multi_fit(tracks, &seeds, &options.fit, &options.temperatures, options.chi_squared_cut)?;
+
+        let compatible = candidates.last().unwrap().tracks.iter().filter(|track| track.weight > 0.5).count();
+        if compatible >= options.min_tracks {
+            vertices = candidates;
+        }
+
+        // the seeding tracks are not used again even if the vertex was rejected
+        for i in cluster.iter() {
+            seed_candidates[*i] = false;
+        }
+        for vertex in vertices.iter() {
+            for (i, track) in vertex.tracks.iter().enumerate() {
+                if track.weight > 0.5 {
+                    seed_candidates[i] = false;
+                }
+            }
+        }
+    }
+
+    Ok(vertices)
+}
+
+// copy of the fit options with the prior moved to `position`
+fn seeded_options(options: &VertexFitOptions, position: &P3) -> VertexFitOptions {
+    let mut seeded = options.clone();
+    seeded.prior_position = position.clone();
+    seeded
+}
+
+// `weights[k][i]` is the weight of track i in vertex k
+fn assignment_weights(
+    vertices: &[Vertex],
+    track_count: usize,
+    temperature: Real,
+    chi_squared_cut: Real
+    ) -> Vec<Vec<Real>> {
+
+    let cutoff = (-chi_squared_cut / (2. * temperature)).exp();
+    let mut weights = vec![vec![0.; track_count]; vertices.len()];
+
+    for i in 0..track_count {
+        let likelihoods : Vec<Real> = vertices.iter()
+                            .map(|vertex| (-vertex.tracks[i].chi_squared / (2. * temperature)).exp())
+                            .collect();
+        let total = cutoff + likelihoods.iter().sum::<Real>();
+
+        for (k, likelihood) in likelihoods.iter().enumerate() {
+            weights[k][i] = likelihood / total;
+        }
+    }
+
+    weights
+}
+
+// indices of the candidate tracks within `window` of the z0 with the most such neighbours
+fn densest_z0_cluster(
+    tracks: &[PerigeeParameters],
+    seed_candidates: &[bool],
+    window: Real
+    ) -> Option<Vec<usize>> {
+
+    let candidates : Vec<usize> = (0..tracks.len()).filter(|i| seed_candidates[*i]).collect();
+
+    candidates.iter()
+        .map(|i| {
+            candidates.iter()
+                .cloned()
+                .filter(|j| (tracks[*j].z0() - tracks[*i].z0()).abs() <= window)
+                .collect::<Vec<usize>>()
+        })
+        .max_by_key(|cluster| cluster.len())
+}
@@ -0,0 +1,201 @@
+use super::super::config::*;
+use super::super::error::*;
+
+use super::super::filter::perigee::{PerigeeParameters, PerigeePropagation};
+use super::super::geometry::PerigeeSurface;
+use super::linearize::{self, LinearizedTrack};
+
+// Kalman vertex fit after Billoir / Fruhwirth. Each track adds its perigee parameters q as a
+// measurement of the vertex v and its own momentum p through q = A v + B p (see `linearize`).
+// The momentum is eliminated from the vertex update with
+//
+//      W  = (B' G B)^-1                    G: weight matrix of the track (inverse covariance)
+//      Gb = G - G B W B' G
+//
+// so that the vertex weight matrix grows by A' Gb A with every track. Once the vertex is known the
+// momenta are refitted under the constraint that the tracks leave the vertex.
+
+/// Settings of the vertex fit
+#[derive(Debug, Clone)]
+pub struct VertexFitOptions {
+    pub perigee: PerigeeSurface,            // surface the track parameters are given on
+    pub propagation: PerigeePropagation,
+    pub prior_position: P3,                 // usually the beam spot
+    pub prior_cov_mat: Mat3,
+    pub max_iterations: usize,              // number of times the tracks are relinearized
+    pub tolerance: Real                     // vertex shift (mm) below which the fit has converged
+}
+
+impl VertexFitOptions {
+    /// Options with a prior at the beam position that is too wide to constrain the vertex
+    pub fn new(perigee: PerigeeSurface, propagation: PerigeePropagation) -> Self {
+        VertexFitOptions{prior_position: perigee.beam_position,
+                         prior_cov_mat: Mat3::identity() * 1e6,
+                         perigee: perigee,
+                         propagation: propagation,
+                         max_iterations: 10,
+                         tolerance: 1e-6}
+    }
+}
+
+/// A track refitted under the vertex constraint
+#[derive(Debug, Clone)]
+pub struct FittedTrack {
+    pub momentum: Vec3,                     // (phi, theta, q/p) at the vertex
+    pub momentum_cov_mat: Mat3,
+    pub chi_squared: Real,                  // compatibility of the track with the vertex
+    pub weight: Real
+}
+
+/// Result of a vertex fit
+#[derive(Debug, Clone)]
+pub struct Vertex {
+    pub position: P3,
+    pub cov_mat: Mat3,
+    pub chi_squared: Real,
+    pub ndf: Real,
+    pub tracks: Vec<FittedTrack>
+}
+
+/// Fits a common vertex to `tracks`
+pub fn fit(
+    tracks: &[PerigeeParameters],
+    options: &VertexFitOptions
+    ) -> Result<Vertex, MatrixError> {
+
+    let weights = vec![1.; tracks.len()];
+    fit_weighted(tracks, &weights, options)
+}
+
+/// Fits a common vertex to `tracks`, each contributing with the matching weight in [0, 1]. A track
+/// with zero weight does not move the vertex but is still refitted and given a chi squared.
+pub fn fit_weighted(
+    tracks: &[PerigeeParameters],
+    weights: &[Real],
+    options: &VertexFitOptions
+    ) -> Result<Vertex, MatrixError> {
+
+    if tracks.len() != weights.len() {
+        panic!("vector lengths need to be the same length")
+    }
+
+    let prior_weight_mat = match options.prior_cov_mat.try_inverse() {
+        Some(inverse) => inverse,
+        None => return Err(MatrixError::NonInvertible)
+    };
+
+    let mut linearization_point = options.prior_position;
+    let mut momenta : Vec<Vec3> = tracks.iter().map(|track| Vec3::new(track.state_vec[2], track.state_vec[3], track.state_vec[4])).collect();
+
+    let mut iteration = 0;
+    loop {
+        let vertex = fit_linearized(tracks, weights, options, &prior_weight_mat, &linearization_point, &momenta)?;
+        iteration += 1;
+
+        let shift = (vertex.position - linearization_point).norm();
+        if shift < options.tolerance || iteration >= options.max_iterations {
+            return Ok(vertex)
+        }
+
+        linearization_point = vertex.position;
+        momenta = vertex.tracks.iter().map(|track| track.momentum).collect();
+    }
+}
+
+// weight form of a track's contribution at one linearization point
+struct TrackTerms {
+    linearized: LinearizedTrack,
+    reduced_state_vec: Vec5,
+    weight_mat: Mat5,                       // G
+    momentum_cov_mat: Mat3,                 // W
+    reduced_weight_mat: Mat5                // Gb
+}
+
+fn track_terms(
+    track: &PerigeeParameters,
+    linearized: LinearizedTrack
+    ) -> Result<TrackTerms, MatrixError> {
+
+    let weight_mat = match track.cov_mat.try_inverse() {
+        Some(inverse) => inverse,
+        None => return Err(MatrixError::NonInvertible)
+    };
+
+    let b = &linearized.momentum_jacobian;
+    let momentum_cov_mat = match (b.transpose() * weight_mat * b).try_inverse() {
+        Some(inverse) => inverse,
+        None => return Err(MatrixError::NonInvertible)
+    };
+
+    let reduced_weight_mat = weight_mat - weight_mat * b * momentum_cov_mat * b.transpose() * weight_mat;
+
+    Ok(TrackTerms{reduced_state_vec: linearized.reduced_state_vec(&track.state_vec),
+                  linearized: linearized,
+                  weight_mat: weight_mat,
+                  momentum_cov_mat: momentum_cov_mat,
+                  reduced_weight_mat: reduced_weight_mat})
+}
+
+fn fit_linearized(
+    tracks: &[PerigeeParameters],
+    weights: &[Real],
+    options: &VertexFitOptions,
+    prior_weight_mat: &Mat3,
+    linearization_point: &P3,
+    momenta: &[Vec3]
+    ) -> Result<Vertex, MatrixError> {
+
+    let mut terms = Vec::with_capacity(tracks.len());
+    for (track, momentum) in tracks.iter().zip(momenta.iter()) {
+        let linearized = linearize::linearize(linearization_point, momentum, &options.perigee, &options.propagation);
+        terms.push(track_terms(track, linearized)?);
+    }
+
+    // filter: every track adds to the weight matrix of the vertex
+    let mut vertex_weight_mat = prior_weight_mat.clone();
+    let mut weighted_position = prior_weight_mat * options.prior_position.coords;
+
+    for (term, weight) in terms.iter().zip(weights.iter()) {
+        let a = &term.linearized.position_jacobian;
+        vertex_weight_mat += a.transpose() * term.reduced_weight_mat * a * *weight;
+        weighted_position += a.transpose() * term.reduced_weight_mat * term.reduced_state_vec * *weight;
+    }
+
+    let cov_mat = match vertex_weight_mat.try_inverse() {
+        Some(inverse) => inverse,
+        None => return Err(MatrixError::NonInvertible)
+    };
+    let position = cov_mat * weighted_position;
+
+    // smoother: refit the momenta with the final vertex
+    let prior_residual = position - options.prior_position.coords;
+    let mut chi_squared = (prior_residual.transpose() * prior_weight_mat * prior_residual)[0];
+    let mut sum_of_weights = 0.;
+
+    let mut fitted_tracks = Vec::with_capacity(tracks.len());
+    for (term, weight) in terms.iter().zip(weights.iter()) {
+        let a = &term.linearized.position_jacobian;
+        let b = &term.linearized.momentum_jacobian;
+
+        let momentum_gain = term.momentum_cov_mat * b.transpose() * term.weight_mat;
+        let momentum = momentum_gain * (term.reduced_state_vec - a * position);
+        let momentum_cov_mat = term.momentum_cov_mat + momentum_gain * a * cov_mat * a.transpose() * momentum_gain.transpose();
+
+        let residual = term.reduced_state_vec - a * position - b * momentum;
+        let track_chi_squared = (residual.transpose() * term.weight_mat * residual)[0];
+
+        chi_squared += track_chi_squared * *weight;
+        sum_of_weights += *weight;
+
+        fitted_tracks.push(FittedTrack{momentum: momentum,
+                                       momentum_cov_mat: momentum_cov_mat,
+                                       chi_squared: track_chi_squared,
+                                       weight: *weight});
+    }
+
+    Ok(Vertex{position: P3::from(position),
+              cov_mat: cov_mat,
+              chi_squared: chi_squared,
+              ndf: 2. * sum_of_weights - 3.,
+              tracks: fitted_tracks})
+}
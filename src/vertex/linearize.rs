@@ -0,0 +1,94 @@
+use super::super::config::*;
+
+use super::super::filter::perigee::{self, PerigeePropagation};
+use super::super::filter::prediction::JACOBIAN_STEP;
+use super::super::geometry::PerigeeSurface;
+
+// A track leaving the vertex v with momentum p = (phi, theta, q/p) reaches the beam line with perigee
+// parameters h(v, p). Around a linearization point (v0, p0) this is approximated by
+//
+//      h(v, p) = h(v0, p0) + A (v - v0) + B (p - p0)
+//
+// which is the measurement model of the Kalman vertex fit.
+
+/// Track model expanded around a vertex position and momentum
+#[derive(Debug, Clone)]
+pub struct LinearizedTrack {
+    pub position_jacobian: Mat5x3,          // A
+    pub momentum_jacobian: Mat5x3,          // B
+    pub state_vec: Vec5,                    // h(v0, p0)
+    pub expansion_point: Vec5               // A v0 + B p0
+}
+
+impl LinearizedTrack {
+    /// Perigee parameters with the constant term of the expansion removed, so that they are
+    /// modelled by A v + B p. The difference in phi is wrapped around the linearization point.
+    pub fn reduced_state_vec(&self, perigee_state_vec: &Vec5) -> Vec5 {
+        let mut difference = perigee_state_vec - self.state_vec;
+        difference[2] = perigee::wrap_angle(difference[2]);
+
+        self.expansion_point + difference
+    }
+}
+
+/// Perigee parameters of a track leaving `vertex` with `momentum` (phi, theta, q/p)
+pub fn vertex_state_vec(
+    vertex: &P3,
+    momentum: &Vec3,
+    perigee: &PerigeeSurface,
+    propagation: &PerigeePropagation
+    ) -> Vec5 {
+
+    let (phi, theta) = (momentum[0], momentum[1]);
+    let direction = Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+
+    perigee::global_perigee_state_vec(vertex, &direction, momentum[2], perigee, propagation)
+}
+
+/// Expands the track model around `vertex` and `momentum`. Both jacobians are found by central differences.
+pub fn linearize(
+    vertex: &P3,
+    momentum: &Vec3,
+    perigee: &PerigeeSurface,
+    propagation: &PerigeePropagation
+    ) -> LinearizedTrack {
+
+    let mut position_jacobian = Mat5x3::zeros();
+    let mut momentum_jacobian = Mat5x3::zeros();
+
+    for j in 0..3 {
+        let step = JACOBIAN_STEP * vertex.coords[j].abs().max(1.);
+        let mut forward = vertex.clone();
+        forward[j] += step;
+        let mut backward = vertex.clone();
+        backward[j] -= step;
+
+        let column = difference_column(&vertex_state_vec(&forward, momentum, perigee, propagation),
+                                       &vertex_state_vec(&backward, momentum, perigee, propagation),
+                                       step);
+        position_jacobian.set_column(j, &column);
+
+        let step = JACOBIAN_STEP * momentum[j].abs().max(1.);
+        let mut forward = momentum.clone();
+        forward[j] += step;
+        let mut backward = momentum.clone();
+        backward[j] -= step;
+
+        let column = difference_column(&vertex_state_vec(vertex, &forward, perigee, propagation),
+                                       &vertex_state_vec(vertex, &backward, perigee, propagation),
+                                       step);
+        momentum_jacobian.set_column(j, &column);
+    }
+
+    LinearizedTrack{state_vec: vertex_state_vec(vertex, momentum, perigee, propagation),
+                    expansion_point: position_jacobian * vertex.coords + momentum_jacobian * momentum,
+                    position_jacobian: position_jacobian,
+                    momentum_jacobian: momentum_jacobian}
+}
+
+// central difference of the perigee parameters, wrapping phi
+fn difference_column(forward: &Vec5, backward: &Vec5, step: Real) -> Vec5 {
+    let mut column = forward - backward;
+    column[2] = perigee::wrap_angle(column[2]);
+    column / (2. * step)
+}
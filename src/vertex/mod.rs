@@ -0,0 +1,3 @@
+pub mod linearize;
+pub mod kalman;
+pub mod adaptive;
@@ -0,0 +1,93 @@
+use kalman_rs::geometry::PerigeeSurface;
+use kalman_rs::filter::perigee::{PerigeeParameters, PerigeePropagation};
+use kalman_rs::vertex::adaptive::{self, VertexFinderOptions};
+use kalman_rs::vertex::{kalman::VertexFitOptions, linearize};
+use kalman_rs::config::*;
+use kalman_rs::error::{Error, ConfigError};
+
+
+fn options() -> VertexFitOptions {
+    let mut options = VertexFitOptions::new(PerigeeSurface::origin(), PerigeePropagation::Helix{b_field: 2.});
+    options.prior_cov_mat = Mat3::from_diagonal(&Vec3::new(1., 1., 1e6));
+    options
+}
+
+// tracks leaving `vertex` with spread out directions
+fn tracks_from(vertex: &P3, count: usize, options: &VertexFitOptions) -> Vec<PerigeeParameters> {
+    (0..count).map(|i| {
+        let momentum = Vec3::new(-3. + 1.3 * i as Real, 0.8 + 0.3 * i as Real, if i % 2 == 0 {0.5} else {-0.4});
+        PerigeeParameters{state_vec: linearize::vertex_state_vec(vertex, &momentum, &options.perigee, &options.propagation),
+                          cov_mat: Mat5::from_diagonal(&Vec5::new(1e-4, 1e-4, 1e-6, 1e-6, 1e-6))}
+    })
+    .collect()
+}
+
+#[test]
+fn adaptive_fit_down_weights_outlier() {
+    let options = options();
+    let truth = P3::new(0.01, -0.02, 4.);
+    let mut tracks = tracks_from(&truth, 5, &options);
+
+    // a track from elsewhere which pulls a plain fit away from the vertex
+    let mut outlier = tracks_from(&P3::new(0., 0., 4.3), 1, &options).remove(0);
+    outlier.state_vec[0] += 0.05;
+    tracks.push(outlier);
+
+    let mut seeded = options.clone();
+    seeded.prior_position = P3::new(0., 0., 4.);
+    let vertex = adaptive::fit(&tracks, &seeded, &[64., 16., 4., 1.], 9.).unwrap();
+
+    assert!((vertex.position - truth).norm() < 1e-4, "found {} expected {}", vertex.position, truth);
+    assert!(vertex.tracks[5].weight < 1e-3);
+    for track in vertex.tracks.iter().take(5) {
+        assert!(track.weight > 0.9);
+    }
+}
+
+#[test]
+fn finder_separates_pile_up() {
+    let options = options();
+    let first = P3::new(0., 0., -10.);
+    let second = P3::new(0., 0., 15.);
+
+    let mut tracks = tracks_from(&first, 5, &options);
+    tracks.extend(tracks_from(&second, 4, &options));
+    // a lone track cannot make a vertex of its own
+    tracks.extend(tracks_from(&P3::new(0., 0., 60.), 1, &options));
+
+    let vertices = adaptive::find(&tracks, &VertexFinderOptions::new(options)).unwrap();
+    assert_eq!(vertices.len(), 2);
+
+    let (near_first, near_second) = if vertices[0].position.z < vertices[1].position.z {(0, 1)} else {(1, 0)};
+    assert!((vertices[near_first].position - first).norm() < 1e-4);
+    assert!((vertices[near_second].position - second).norm() < 1e-4);
+
+    for i in 0..tracks.len() {
+        let owner = match i {
+            0..=4 => Some(near_first),
+            5..=8 => Some(near_second),
+            _ => None
+        };
+        for (k, vertex) in vertices.iter().enumerate() {
+            if Some(k) == owner {
+                assert!(vertex.tracks[i].weight > 0.9);
+            }
+            else {
+                assert!(vertex.tracks[i].weight < 1e-3);
+            }
+        }
+    }
+}
+
+#[test]
+fn rejects_non_positive_temperatures() {
+    let options = options();
+    let tracks = tracks_from(&P3::new(0., 0., 4.), 3, &options);
+
+    for temperatures in [[4., 0.], [4., -1.]].iter() {
+        match adaptive::fit(&tracks, &options, temperatures, 9.) {
+            Err(Error::Config(ConfigError::InvalidValue(_))) => {},
+            _ => panic!("expected an invalid temperature error")
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::combinatorial::TrackCandidate;
+use kalman_rs::filter::ambiguity::{self, AmbiguityOptions};
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::config::*;
+
+
+// two hits on each of `num` stacked sensors
+fn inputs(num: usize) -> (Vec<Vec<Mat2>>, Vec<Vec<Vec2>>, Vec<Rectangle>) {
+    let noise = vec![vec![Mat2::identity() * 0.01; 2]; num];
+    let measurements = (0..num).map(|i| vec![Vec2::new(0.01 * i as Real, 0.), Vec2::new(0.1, 0.02 * i as Real)]).collect();
+    let sensors = (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap()).collect();
+    (noise, measurements, sensors)
+}
+
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0., std::f64::consts::FRAC_PI_2, 0.), Mat5::identity())
+}
+
+
+// build a candidate where every hit contributes the same chi squared
+fn track(hits: Vec<Option<usize>>, chi_squared_per_hit: Real) -> TrackCandidate {
+    let len = hits.len();
+    let chi_squared_inc : Vec<Real> = hits.iter()
+                            .map(|hit| if hit.is_some() {chi_squared_per_hit} else {0.})
+                            .collect();
+    let ndf = 2 * hits.iter().filter(|hit| hit.is_some()).count();
+
+    let (seed_x, seed_c) = seed();
+
+    TrackCandidate{seed_state_vec: seed_x,
+                   seed_cov_mat: seed_c,
+                   hits: hits,
+                   filt_state_vec: vec![Vec5::zeros(); len],
+                   filt_cov_mat: vec![Mat5::identity(); len],
+                   chi_squared: chi_squared_inc.iter().sum(),
+                   chi_squared_inc: chi_squared_inc,
+                   ndf: ndf,
+                   holes: 0}
+}
+
+#[test]
+fn disjoint_tracks_untouched() {
+    let (noise, measurements, sensors) = inputs(3);
+    let tracks = vec![
+        track(vec![Some(0), Some(0), Some(0)], 1.0),
+        track(vec![Some(1), Some(1), Some(1)], 2.0)
+    ];
+
+    let resolved = ambiguity::resolve(tracks, &noise, &measurements, &sensors, &AmbiguityOptions::new(0, 3));
+
+    assert_eq!(resolved.len(), 2);
+    assert_eq!(resolved[0].hits, vec![Some(0), Some(0), Some(0)]);
+}
+
+#[test]
+fn worse_duplicate_removed() {
+    let (noise, measurements, sensors) = inputs(3);
+    let tracks = vec![
+        track(vec![Some(0), Some(1), Some(0)], 3.0),
+        track(vec![Some(0), Some(1), Some(1)], 1.0)
+    ];
+
+    let resolved = ambiguity::resolve(tracks, &noise, &measurements, &sensors, &AmbiguityOptions::new(1, 3));
+
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].hits, vec![Some(0), Some(1), Some(1)]);
+}
+
+#[test]
+fn shared_hit_stripped_from_weaker_track() {
+    let (noise, measurements, sensors) = inputs(4);
+    let tracks = vec![
+        track(vec![Some(0), Some(0), Some(0), Some(0)], 1.0),
+        track(vec![Some(1), Some(1), Some(1), Some(0)], 2.0)
+    ];
+
+    let resolved = ambiguity::resolve(tracks, &noise, &measurements, &sensors, &AmbiguityOptions::new(1, 3));
+
+    assert_eq!(resolved.len(), 2);
+    assert_eq!(resolved[1].hits, vec![Some(1), Some(1), Some(1), None]);
+    assert_eq!(resolved[1].ndf, 6);
+    assert_eq!(resolved[1].holes, 1);
+
+    // the weaker track is refit without the hit it lost
+    let (seed_x, seed_c) = seed();
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    for i in 0..4 {
+        kf.predict(&sensors[i]).unwrap();
+        if i < 3 {
+            kf.update(&measurements[i][1], &noise[i][1]).unwrap();
+        }
+    }
+
+    assert!((resolved[1].filt_state_vec[3] - kf.state_vec()).norm() < 1e-9);
+    assert!((resolved[1].filt_cov_mat[3] - kf.cov_mat()).norm() < 1e-9);
+    assert!((resolved[1].chi_squared - kf.chi_squared()).abs() < 1e-9);
+    assert_eq!(resolved[1].chi_squared_inc[3], 0.0);
+}
+
+#[test]
+fn short_tracks_dropped() {
+    let (noise, measurements, sensors) = inputs(3);
+    let tracks = vec![
+        track(vec![Some(0), None, None], 0.5),
+        track(vec![Some(1), Some(1), Some(1)], 2.0)
+    ];
+
+    let resolved = ambiguity::resolve(tracks, &noise, &measurements, &sensors, &AmbiguityOptions::new(0, 2));
+
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].hits, vec![Some(1), Some(1), Some(1)]);
+}
+
+#[test]
+fn dropped_tracks_release_their_hits() {
+    let (noise, measurements, sensors) = inputs(4);
+    let tracks = vec![
+        track(vec![Some(0), Some(0), Some(0), Some(0)], 1.0),
+        // loses two hits to the first track and falls below `min_hits`
+        track(vec![Some(0), Some(0), Some(1), Some(1)], 1.5),
+        track(vec![Some(1), Some(1), Some(1), None], 1.0)
+    ];
+
+    let resolved = ambiguity::resolve(tracks, &noise, &measurements, &sensors, &AmbiguityOptions::new(3, 3));
+
+    assert_eq!(resolved.len(), 2);
+    assert_eq!(resolved[1].hits, vec![Some(1), Some(1), Some(1), None]);
+    assert_eq!(resolved[1].holes, 0);
+}
@@ -0,0 +1,122 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::annealing;
+use kalman_rs::error::{Error, ConfigError};
+use kalman_rs::config::*;
+
+use std::f64::consts::FRAC_PI_2;
+
+
+fn noise(num: usize) -> Vec<Mat2> {
+    (0..num).map(|_| Mat2::identity() * 0.01).collect()
+}
+
+// sensors stacked along z, `spacing` apart
+fn initialize_sensors(num: usize, spacing: Real) -> Vec<Rectangle> {
+    (0..num).map(|i| {
+            let mut tfm = Mat4::identity();
+            tfm[(2, 3)] = spacing * i as Real;
+            Rectangle::new(100 as Real, 100 as Real, tfm).unwrap()
+        })
+        .collect()
+}
+
+// heading straight through the sensors along z
+fn seed() -> Vec5 {
+    Vec5::new(0., 0., 0., FRAC_PI_2, 0.)
+}
+
+fn schedule() -> Vec<Real> {
+    vec![81., 9., 4., 1., 1.]
+}
+
+#[test]
+fn outlier_is_down_weighted() {
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0)],
+        vec![Vec2::new(0.02, -0.01), Vec2::new(2.0, 1.5)],
+        vec![Vec2::new(-0.01, 0.01)],
+        vec![Vec2::new(0.01, 0.0)]
+    ];
+    let noise = vec![noise(1), noise(2), noise(1), noise(1)];
+
+    let result = annealing::run(&seed(), &Mat5::identity(), &noise, &measurements, &initialize_sensors(measurements.len(), 0.), &schedule(), 9.).unwrap();
+
+    assert_eq!(result.smth_state_vec.len(), 4);
+    assert!(result.weights[1][0] > 0.9);
+    assert!(result.weights[1][1] < 0.01);
+
+    // the outlier should not drag the smoothed track
+    assert!(result.smth_state_vec[1][0].abs() < 0.1);
+    assert!(result.smth_state_vec[1][1].abs() < 0.1);
+}
+
+#[test]
+fn weights_are_probabilities() {
+    // the only hit on the last sensor is an injected outlier
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0), Vec2::new(0.05, 0.0), Vec2::new(-0.05, 0.0)],
+        vec![Vec2::new(0.0, 0.0)],
+        vec![Vec2::new(3.0, -2.0)]
+    ];
+    let noise = vec![noise(3), noise(1), noise(1)];
+
+    let result = annealing::run(&seed(), &Mat5::identity(), &noise, &measurements, &initialize_sensors(measurements.len(), 0.), &schedule(), 9.).unwrap();
+
+    for sensor_weights in result.weights.iter() {
+        let total : Real = sensor_weights.iter().sum();
+        assert!(total <= 1.0);
+        assert!(sensor_weights.iter().all(|w| *w >= 0.));
+    }
+
+    // compatible hits keep nearly all of the probability, the outlier ends up with none
+    assert!(result.weights[0].iter().sum::<Real>() > 0.9);
+    assert!(result.weights[1][0] > 0.9);
+    assert!(result.weights[2][0] < 1e-3);
+}
+
+#[test]
+fn empty_track() {
+    let sensors : Vec<Rectangle> = Vec::new();
+    let result = annealing::run(&seed(), &Mat5::identity(), &vec![], &vec![], &sensors, &schedule(), 9.).unwrap();
+
+    assert!(result.smth_state_vec.is_empty());
+    assert!(result.weights.is_empty());
+}
+
+#[test]
+fn follows_track_between_separated_sensors() {
+    let sensors = initialize_sensors(4, 10.);
+
+    // straight track leaving the first sensor at (0.5, -0.5) with theta = 0.2, phi = 1.2
+    let (theta, phi) : (Real, Real) = (0.2, 1.2);
+    let step = 10. / phi.tan();
+    let truth : Vec<Vec2> = (0..4).map(|i| Vec2::new(0.5 + step * theta.cos() * i as Real, -0.5 + step * theta.sin() * i as Real)).collect();
+
+    let mut measurements : Vec<Vec<Vec2>> = truth.iter().map(|hit| vec![*hit]).collect();
+    measurements[2].push(truth[2] + Vec2::new(1.5, -1.));
+    let noise = vec![noise(1), noise(1), noise(2), noise(1)];
+
+    let seed_x = Vec5::new(0.5, -0.5, theta, phi, 0.);
+    let result = annealing::run(&seed_x, &(Mat5::identity() * 0.1), &noise, &measurements, &sensors, &schedule(), 9.).unwrap();
+
+    assert!(result.weights[2][0] > 0.9);
+    assert!(result.weights[2][1] < 1e-3);
+    for (i, (state_vec, hit)) in result.smth_state_vec.iter().zip(truth.iter()).enumerate() {
+        let smoothed = Vec2::new(state_vec[0], state_vec[1]);
+        assert!((smoothed - hit).norm() < 1e-2, "sensor {}: {} expected {}", i, smoothed, hit);
+    }
+}
+
+#[test]
+fn rejects_bad_schedules() {
+    let measurements = vec![vec![Vec2::new(0.0, 0.0)], vec![Vec2::new(0.0, 0.0)]];
+    let noise = vec![noise(1), noise(1)];
+    let sensors = initialize_sensors(2, 0.);
+
+    for temperatures in [vec![], vec![4., 0.], vec![4., -1.], vec![Real::NAN]].iter() {
+        match annealing::run(&seed(), &Mat5::identity(), &noise, &measurements, &sensors, temperatures, 9.) {
+            Err(Error::Config(ConfigError::InvalidValue(_))) => {},
+            _ => panic!("expected an invalid schedule error for {:?}", temperatures)
+        }
+    }
+}
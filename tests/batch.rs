@@ -0,0 +1,85 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::batch::{self, TrackInput};
+use kalman_rs::error::{Error, InputError, MatrixError};
+use kalman_rs::config::*;
+
+
+fn initialize_geometry(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+// track hitting every sensor at a fixed offset
+fn track(offset: Real) -> TrackInput {
+    TrackInput{seed_state_vec: Vec5::new(offset, 0., 0.1, 0.3, 0.5),
+               measurements: vec![Vec2::new(offset, 0.01), Vec2::new(offset, 0.02), Vec2::new(offset, 0.03)],
+               measurement_noise: vec![Mat2::identity() * 0.01; 3],
+               sensor_indices: vec![0, 1, 2]}
+}
+
+fn options() -> FitterOptions {
+    FitterOptions::builder()
+        .seed_covariance(Mat5::identity() * 0.1)
+        .build()
+}
+
+#[test]
+fn results_in_input_order() {
+    let geometry = initialize_geometry(3);
+    let tracks : Vec<TrackInput> = (0..50).map(|i| track(i as Real * 0.1)).collect();
+
+    let results = batch::fit_batch(&geometry, &tracks, &options());
+
+    assert_eq!(results.len(), 50);
+    for (i, result) in results.iter().enumerate() {
+        let smoothed = result.as_ref().unwrap();
+        let single = batch::fit_track(&geometry, &tracks[i], &options()).unwrap();
+
+        assert_eq!(smoothed.state_vec(), single.state_vec());
+        assert!((smoothed.state_vec()[2][0] - i as Real * 0.1).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn bad_track_does_not_stop_batch() {
+    let geometry = initialize_geometry(3);
+
+    let mut unknown_sensor = track(0.5);
+    unknown_sensor.sensor_indices[2] = 7;
+
+    let mut missing_noise = track(0.5);
+    missing_noise.measurement_noise.pop();
+
+    let tracks = vec![track(0.1), unknown_sensor, missing_noise, track(0.2)];
+    let results = batch::fit_batch(&geometry, &tracks, &options());
+
+    assert!(results[0].is_ok());
+    match results[1] {
+        Err(Error::Input(InputError::UnknownSensor(7))) => {},
+        _ => panic!("expected an unknown sensor error")
+    }
+    match results[2] {
+        Err(Error::Input(InputError::LengthMismatch)) => {},
+        _ => panic!("expected a length mismatch error")
+    }
+    assert!(results[3].is_ok());
+}
+
+#[test]
+fn singular_track_does_not_stop_batch() {
+    let geometry = initialize_geometry(3);
+
+    // cancels the seed covariance on the first sensor so the residual covariance matrix is zero
+    let mut singular = track(0.5);
+    singular.measurement_noise[0] = Mat2::identity() * -0.1;
+
+    let tracks = vec![track(0.1), singular, track(0.2)];
+    let results = batch::fit_batch(&geometry, &tracks, &options());
+
+    assert!(results[0].is_ok());
+    match results[1] {
+        Err(Error::Matrix(MatrixError::NonInvertible)) => {},
+        _ => panic!("expected a non invertible error")
+    }
+    assert!(results[2].is_ok());
+}
@@ -0,0 +1,108 @@
+use kalman_rs::geometry::{Rectangle, Trapezoid, Disc, Polygon, Cylinder};
+use kalman_rs::geometry::traits::Transform;
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::error::{Error, SensorError};
+use kalman_rs::config::*;
+
+use std::f64::consts::PI;
+
+
+fn assert_distance<T: Transform>(sensor: &T, point: P2, expected: Real) {
+    let found = sensor.boundary_distance(&point);
+    assert!((found - expected).abs() < 1e-9, "{}: found {} expected {}", point, found, expected);
+}
+
+#[test]
+fn signed_distances() {
+    let rectangle = Rectangle::new(4., 2., Mat4::identity()).unwrap();
+    assert_distance(&rectangle, P2::new(0., 0.), -1.);
+    assert_distance(&rectangle, P2::new(1.5, 0.), -0.5);
+    assert_distance(&rectangle, P2::new(3., 0.), 1.);
+    assert_distance(&rectangle, P2::new(5., 5.), (9. + 16. as Real).sqrt());
+
+    let trapezoid = Trapezoid::new(2., 2., Mat4::identity(), 2.).unwrap();
+    assert_distance(&trapezoid, P2::new(0., 0.5), -0.5);
+    assert_distance(&trapezoid, P2::new(0., -3.), 2.);
+
+    let polygon = Polygon::new(vec![P2::new(0., 0.), P2::new(4., 0.), P2::new(0., 3.)], Mat4::identity()).unwrap();
+    // the incenter, one away from every edge
+    assert_distance(&polygon, P2::new(1., 1.), -1.);
+    assert_distance(&polygon, P2::new(-1., -1.), (2. as Real).sqrt());
+
+    // sector from 0 to 90 degrees between radii 10 and 20
+    let sector = Disc::new(10., 20., 0., PI / 2., Mat4::identity()).unwrap();
+    assert_distance(&sector, P2::new(15. * (PI / 4.).cos(), 15. * (PI / 4.).sin()), -5.);
+    assert_distance(&sector, P2::new(15., 1.), -1.);
+    assert_distance(&sector, P2::new(25., 0.), 5.);
+    assert_distance(&sector, P2::new(15., -2.), 2.);
+
+    let cylinder = Cylinder::new(30., 100., Mat4::identity()).unwrap();
+    assert_distance(&cylinder, P2::new(10., 40.), -10.);
+    assert_distance(&cylinder, P2::new(10., -60.), 10.);
+}
+
+#[test]
+fn tolerance_kinds() {
+    let rectangle = Rectangle::new(2., 2., Mat4::identity()).unwrap();
+    let just_outside = P2::new(1.001, 0.);
+    let cov_mat = Mat2::new(1e-4, 0., 0., 1.);
+
+    assert!(!rectangle.inside_with_tolerance(&just_outside, &cov_mat, &Tolerance::None));
+    assert!(rectangle.inside_with_tolerance(&just_outside, &cov_mat, &Tolerance::Absolute(0.002)));
+    assert!(!rectangle.inside_with_tolerance(&just_outside, &cov_mat, &Tolerance::Absolute(0.0005)));
+
+    // only the spread towards the boundary counts, which is 0.01 along x
+    assert!(rectangle.inside_with_tolerance(&just_outside, &cov_mat, &Tolerance::Sigmas(1.)));
+    assert!(!rectangle.inside_with_tolerance(&P2::new(1.05, 0.), &cov_mat, &Tolerance::Sigmas(3.)));
+    assert!(rectangle.inside_with_tolerance(&P2::new(0., 1.05), &cov_mat, &Tolerance::Sigmas(3.)));
+}
+
+#[test]
+fn filter_accepts_near_edge_prediction() {
+    let first = Rectangle::new(10., 10., Mat4::identity()).unwrap();
+    let second = Rectangle::new(2., 2., Mat4::identity()).unwrap();
+    let seed_state_vec = Vec5::new(1.0005, 0., 0., 1., 0.5);
+
+    let predict_with = |tolerance: Tolerance| {
+        let options = FitterOptions::builder()
+                        .seed_covariance(Mat5::identity() * 1e-4)
+                        .boundary_tolerance(tolerance)
+                        .build();
+        let mut kf = KalmanFilter::with_options(seed_state_vec, options);
+        kf.predict(&first).unwrap();
+        kf.predict(&second)
+    };
+
+    match predict_with(Tolerance::None) {
+        Err(Error::Sensor(SensorError::OutsideSensorBounds)) => {},
+        _ => panic!("strict bounds should miss the sensor")
+    }
+    assert!(predict_with(Tolerance::Absolute(0.001)).is_ok());
+    assert!(predict_with(Tolerance::Sigmas(3.)).is_ok());
+}
+
+// a sensor written before `boundary_distance` existed
+struct Square;
+
+impl Transform for Square {
+    fn to_global(&self, input_point: P3) -> P3 {
+        input_point
+    }
+
+    fn to_local(&self, input_point: P3) -> P2 {
+        P2::new(input_point.x, input_point.y)
+    }
+
+    fn inside(&self, input: &P2) -> bool {
+        input.x.abs() < 1. && input.y.abs() < 1.
+    }
+}
+
+#[test]
+fn default_distance_keeps_strict_bounds() {
+    let cov_mat = Mat2::identity();
+    for tolerance in [Tolerance::None, Tolerance::Absolute(0.5), Tolerance::Sigmas(3.)].iter() {
+        assert!(Square.inside_with_tolerance(&P2::new(0.9, 0.), &cov_mat, tolerance));
+        assert!(!Square.inside_with_tolerance(&P2::new(1.1, 0.), &cov_mat, tolerance));
+    }
+}
@@ -0,0 +1,113 @@
+use kalman_rs::filter::bundle::{TrackBundle4, TrackBundle8};
+use kalman_rs::filter::{prediction, filter_gain, utils};
+use kalman_rs::config::*;
+
+
+fn seed(lane: usize) -> (Vec5, Mat5) {
+    let offset = lane as Real * 0.1;
+    let state_vec = Vec5::new(offset, -offset, 0.1, 0.3, 0.5);
+    let mut cov_mat = Mat5::identity() * (0.1 + offset);
+    cov_mat[(0, 1)] = 0.01;
+    cov_mat[(1, 0)] = 0.01;
+    (state_vec, cov_mat)
+}
+
+fn measurement(lane: usize, step: usize) -> (Vec2, Mat2) {
+    let m = Vec2::new(lane as Real * 0.1 + 0.01 * step as Real, 0.02 * step as Real);
+    let mut v = Mat2::identity() * (0.01 + 0.001 * lane as Real);
+    v[(0, 1)] = 0.001;
+    v[(1, 0)] = 0.001;
+    (m, v)
+}
+
+fn jacobian() -> Mat5 {
+    let mut jacobian = Mat5::identity();
+    jacobian[(0, 2)] = 0.5;
+    jacobian[(1, 3)] = 0.5;
+    jacobian
+}
+
+macro_rules! compare_to_scalar {
+    ($name:ident, $bundle:ident, $lanes:expr) => {
+        #[test]
+        fn $name() {
+            let seeds : Vec<(Vec5, Mat5)> = (0..$lanes).map(seed).collect();
+            let state_vecs : Vec<Vec5> = seeds.iter().map(|s| s.0).collect();
+            let cov_mats : Vec<Mat5> = seeds.iter().map(|s| s.1).collect();
+
+            let mut bundle = $bundle::from_tracks(&state_vecs, &cov_mats);
+            let mut scalar = seeds.clone();
+
+            let jac = jacobian();
+            let meas_map_mat = utils::sensor_mapping_mat();
+
+            for step in 0..5 {
+                let (ms, vs) : (Vec<Vec2>, Vec<Mat2>) = (0..$lanes).map(|l| measurement(l, step)).unzip();
+                let (packed_m, packed_v) = $bundle::pack_measurements(&ms, &vs);
+
+                bundle.predict(&jac);
+                let chi_squared = bundle.update(&packed_m, &packed_v);
+
+                for l in 0..$lanes {
+                    let (ref mut x, ref mut c) = scalar[l];
+                    let pred_x = prediction::state_vector(&jac, x);
+                    let pred_c = prediction::covariance_matrix(&jac, c);
+
+                    let gain = filter_gain::kalman_gain(&pred_c, &meas_map_mat, &vs[l]);
+                    *x = filter_gain::state_vector(&pred_x, &gain, &ms[l], &meas_map_mat);
+                    *c = filter_gain::covariance_matrix(&gain, &meas_map_mat, &pred_c);
+
+                    let res = prediction::residual_vec(&ms[l], &meas_map_mat, &pred_x);
+                    let res_mat = prediction::residual_mat(&vs[l], &meas_map_mat, &pred_c);
+                    let scalar_chi = (res.transpose() * res_mat.try_inverse().unwrap() * res)[0];
+
+                    assert!((bundle.state_vec(l) - *x).norm() < 1e-10);
+                    assert!((bundle.cov_mat(l) - *c).norm() < 1e-10);
+                    assert!((chi_squared[l] - scalar_chi).abs() < 1e-10);
+                }
+            }
+        }
+    };
+}
+
+compare_to_scalar!{bundle4_matches_scalar, TrackBundle4, 4}
+compare_to_scalar!{bundle8_matches_scalar, TrackBundle8, 8}
+
+#[test]
+fn singular_lane_is_left_unchanged() {
+    let seeds : Vec<(Vec5, Mat5)> = (0..4).map(seed).collect();
+    let mut state_vecs : Vec<Vec5> = seeds.iter().map(|s| s.0).collect();
+    let mut cov_mats : Vec<Mat5> = seeds.iter().map(|s| s.1).collect();
+
+    // lane 1 has no spread in the measured coordinates and lane 2 an infinite one
+    cov_mats[1] = Mat5::zeros();
+    cov_mats[2][(0, 0)] = Real::INFINITY;
+    state_vecs[2][0] = 0.2;
+
+    let mut bundle = TrackBundle4::from_tracks(&state_vecs, &cov_mats);
+
+    let (ms, mut vs) : (Vec<Vec2>, Vec<Mat2>) = (0..4).map(|l| measurement(l, 1)).unzip();
+    vs[1] = Mat2::zeros();
+    let (packed_m, packed_v) = TrackBundle4::pack_measurements(&ms, &vs);
+
+    let chi_squared = bundle.update(&packed_m, &packed_v);
+
+    for l in 1..3 {
+        assert!(chi_squared[l].is_nan());
+        assert_eq!(bundle.state_vec(l), state_vecs[l]);
+    }
+    assert_eq!(bundle.cov_mat(1), cov_mats[1]);
+    assert_eq!(bundle.cov_mat(2)[(0, 0)], Real::INFINITY);
+
+    // the other lanes are updated as usual
+    for l in [0, 3].iter() {
+        assert!(chi_squared[*l].is_finite());
+        assert!(bundle.state_vec(*l) != state_vecs[*l]);
+    }
+}
+
+#[test]
+#[should_panic]
+fn wrong_number_of_tracks() {
+    TrackBundle4::from_tracks(&[Vec5::zeros(); 3], &[Mat5::identity(); 3]);
+}
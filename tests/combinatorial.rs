@@ -0,0 +1,191 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::combinatorial::{self, CombinatorialOptions};
+use kalman_rs::config::*;
+
+
+// large sensors on the identity transform so every prediction lands inside
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+// heading straight through the sensors along z
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0., std::f64::consts::FRAC_PI_2, 0.), Mat5::identity())
+}
+
+fn noise(num: usize) -> Vec<Mat2> {
+    (0..num).map(|_| Mat2::identity() * 0.01).collect()
+}
+
+#[test]
+fn rejects_far_hits() {
+    let sensors = initialize_sensors(3);
+    let (seed_x, seed_c) = seed();
+
+    let measurements = vec![
+        vec![Vec2::new(0.1, 0.0), Vec2::new(8.0, 8.0)],
+        vec![Vec2::new(0.1, 0.1)],
+        vec![Vec2::new(-9.0, 5.0), Vec2::new(0.0, 0.1)]
+    ];
+    let noise = vec![noise(2), noise(1), noise(2)];
+    let options = CombinatorialOptions::new(9.0, 10, 1);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].hits, vec![Some(0), Some(0), Some(1)]);
+    assert_eq!(candidates[0].ndf, 6);
+}
+
+#[test]
+fn branches_on_ambiguous_hits() {
+    let sensors = initialize_sensors(2);
+    let (seed_x, seed_c) = seed();
+
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0)],
+        vec![Vec2::new(0.05, 0.0), Vec2::new(0.3, 0.0)]
+    ];
+    let noise = vec![noise(1), noise(2)];
+    let options = CombinatorialOptions::new(25.0, 10, 0);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    assert_eq!(candidates.len(), 2);
+    // the closer hit gives the better candidate
+    assert_eq!(candidates[0].hits, vec![Some(0), Some(0)]);
+    assert_eq!(candidates[1].hits, vec![Some(0), Some(1)]);
+    assert!(candidates[0].chi_squared < candidates[1].chi_squared);
+}
+
+#[test]
+fn prunes_to_max_branches() {
+    let sensors = initialize_sensors(2);
+    let (seed_x, seed_c) = seed();
+
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0), Vec2::new(0.1, 0.0), Vec2::new(0.2, 0.0)],
+        vec![Vec2::new(0.0, 0.0), Vec2::new(0.1, 0.0)]
+    ];
+    let noise = vec![noise(3), noise(2)];
+    let options = CombinatorialOptions::new(100.0, 2, 0);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    assert_eq!(candidates.len(), 2);
+}
+
+#[test]
+fn holes_are_limited() {
+    let sensors = initialize_sensors(3);
+    let (seed_x, seed_c) = seed();
+
+    // nothing compatible on the last two sensors
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0)],
+        vec![Vec2::new(9.0, 9.0)],
+        vec![Vec2::new(-9.0, -9.0)]
+    ];
+    let noise = vec![noise(1), noise(1), noise(1)];
+
+    let one_hole = CombinatorialOptions::new(9.0, 10, 1);
+    let two_holes = CombinatorialOptions::new(9.0, 10, 2);
+
+    assert_eq!(combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &one_hole).len(), 0);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &two_holes);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].holes, 2);
+    assert_eq!(candidates[0].hits, vec![Some(0), None, None]);
+}
+
+#[test]
+fn branches_follow_sensor_geometry() {
+    // sensors stacked along z, 10 apart
+    let sensors: Vec<Rectangle> = (0..3)
+        .map(|i| Rectangle::new(100 as Real, 100 as Real, Mat4::new_translation(&Vec3::new(0., 0., 10. * i as Real))).unwrap())
+        .collect();
+
+    // moving along (1, 0, 1) from x = 1 on the first sensor
+    let seed_x = Vec5::new(1., 0., 0., std::f64::consts::FRAC_PI_4, 0.);
+    let seed_c = Mat5::identity() * 0.01;
+
+    // the first hit on each later sensor sits where an unextrapolated branch would expect it
+    let measurements = vec![
+        vec![Vec2::new(1.0, 0.0)],
+        vec![Vec2::new(1.0, 0.0), Vec2::new(11.0, 0.0)],
+        vec![Vec2::new(1.0, 0.0), Vec2::new(21.0, 0.0)]
+    ];
+    let noise = vec![noise(1), noise(2), noise(2)];
+    let options = CombinatorialOptions::new(9.0, 10, 0);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].hits, vec![Some(0), Some(1), Some(1)]);
+}
+
+#[test]
+fn holes_count_against_branches() {
+    let sensors = initialize_sensors(3);
+    let (seed_x, seed_c) = seed();
+
+    // the hit at 0.0 on the second sensor fits best, but leaves nothing compatible on the third
+    let measurements = vec![
+        vec![Vec2::new(0.0, 0.0)],
+        vec![Vec2::new(0.0, 0.0), Vec2::new(0.45, 0.0)],
+        vec![Vec2::new(0.62, 0.0)]
+    ];
+    let noise = vec![noise(1), noise(2), noise(1)];
+    let options = CombinatorialOptions::new(25.0, 2, 1);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].hits, vec![Some(0), Some(1), Some(0)]);
+    assert_eq!(candidates[1].hits, vec![Some(0), Some(0), None]);
+    assert!(candidates[1].quality() < candidates[0].quality());
+}
+
+#[test]
+fn boundary_tolerance_keeps_hits_near_the_edge() {
+    let sensors = initialize_sensors(2);
+    let (mut seed_x, seed_c) = seed();
+    // the sensors end at x = 10
+    seed_x[0] = 10.2;
+
+    let measurements = vec![vec![Vec2::new(10.2, 0.)], vec![Vec2::new(10.2, 0.)]];
+    let noise = vec![noise(1), noise(1)];
+
+    let strict = CombinatorialOptions::new(9.0, 10, 0);
+    // every prediction misses, so the branch never picks up a hit
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &strict);
+    assert!(candidates.is_empty());
+
+    let tolerant = CombinatorialOptions::new(9.0, 10, 0).boundary_tolerance(Tolerance::Absolute(0.5));
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &tolerant);
+    assert_eq!(candidates[0].hits, vec![Some(0), Some(0)]);
+}
+
+#[test]
+fn branches_end_on_parallel_sensors() {
+    // the third sensor stands upright at x = 5, parallel to the track
+    let mut shifted = Mat4::identity();
+    shifted[(2, 3)] = 10.;
+    let mut upright = Rot3::from_axis_angle(&Vec3::y_axis(), std::f64::consts::FRAC_PI_2).to_homogeneous();
+    upright[(0, 3)] = 5.;
+    let sensors = vec![Rectangle::new(20., 20., Mat4::identity()).unwrap(),
+                       Rectangle::new(20., 20., shifted).unwrap(),
+                       Rectangle::new(20., 20., upright).unwrap()];
+    let (seed_x, seed_c) = seed();
+
+    let measurements = vec![vec![Vec2::new(0., 0.)], vec![Vec2::new(0., 0.1)], vec![Vec2::new(0., 0.)]];
+    let noise = vec![noise(1), noise(1), noise(1)];
+    let options = CombinatorialOptions::new(9.0, 10, 1);
+
+    let candidates = combinatorial::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &options);
+
+    // no branch carries a state that could not be extrapolated
+    assert!(candidates.is_empty());
+}
@@ -0,0 +1,83 @@
+use kalman_rs::config::*;
+use kalman_rs::error::ConfigError;
+
+
+#[test]
+fn builder_overrides_defaults() {
+    let options = FitterOptions::builder()
+                    .propagator(Propagator::Unscented)
+                    .max_iterations(4)
+                    .build();
+
+    assert_eq!(options.propagator, Propagator::Unscented);
+    assert_eq!(options.max_iterations, 4);
+    assert_eq!(options.smoother, Smoother::RauchTungStriebel);
+    assert_eq!(options.seed_covariance, Mat5::identity());
+}
+
+#[test]
+fn full_toml_file() {
+    let contents = r#"
+        propagator = "unscented"
+        material_effects = true
+        radiation_length_fraction = 0.02
+        particle_hypothesis = "electron"
+        outlier_chi_squared = 25.0
+        covariance_update = "joseph"
+        smoother = "disabled"
+        max_iterations = 5
+        seed_covariance = [1.0, 2.0, 3.0, 4.0, 5.0]
+    "#;
+
+    let options = FitterOptions::from_toml_str(contents).unwrap();
+
+    assert_eq!(options.propagator, Propagator::Unscented);
+    assert!(options.material_effects);
+    assert_eq!(options.radiation_length_fraction, 0.02);
+    assert_eq!(options.particle_hypothesis, ParticleHypothesis::Electron);
+    assert_eq!(options.outlier_chi_squared, 25.0);
+    assert_eq!(options.covariance_update, CovarianceUpdate::Joseph);
+    assert_eq!(options.smoother, Smoother::Disabled);
+    assert_eq!(options.max_iterations, 5);
+    assert_eq!(options.seed_covariance, Mat5::from_diagonal(&Vec5::new(1., 2., 3., 4., 5.)));
+}
+
+#[test]
+fn partial_toml_keeps_defaults() {
+    let options = FitterOptions::from_toml_str("max_iterations = 3").unwrap();
+
+    assert_eq!(options.max_iterations, 3);
+    assert_eq!(options.propagator, Propagator::Linear);
+}
+
+#[test]
+fn boundary_tolerance_from_toml() {
+    let options = FitterOptions::from_toml_str("boundary_tolerance_sigmas = 3.0").unwrap();
+    assert_eq!(options.boundary_tolerance, Tolerance::Sigmas(3.));
+
+    match FitterOptions::from_toml_str("boundary_tolerance = 0.1\nboundary_tolerance_sigmas = 3.0") {
+        Err(ConfigError::InvalidValue(_)) => {},
+        _ => panic!("both tolerances should be rejected")
+    }
+}
+
+#[test]
+fn bad_toml_rejected() {
+    match FitterOptions::from_toml_str("seed_covariance = [1.0, 2.0]") {
+        Err(ConfigError::InvalidValue(_)) => {},
+        _ => panic!("seed covariance with 2 elements should be rejected")
+    }
+
+    match FitterOptions::from_toml_str("propagator = \"runge_kutta\"") {
+        Err(ConfigError::Parse(_)) => {},
+        _ => panic!("unknown propagator should be rejected")
+    }
+}
+
+#[test]
+fn missing_file() {
+    match FitterOptions::from_toml_file("/this/file/does/not/exist.toml") {
+        Err(ConfigError::Io(_)) => {},
+        _ => panic!("missing file should be an io error")
+    }
+}
@@ -0,0 +1,88 @@
+use kalman_rs::geometry::{Cylinder, Rectangle};
+use kalman_rs::geometry::traits::{Surface, Transform};
+use kalman_rs::filter::{parameters, propagation};
+use kalman_rs::config::*;
+
+use std::f64::consts::FRAC_PI_2;
+
+
+fn barrel(radius: Real) -> Cylinder {
+    Cylinder::new(radius, 1000., Mat4::identity()).unwrap()
+}
+
+#[test]
+fn local_coordinates_are_arc_length_and_z() {
+    let layer = barrel(50.);
+
+    let global = layer.to_global(P3::new(50. * FRAC_PI_2, 20., 0.));
+    assert!((global - P3::new(0., 50., 20.)).norm() < 1e-9);
+
+    let local = layer.to_local(P3::new(-50., 0., -30.));
+    assert!((local.x - 50. * std::f64::consts::PI).abs() < 1e-9);
+    assert!((local.y + 30.).abs() < 1e-9);
+
+    assert!(layer.inside(&P2::new(0., 499.)));
+    assert!(!layer.inside(&P2::new(0., 501.)));
+
+    let normal = layer.normal_at(&P3::new(30., 40., 10.));
+    assert!((normal - Vec3::new(0.6, 0.8, 0.)).norm() < 1e-12);
+
+    assert!(Cylinder::new(-1., 10., Mat4::identity()).is_err());
+}
+
+#[test]
+fn intersection_goes_forwards() {
+    // shifted cylinder with its axis along global x
+    let mut tfm = Rot3::from_euler_angles(0., FRAC_PI_2, 0.).to_homogeneous();
+    tfm[(1, 3)] = 5.;
+    let pipe = Cylinder::new(20., 500., tfm).unwrap();
+
+    // from inside the pipe the crossing is ahead in both directions
+    let start = P3::new(0., 5., 0.);
+    assert!((pipe.intersect(&start, &Vec3::z()).unwrap().path_length - 20.).abs() < 1e-9);
+    assert!((pipe.intersect(&start, &-Vec3::z()).unwrap().path_length - 20.).abs() < 1e-9);
+
+    // from outside, the nearer wall is hit and a track heading away never reaches it
+    let outside = P3::new(0., 5., -50.);
+    assert!((pipe.intersect(&outside, &Vec3::z()).unwrap().path_length - 30.).abs() < 1e-9);
+    assert!(pipe.intersect(&outside, &-Vec3::z()).is_none());
+
+    // parallel to the axis
+    assert!(pipe.intersect(&start, &Vec3::x()).is_none());
+}
+
+#[test]
+fn propagation_through_barrel_layers() {
+    let layers = vec![barrel(30.), barrel(60.), barrel(90.)];
+
+    // track from the origin
+    let (theta, phi) = (0.4, 0.3);
+    let direction = parameters::direction(theta, phi);
+    let transverse = (direction.x * direction.x + direction.y * direction.y).sqrt();
+
+    let truth : Vec<Vec5> = layers.iter().map(|layer| {
+        let local = layer.to_local(P3::origin() + direction * layer.radius / transverse);
+        Vec5::new(local.x, local.y, theta, phi, 0.5)
+    })
+    .collect();
+
+    let mut state_vec = truth[0];
+    let mut cov_mat = Mat5::identity() * 0.01;
+    for i in 1..layers.len() {
+        let (pred_state_vec, pred_cov_mat) = propagation::predict(&layers[i - 1], &layers[i], &state_vec, &cov_mat).unwrap();
+        assert!((pred_state_vec - truth[i]).norm() < 1e-9);
+        assert!(pred_cov_mat[(0, 0)] > cov_mat[(0, 0)]);
+
+        state_vec = pred_state_vec;
+        cov_mat = pred_cov_mat;
+    }
+
+    // from a plane through the origin onto the first layer
+    let plane = Rectangle::new(10., 10., Mat4::identity()).unwrap();
+    let on_layer = propagation::surface_extrapolation(&plane, &layers[0], &Vec5::new(0., 0., theta, phi, 0.5)).unwrap();
+    assert!((on_layer - truth[0]).norm() < 1e-9);
+
+    // tracks heading inwards cross the inner layers as well
+    let inwards = Vec5::new(0., 0., theta + std::f64::consts::PI, -phi, 0.5);
+    assert!(propagation::surface_extrapolation(&layers[2], &layers[1], &inwards).is_ok());
+}
@@ -0,0 +1,84 @@
+use kalman_rs::geometry::Disc;
+use kalman_rs::geometry::traits::{Plane, Transform};
+use kalman_rs::filter::timing::{self, Measurement};
+use kalman_rs::config::*;
+
+use std::f64::consts::PI;
+
+
+// endcap disc `z` along the beam line
+fn endcap_tfm(z: Real) -> Mat4 {
+    let mut tfm = Mat4::identity();
+    tfm[(2, 3)] = z;
+    tfm
+}
+
+#[test]
+fn sector_bounds_are_polar() {
+    // sector crossing phi = pi, from 150 to 210 degrees
+    let sector = Disc::new(30., 100., 5. * PI / 6., 7. * PI / 6., endcap_tfm(500.)).unwrap();
+
+    assert!(sector.inside(&P2::new(-50., 0.)));
+    assert!(sector.inside(&P2::new(-50., 10.)));
+    assert!(sector.inside(&P2::new(-50., -10.)));
+
+    // radius too small or too large
+    assert!(!sector.inside(&P2::new(-20., 0.)));
+    assert!(!sector.inside(&P2::new(-110., 0.)));
+
+    // outside the phi range
+    assert!(!sector.inside(&P2::new(50., 0.)));
+    assert!(!sector.inside(&P2::new(-50., 40.)));
+
+    // the corner of the bounding box is outside the outer radius
+    assert!(!sector.inside(&P2::new(-95., 45.)));
+
+    assert!(sector.inside_global(P3::new(-60., 5., 500.)));
+    assert!(sector.on_plane(&P3::new(-60., 5., 500.)));
+    assert!(!sector.on_plane(&P3::new(-60., 5., 0.)));
+}
+
+#[test]
+fn annulus_and_invalid_bounds() {
+    let annulus = Disc::new_annulus(10., 20., Mat4::identity()).unwrap();
+    for i in 0..8 {
+        let phi = i as Real * PI / 4.;
+        assert!(annulus.inside(&P2::new(15. * phi.cos(), 15. * phi.sin())));
+        assert!(!annulus.inside(&P2::new(5. * phi.cos(), 5. * phi.sin())));
+    }
+
+    assert!(Disc::new_annulus(20., 10., Mat4::identity()).is_err());
+    assert!(Disc::new(10., 20., 1., 0.5, Mat4::identity()).is_err());
+    assert!(Disc::new_annulus(10., 20., Mat4::zeros()).is_err());
+}
+
+#[test]
+fn forward_track_fits_on_discs() {
+    let discs : Vec<Disc> = (0..5).map(|i| Disc::new_annulus(20., 400., endcap_tfm(300. + 100. * i as Real)).unwrap()).collect();
+    let mass = ParticleHypothesis::Pion.mass();
+
+    // steep track heading into the endcap
+    let truth_start = Vec6::new(40., 10., 0.3, 1.3, 1., 0.);
+    let mut truth = vec![truth_start];
+    for i in 1..discs.len() {
+        let next = timing::time_extrapolation(&discs[i - 1], &discs[i], &truth[i - 1], mass);
+        truth.push(next);
+    }
+
+    for (disc, state) in discs.iter().zip(truth.iter()) {
+        assert!(disc.inside(&P2::new(state[0], state[1])));
+    }
+
+    let measurements = truth.iter()
+        .map(|state| Measurement::Space{position: Vec2::new(state[0], state[1]), noise: Mat2::identity() * 1e-4})
+        .collect();
+
+    let seed_state_vec = truth_start + Vec6::new(0.5, -0.5, 0.01, 0.01, 0., 0.);
+    let seed_cov_mat = Mat6::from_diagonal(&Vec6::new(1., 1., 0.01, 0.01, 0.01, 1.));
+
+    let fit = timing::run(&seed_state_vec, &seed_cov_mat, &measurements, &discs, mass).unwrap();
+    for (smoothed, true_state) in fit.smth_state_vec.iter().zip(truth.iter()) {
+        assert!((smoothed[0] - true_state[0]).abs() < 0.05);
+        assert!((smoothed[1] - true_state[1]).abs() < 0.05);
+    }
+}
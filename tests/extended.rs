@@ -0,0 +1,125 @@
+use kalman_rs::filter::extended::{ExtendedKalmanFilter, StateModel, MeasurementModel};
+use kalman_rs::config::*;
+
+
+// position / velocity moving with a fixed time step
+struct ConstantVelocity {
+    dt: Real
+}
+
+impl StateModel for ConstantVelocity {
+    fn transition(&self, state_vec: &DVec) -> DVec {
+        DVec::from_vec(vec![state_vec[0] + self.dt * state_vec[1], state_vec[1]])
+    }
+
+    fn jacobian(&self, _state_vec: &DVec) -> DMat {
+        DMat::from_row_slice(2, 2, &[1., self.dt,
+                                     0., 1.])
+    }
+}
+
+// sensor sitting at `height` above the track measuring the distance to it
+struct Range {
+    height: Real
+}
+
+impl MeasurementModel for Range {
+    fn measure(&self, state_vec: &DVec) -> DVec {
+        DVec::from_vec(vec![(state_vec[0].powi(2) + self.height.powi(2)).sqrt()])
+    }
+
+    fn jacobian(&self, state_vec: &DVec) -> DMat {
+        let range = (state_vec[0].powi(2) + self.height.powi(2)).sqrt();
+        DMat::from_row_slice(1, 2, &[state_vec[0] / range, 0.])
+    }
+}
+
+// models with a bug: the outputs have one dimension too many
+struct Broken;
+
+impl StateModel for Broken {
+    fn transition(&self, state_vec: &DVec) -> DVec {
+        DVec::from_vec(vec![state_vec[0], state_vec[1], 0.])
+    }
+
+    fn jacobian(&self, _state_vec: &DVec) -> DMat {
+        DMat::identity(2, 2)
+    }
+}
+
+impl MeasurementModel for Broken {
+    fn measure(&self, state_vec: &DVec) -> DVec {
+        DVec::from_vec(vec![state_vec[0]])
+    }
+
+    fn jacobian(&self, _state_vec: &DVec) -> DMat {
+        DMat::identity(1, 3)
+    }
+}
+
+fn initialize_filter() -> ExtendedKalmanFilter<ConstantVelocity, Range> {
+    ExtendedKalmanFilter::new(
+        ConstantVelocity{dt: 0.1},
+        Range{height: 1.},
+        DMat::identity(2, 2) * 1e-6,
+        DMat::identity(1, 1) * 1e-4,
+        DVec::from_vec(vec![1., 0.5]),
+        DMat::identity(2, 2)
+    ).unwrap()
+}
+
+#[test]
+fn converges_to_true_track() {
+    let mut ekf = initialize_filter();
+
+    // true track starts at 2 and moves with velocity 1
+    for step in 1..200 {
+        let x = 2. + (step as Real) * 0.1;
+        ekf.predict().unwrap();
+        ekf.update(&DVec::from_vec(vec![(x * x + 1.).sqrt()])).unwrap();
+    }
+
+    let truth = 2. + 199. * 0.1;
+    assert!((ekf.state_vec()[0] - truth).abs() < 0.05);
+    assert!((ekf.state_vec()[1] - 1.).abs() < 0.05);
+}
+
+#[test]
+fn covariance_shrinks_after_update() {
+    let mut ekf = initialize_filter();
+
+    ekf.predict().unwrap();
+    let before = ekf.cov_mat()[(0, 0)];
+    ekf.update(&DVec::from_vec(vec![1.5])).unwrap();
+
+    assert!(ekf.cov_mat()[(0, 0)] < before);
+}
+
+#[test]
+fn wrong_dimensions_rejected() {
+    let mut ekf = initialize_filter();
+    assert!(ekf.update(&DVec::from_vec(vec![1., 2.])).is_err());
+
+    let bad = ExtendedKalmanFilter::new(
+        ConstantVelocity{dt: 0.1},
+        Range{height: 1.},
+        DMat::identity(3, 3),
+        DMat::identity(1, 1),
+        DVec::from_vec(vec![1., 0.5]),
+        DMat::identity(2, 2)
+    );
+    assert!(bad.is_err());
+
+    // model outputs of the wrong size are reported instead of panicking
+    let mut broken = ExtendedKalmanFilter::new(
+        Broken,
+        Broken,
+        DMat::identity(2, 2),
+        DMat::identity(1, 1),
+        DVec::from_vec(vec![1., 0.5]),
+        DMat::identity(2, 2)
+    ).unwrap();
+    assert!(broken.predict().is_err());
+    assert!(broken.update(&DVec::from_vec(vec![1.])).is_err());
+    assert_eq!(broken.state_vec().len(), 2);
+}
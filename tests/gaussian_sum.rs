@@ -0,0 +1,142 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::gaussian_sum::{self, BetheHeitlerMixture, Component};
+use kalman_rs::filter::linear;
+use kalman_rs::filter::workspace::FitWorkspace;
+use kalman_rs::error::{Error, ConfigError};
+use kalman_rs::config::*;
+
+
+// sensors stacked 10 apart along z
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|i| {
+            let mut tfm = Mat4::identity();
+            tfm[(2, 3)] = 10. * i as Real;
+            Rectangle::new(100 as Real, 100 as Real, tfm).unwrap()
+        })
+        .collect()
+}
+
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0.1, 1.2, 0.5), Mat5::identity())
+}
+
+// hits scattered slightly around the straight line of the seed
+fn inputs() -> (Vec<Mat2>, Vec<Vec2>) {
+    let (seed_x, _) = seed();
+    let step = 10. / seed_x[3].tan();
+    let noise = vec![Mat2::identity() * 0.01; 3];
+    let measurements = [(0.1, 0.0), (0.02, 0.02), (0.05, -0.01)].iter()
+        .enumerate()
+        .map(|(i, (dx, dy))| Vec2::new(step * seed_x[2].cos() * i as Real + dx, step * seed_x[2].sin() * i as Real + dy))
+        .collect();
+    (noise, measurements)
+}
+
+fn three_component_mixture() -> BetheHeitlerMixture {
+    BetheHeitlerMixture::new(vec![(0.6, 0.95, 0.001), (0.3, 0.8, 0.01), (0.1, 0.5, 0.05)])
+}
+
+#[test]
+fn no_loss_matches_kalman_filter() {
+    let (seed_x, seed_c) = seed();
+    let (noise, measurements) = inputs();
+    let sensors = initialize_sensors(3);
+
+    let result = gaussian_sum::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &BetheHeitlerMixture::no_loss(), 4).unwrap();
+
+    // a single component without energy loss is the regular filter
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &seed_x, &seed_c, &noise, &measurements, &sensors).unwrap();
+
+    for i in 0..3 {
+        assert!((result.mean_state_vec[i] - workspace.filt_state_vec()[i]).norm() < 1e-9);
+        assert!((result.mean_cov_mat[i] - workspace.filt_cov_mat()[i]).norm() < 1e-9);
+        assert!((result.mode_state_vec[i] - workspace.filt_state_vec()[i]).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn follows_track_between_separated_sensors() {
+    let (seed_x, seed_c) = seed();
+    let (noise, measurements) = inputs();
+
+    let result = gaussian_sum::run(&seed_x, &seed_c, &noise, &measurements, &initialize_sensors(3), &three_component_mixture(), 4).unwrap();
+
+    for (state, hit) in result.mean_state_vec.iter().zip(measurements.iter()) {
+        assert!((state[0] - hit[0]).abs() < 0.2);
+        assert!((state[1] - hit[1]).abs() < 0.2);
+    }
+}
+
+#[test]
+fn rejects_bad_bethe_heitler_components() {
+    let (seed_x, seed_c) = seed();
+    let (noise, measurements) = inputs();
+    let sensors = initialize_sensors(3);
+
+    let bad_mixtures = [
+        BetheHeitlerMixture::new(vec![]),
+        BetheHeitlerMixture::new(vec![(1.0, 0.0, 0.01)]),
+        BetheHeitlerMixture::new(vec![(1.0, -0.5, 0.01)]),
+        BetheHeitlerMixture::new(vec![(0.0, 0.9, 0.01), (1.0, 0.95, 0.001)]),
+        BetheHeitlerMixture::new(vec![(Real::NAN, 0.9, 0.01)])
+    ];
+
+    for mixture in bad_mixtures.iter() {
+        match gaussian_sum::run(&seed_x, &seed_c, &noise, &measurements, &sensors, mixture, 4) {
+            Err(Error::Config(ConfigError::InvalidValue(_))) => {},
+            _ => panic!("bad Bethe-Heitler mixture was accepted")
+        }
+    }
+}
+
+#[test]
+fn components_are_reduced() {
+    let (seed_x, seed_c) = seed();
+    let (noise, measurements) = inputs();
+
+    let result = gaussian_sum::run(&seed_x, &seed_c, &noise, &measurements, &initialize_sensors(3), &three_component_mixture(), 4).unwrap();
+
+    for mixture in result.components.iter() {
+        assert!(mixture.len() <= 4);
+        let total : Real = mixture.iter().map(|c| c.weight).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn energy_loss_raises_q_over_p() {
+    let (seed_x, seed_c) = seed();
+    let (noise, measurements) = inputs();
+
+    let result = gaussian_sum::run(&seed_x, &seed_c, &noise, &measurements, &initialize_sensors(3), &three_component_mixture(), 6).unwrap();
+
+    // losing energy increases |q/p| on average
+    assert!(result.mean_state_vec[2][4] > seed_x[4]);
+}
+
+#[test]
+fn mode_of_separated_mixture() {
+    let heavy = Component{weight: 0.8, state_vec: Vec5::zeros(), cov_mat: Mat5::identity()};
+    let light = Component{weight: 0.2, state_vec: Vec5::repeat(10.), cov_mat: Mat5::identity()};
+    let mixture = vec![heavy, light];
+
+    let (mean, _) = gaussian_sum::mean(&mixture);
+    let mode = gaussian_sum::mode(&mixture, &mean).unwrap();
+
+    assert!((mean - Vec5::repeat(2.)).norm() < 1e-9);
+    assert!(mode.norm() < 1e-6);
+}
+
+#[test]
+fn mode_skips_zero_weight_components() {
+    let heavy = Component{weight: 1.0, state_vec: Vec5::zeros(), cov_mat: Mat5::identity()};
+    let empty = Component{weight: 0.0, state_vec: Vec5::repeat(10.), cov_mat: Mat5::identity()};
+    let mixture = vec![heavy, empty];
+
+    let (mean, _) = gaussian_sum::mean(&mixture);
+    let mode = gaussian_sum::mode(&mixture, &mean).unwrap();
+
+    assert!(mode.iter().all(|value| value.is_finite()));
+    assert!(mode.norm() < 1e-6);
+}
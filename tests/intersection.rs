@@ -0,0 +1,86 @@
+use kalman_rs::geometry::{Disc, Polygon, Rectangle, Trapezoid};
+use kalman_rs::geometry::{intersection, utils};
+use kalman_rs::geometry::traits::{Plane, Surface, Transform};
+use kalman_rs::filter::prediction;
+use kalman_rs::config::*;
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+
+#[test]
+fn rotated_and_shifted_rectangle() {
+    // sensor facing global x, centered at x = 10
+    let mut tfm = Rot3::from_euler_angles(0., FRAC_PI_2, 0.).to_homogeneous();
+    tfm[(0, 3)] = 10.;
+    let sensor = Rectangle::new(20., 20., tfm).unwrap();
+
+    let start = P3::new(0., 1., 2.);
+    let crossing = sensor.intersect(&start, &Vec3::x()).unwrap();
+    assert!((crossing.point - P3::new(10., 1., 2.)).norm() < 1e-9);
+    assert!((crossing.path_length - 10.).abs() < 1e-9);
+    assert!(crossing.incidence_angle.abs() < 1e-6);
+
+    let diagonal = Vec3::new(1., 1., 0.).normalize();
+    let crossing = sensor.intersect(&start, &diagonal).unwrap();
+    assert!((crossing.point - P3::new(10., 11., 2.)).norm() < 1e-9);
+    assert!((crossing.path_length - 10. * 2f64.sqrt()).abs() < 1e-9);
+    assert!((crossing.incidence_angle - FRAC_PI_4).abs() < 1e-9);
+
+    // behind the start the surface is not reached, but the plane crossing is still available
+    let behind = P3::new(20., 0., 0.);
+    assert!(sensor.intersect(&behind, &Vec3::x()).is_none());
+    assert!((intersection::plane_intersection(&sensor, &behind, &Vec3::x()).unwrap().path_length + 10.).abs() < 1e-9);
+
+    // parallel to the plane
+    assert!(intersection::plane_intersection(&sensor, &start, &Vec3::y()).is_none());
+
+    // so close to parallel that the crossing would be far out of any detector
+    let grazing = Vec3::new(1e-5, 1., 0.).normalize();
+    assert!(intersection::plane_intersection(&sensor, &start, &grazing).is_none());
+}
+
+// the crossing uses the plane stored on the sensor instead of finding it from the transform again
+#[test]
+fn cached_plane_matches_transform() {
+    let mut tfm = Rot3::from_euler_angles(0.3, -0.2, 1.1).to_homogeneous();
+    tfm[(0, 3)] = 2.;
+    tfm[(1, 3)] = -3.;
+    tfm[(2, 3)] = 7.;
+
+    let rectangle = Rectangle::new(4., 6., tfm).unwrap();
+    let trapezoid = Trapezoid::new(2., 5., tfm, 2.).unwrap();
+    let disc = Disc::new(1., 3., 0., 1., tfm).unwrap();
+    let polygon = Polygon::new(vec![P2::new(0., 0.), P2::new(1., 0.), P2::new(0., 1.)], tfm).unwrap();
+
+    let planes : [(&dyn Plane, &dyn Transform); 4] = [(&rectangle, &rectangle), (&trapezoid, &trapezoid),
+                                                      (&disc, &disc), (&polygon, &polygon)];
+    for (plane, transform) in planes.iter() {
+        let (_, normal, plane_constant) = utils::plane_from_transform(|point| transform.to_global(point));
+        assert!((plane.plane_normal_vec() - normal).norm() < 1e-12);
+        assert!((plane.plane_constant() - plane_constant).abs() < 1e-12);
+    }
+
+    let start = P3::new(1., 1., -5.);
+    let direction = Vec3::new(0.1, -0.2, 1.).normalize();
+    let crossing = intersection::plane_intersection(&rectangle, &start, &direction).unwrap();
+    assert!(rectangle.on_plane(&crossing.point));
+    assert!((crossing.point - (start + direction * crossing.path_length)).norm() < 1e-9);
+}
+
+#[test]
+fn linear_extrapolation_onto_shifted_sensor() {
+    let start = Rectangle::new(100., 100., Mat4::identity()).unwrap();
+    let end = Rectangle::new(100., 100., Mat4::new_translation(&Vec3::new(0., 0., 10.))).unwrap();
+
+    // theta = 0, phi = pi / 4: moving along (1, 0, 1)
+    let state = Vec5::new(1., 2., 0., FRAC_PI_4, 0.);
+    let pred = prediction::linear_extrapolation(&start, &end, &state);
+    assert!((pred[0] - 11.).abs() < 1e-9);
+    assert!((pred[1] - 2.).abs() < 1e-9);
+
+    // a track parallel to the sensors never reaches the next one
+    let parallel = Vec5::new(1., 2., 0., 0., 0.);
+    let pred = prediction::linear_extrapolation(&start, &end, &parallel);
+    assert!(!pred[0].is_finite());
+    assert!(prediction::linear_state_vector(&start, &end, &parallel, &Mat5::identity(), &Tolerance::None).is_err());
+}
@@ -0,0 +1,108 @@
+use kalman_rs::geometry::{Rectangle, intersection};
+use kalman_rs::geometry::traits::Transform;
+use kalman_rs::filter::iterated;
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::config::*;
+
+use nalgebra as na;
+
+
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+// sensors 10 apart along z, tilted by a growing angle around the x axis
+fn initialize_shifted_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|i| {
+            let mut tfm = Rot3::from_axis_angle(&Vec3::x_axis(), 0.05 * i as Real).to_homogeneous();
+            tfm[(2, 3)] = 10. * i as Real;
+            Rectangle::new(100 as Real, 100 as Real, tfm).unwrap()
+        })
+        .collect()
+}
+
+fn inputs() -> (Vec<Mat2>, Vec<Vec2>) {
+    let noise = vec![Mat2::identity() * 0.01; 3];
+    let measurements = vec![Vec2::new(0.1, 0.0), Vec2::new(0.12, 0.02), Vec2::new(0.15, 0.01)];
+    (noise, measurements)
+}
+
+fn options(max_iterations: usize) -> FitterOptions {
+    FitterOptions::builder()
+        .max_iterations(max_iterations)
+        .seed_covariance(Mat5::identity() * 0.1)
+        .build()
+}
+
+fn seed() -> Vec5 {
+    Vec5::new(0., 0., 0.1, 0.3, 0.5)
+}
+
+#[test]
+fn linear_problem_converges_immediately() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+
+    let fit = iterated::run(&seed(), &noise, &measurements, &sensors, &options(10), 1e-8).unwrap();
+
+    // a second pass is needed to see that the chi squared did not change
+    assert!(fit.converged);
+    assert_eq!(fit.iterations, 2);
+
+    let mut kf = KalmanFilter::with_options(seed(), options(1));
+    for i in 0..3 {
+        kf.predict(&sensors[i]).unwrap();
+        kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+    assert!((fit.chi_squared - kf.chi_squared()).abs() < 1e-8);
+}
+
+#[test]
+fn iteration_cap() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+
+    let fit = iterated::run(&seed(), &noise, &measurements, &sensors, &options(1), 1e-8).unwrap();
+
+    assert_eq!(fit.iterations, 1);
+    assert!(!fit.converged);
+    assert_eq!(fit.smoothed.state_vec().len(), 3);
+}
+
+#[test]
+fn tilted_sensors_converge() {
+    let sensors = initialize_shifted_sensors(5);
+
+    // straight track through every sensor, measured without noise
+    let truth = Vec5::new(0.1, 0.2, 0.1, 1.2, 0.5);
+    let start = sensors[0].to_global(P3::new(truth[0], truth[1], 0.));
+    let direction = Vec3::new(truth[3].cos() * truth[2].cos(), truth[3].cos() * truth[2].sin(), truth[3].sin());
+
+    let measurements : Vec<Vec2> = sensors.iter()
+        .map(|sensor| {
+            let crossing = intersection::plane_intersection(sensor, &start, &direction).unwrap();
+            let local = sensor.to_local(crossing.point);
+            Vec2::new(local.x, local.y)
+        })
+        .collect();
+    let noise = vec![Mat2::identity() * 1e-4; 5];
+
+    // seeded with directions far from the truth
+    let seed = Vec5::new(0., 0., 0.4, 0.9, 0.5);
+    let tolerance = 1e-6;
+
+    let fit = iterated::run(&seed, &noise, &measurements, &sensors, &options(20), tolerance).unwrap();
+    assert!(fit.converged);
+    assert!(fit.iterations > 1);
+
+    // the last pass hardly moved the track
+    let previous = iterated::run(&seed, &noise, &measurements, &sensors, &options(fit.iterations - 1), tolerance).unwrap();
+    assert!((fit.smoothed.state_vec()[0] - previous.smoothed.state_vec()[0]).norm() < tolerance);
+
+    // and iterating got closer to the truth than a single pass
+    let single = iterated::run(&seed, &noise, &measurements, &sensors, &options(1), tolerance).unwrap();
+    let error = (fit.smoothed.state_vec()[0] - truth).fixed_rows::<na::U4>(0).norm();
+    let single_error = (single.smoothed.state_vec()[0] - truth).fixed_rows::<na::U4>(0).norm();
+    assert!(error < single_error);
+}
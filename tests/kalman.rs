@@ -0,0 +1,229 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::filter::{filter_gain, utils};
+use kalman_rs::error::{Error, SensorError};
+use kalman_rs::config::*;
+
+
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+fn inputs() -> (Vec<Mat2>, Vec<Vec2>) {
+    let noise = vec![Mat2::identity() * 0.01; 3];
+    let measurements = vec![Vec2::new(0.1, 0.0), Vec2::new(0.12, 0.02), Vec2::new(0.15, 0.01)];
+    (noise, measurements)
+}
+
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0.1, 0.3, 0.5), Mat5::identity() * 0.1)
+}
+
+#[test]
+fn streaming_matches_batch() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+
+    let meas_map_mat = utils::sensor_mapping_mat();
+    let mut x = seed_x;
+    let mut c = seed_c;
+
+    for i in 0..3 {
+        kf.predict(&sensors[i]).unwrap();
+        kf.update(&measurements[i], &noise[i]).unwrap();
+
+        // intermediate state is available after every measurement
+        let gain = filter_gain::kalman_gain(&c, &meas_map_mat, &noise[i]);
+        x = filter_gain::state_vector(&x, &gain, &measurements[i], &meas_map_mat);
+        c = filter_gain::covariance_matrix(&gain, &meas_map_mat, &c);
+
+        assert!((kf.state_vec() - x).norm() < 1e-9);
+        assert!((kf.cov_mat() - c).norm() < 1e-9);
+    }
+
+    assert_eq!(kf.steps().len(), 3);
+    assert!(kf.chi_squared() > 0.);
+
+    let smoothed = kf.smooth().unwrap();
+    assert_eq!(smoothed.state_vec().len(), 3);
+    for state_vec in smoothed.state_vec().iter() {
+        assert!((state_vec - x).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn predict_without_update_is_a_hole() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    kf.predict(&sensors[0]).unwrap();
+    kf.update(&measurements[0], &noise[0]).unwrap();
+    kf.predict(&sensors[1]).unwrap();
+    kf.predict(&sensors[2]).unwrap();
+    kf.update(&measurements[2], &noise[2]).unwrap();
+
+    assert!(kf.steps()[1].measurement.is_none());
+
+    let smoothed = kf.smooth().unwrap();
+    assert_eq!(smoothed.res_vec()[1], Vec2::zeros());
+}
+
+#[test]
+fn predict_outside_sensor() {
+    let sensors = initialize_sensors(1);
+    let small = Rectangle::new(0.1, 0.1, Mat4::identity()).unwrap();
+    let (_, seed_c) = seed();
+    let seed_x = Vec5::new(5., 5., 0.1, 0.3, 0.5);
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    kf.predict(&sensors[0]).unwrap();
+
+    match kf.predict(&small) {
+        Err(Error::Sensor(SensorError::OutsideSensorBounds)) => {},
+        _ => panic!("prediction should have missed the sensor")
+    }
+    assert_eq!(kf.steps().len(), 1);
+    assert_eq!(kf.state_vec(), &seed_x);
+}
+
+#[test]
+fn outlier_is_ignored() {
+    let sensors = initialize_sensors(2);
+    let (noise, _) = inputs();
+    let (seed_x, _) = seed();
+    let options = FitterOptions::builder()
+                    .outlier_chi_squared(9.)
+                    .seed_covariance(Mat5::identity() * 0.1)
+                    .build();
+
+    let mut kf = KalmanFilter::with_options(seed_x, options);
+    kf.predict(&sensors[0]).unwrap();
+    kf.update(&Vec2::new(0.0, 0.0), &noise[0]).unwrap();
+    let before = kf.state_vec().clone();
+
+    kf.predict(&sensors[1]).unwrap();
+    let chi_squared = kf.update(&Vec2::new(5.0, 5.0), &noise[1]).unwrap();
+
+    assert!(chi_squared > 9.);
+    assert!(kf.steps()[1].outlier);
+    assert_eq!(kf.state_vec(), &before);
+}
+
+#[test]
+fn joseph_matches_standard() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+    let joseph = FitterOptions::builder()
+                    .covariance_update(CovarianceUpdate::Joseph)
+                    .seed_covariance(seed_c)
+                    .build();
+
+    let mut standard_kf = KalmanFilter::new(seed_x, seed_c);
+    let mut joseph_kf = KalmanFilter::with_options(seed_x, joseph);
+
+    for i in 0..3 {
+        standard_kf.predict(&sensors[i]).unwrap();
+        standard_kf.update(&measurements[i], &noise[i]).unwrap();
+        joseph_kf.predict(&sensors[i]).unwrap();
+        joseph_kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+
+    assert!((standard_kf.cov_mat() - joseph_kf.cov_mat()).norm() < 1e-9);
+}
+
+#[test]
+fn unscented_propagator_matches_linear() {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+    let unscented = FitterOptions::builder()
+                    .propagator(Propagator::Unscented)
+                    .seed_covariance(seed_c)
+                    .build();
+
+    let mut linear_kf = KalmanFilter::new(seed_x, seed_c);
+    let mut unscented_kf = KalmanFilter::with_options(seed_x, unscented);
+
+    for i in 0..3 {
+        linear_kf.predict(&sensors[i]).unwrap();
+        linear_kf.update(&measurements[i], &noise[i]).unwrap();
+        unscented_kf.predict(&sensors[i]).unwrap();
+        unscented_kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+
+    let linear = linear_kf.smooth().unwrap();
+    let unscented = unscented_kf.smooth().unwrap();
+    for (a, b) in linear.state_vec().iter().zip(unscented.state_vec().iter()) {
+        assert!((a - b).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn material_inflates_angle_covariance() {
+    let sensors = initialize_sensors(2);
+    let (seed_x, seed_c) = seed();
+    let material = FitterOptions::builder()
+                    .material_effects(true)
+                    .radiation_length_fraction(0.05)
+                    .particle_hypothesis(ParticleHypothesis::Electron)
+                    .seed_covariance(seed_c)
+                    .build();
+
+    let mut kf = KalmanFilter::with_options(seed_x, material);
+    kf.predict(&sensors[0]).unwrap();
+    kf.predict(&sensors[1]).unwrap();
+
+    assert!(kf.cov_mat()[(2, 2)] > seed_c[(2, 2)]);
+    assert!(kf.cov_mat()[(3, 3)] > seed_c[(3, 3)]);
+    assert_eq!(kf.cov_mat()[(0, 0)], seed_c[(0, 0)]);
+}
+
+#[test]
+fn smoother_can_be_disabled() {
+    let sensors = initialize_sensors(2);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+    let disabled = FitterOptions::builder()
+                    .smoother(Smoother::Disabled)
+                    .seed_covariance(seed_c)
+                    .build();
+
+    let mut kf = KalmanFilter::with_options(seed_x, disabled);
+    for i in 0..2 {
+        kf.predict(&sensors[i]).unwrap();
+        kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+
+    let smoothed = kf.smooth().unwrap();
+    assert_eq!(smoothed.state_vec()[0], kf.steps()[0].filt_state_vec);
+}
+
+#[test]
+fn update_misuse() {
+    let sensors = initialize_sensors(1);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    assert!(kf.update(&measurements[0], &noise[0]).is_err());
+
+    // a second update on the same sensor replaces the first
+    kf.predict(&sensors[0]).unwrap();
+    kf.update(&Vec2::new(1.0, 1.0), &noise[0]).unwrap();
+    let chi_squared_inc = kf.update(&measurements[0], &noise[0]).unwrap();
+
+    let mut single = KalmanFilter::new(seed_x, seed_c);
+    single.predict(&sensors[0]).unwrap();
+    single.update(&measurements[0], &noise[0]).unwrap();
+
+    assert_eq!(kf.chi_squared(), chi_squared_inc);
+    assert_eq!(kf.chi_squared(), single.chi_squared());
+    assert_eq!(kf.state_vec(), single.state_vec());
+}
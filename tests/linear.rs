@@ -0,0 +1,174 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::{linear, filter_gain, prediction, utils};
+use kalman_rs::filter::workspace::FitWorkspace;
+use kalman_rs::error::{Error, MatrixError};
+use kalman_rs::config::*;
+
+use std::f64::consts::FRAC_PI_2;
+
+
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+// sensors stacked along z, 10 apart
+fn initialize_shifted_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|i| {
+            let mut tfm = Mat4::identity();
+            tfm[(2, 3)] = 10. * i as Real;
+            Rectangle::new(100 as Real, 100 as Real, tfm).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn smoothed_results_in_sensor_order() {
+    let sensors = initialize_sensors(5);
+    let noise = vec![Mat2::identity() * 0.01; 5];
+    let measurements : Vec<Vec2> = (0..5).map(|i| Vec2::new(0.1 * i as Real, 0.)).collect();
+
+    // `run` seeds at random, so the same fit is done through the workspace with a fixed seed
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &Vec5::new(0., 0., 0., FRAC_PI_2, 0.), &(Mat5::identity() * 0.1), &noise, &measurements, &sensors).unwrap();
+    let smoothed = workspace.smoothed_data();
+
+    assert_eq!(smoothed.state_vec().len(), 5);
+
+    // sensors share a plane, so every smoothed state is the same and the residuals
+    // step along with the measurements of the sensor they belong to
+    for i in 1..5 {
+        assert!((smoothed.state_vec()[i] - smoothed.state_vec()[0]).norm() < 1e-6);
+        assert!((smoothed.res_vec()[i].x - smoothed.res_vec()[i - 1].x - 0.1).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn smoothed_data_follows_sensor_order() {
+    let sensors = initialize_shifted_sensors(5);
+    let noise = vec![Mat2::identity() * 0.01; 5];
+
+    // straight track crossing every sensor at a different spot
+    let (theta, phi) : (Real, Real) = (0.3, 1.2);
+    let direction = Vec3::new(phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin());
+    let measurements : Vec<Vec2> = (0..5).map(|i| {
+            let hit = Vec3::new(0.2, -0.1, 0.) + direction * (10. * i as Real / direction.z);
+            Vec2::new(hit.x, hit.y)
+        })
+        .collect();
+    let seed = Vec5::new(0., 0., theta, phi, 0.5);
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &seed, &(Mat5::identity() * 0.1), &noise, &measurements, &sensors).unwrap();
+    let smoothed = workspace.smoothed_data();
+
+    // results reversed onto the last sensor first would put the first state near the last hit
+    assert_eq!(smoothed.state_vec().len(), 5);
+    for (state, hit) in smoothed.state_vec().iter().zip(measurements.iter()) {
+        assert!((Vec2::new(state[0], state[1]) - hit).norm() < 0.05);
+    }
+}
+
+#[test]
+fn filtered_covariance_uses_gain_on_the_left() {
+    let sensors = initialize_sensors(4);
+    let noise = vec![Mat2::identity() * 0.01; 4];
+    let measurements : Vec<Vec2> = (0..4).map(|i| Vec2::new(0.1 * i as Real, -0.1 * i as Real)).collect();
+
+    // position and direction are correlated so that C (1 - KH) and (1 - KH) C differ
+    let mut seed_cov = Mat5::identity() * 0.1;
+    seed_cov[(0, 2)] = 0.05;
+    seed_cov[(2, 0)] = 0.05;
+    seed_cov[(1, 3)] = -0.04;
+    seed_cov[(3, 1)] = -0.04;
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &Vec5::new(0., 0., 0., FRAC_PI_2, 0.), &seed_cov, &noise, &measurements, &sensors).unwrap();
+
+    let meas_map_mat = utils::sensor_mapping_mat();
+    let gain = filter_gain::kalman_gain(&seed_cov, &meas_map_mat, &noise[0]);
+    let joseph = filter_gain::covariance_matrix_joseph(&gain, &meas_map_mat, &seed_cov, &noise[0]);
+
+    assert!((workspace.filt_cov_mat()[0] - joseph).norm() < 1e-12);
+    for cov in workspace.filt_cov_mat() {
+        assert!((cov - cov.transpose()).norm() < 1e-12);
+    }
+}
+
+#[test]
+fn run_in_extrapolates_between_sensors() {
+    let sensors = initialize_shifted_sensors(4);
+    let noise = vec![Mat2::identity() * 0.01; 4];
+
+    // straight track starting at (0.2, -0.1) on the first sensor
+    let (theta, phi) : (Real, Real) = (0.3, 1.2);
+    let direction = Vec3::new(phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin());
+    let truth : Vec<Vec5> = (0..4).map(|i| {
+            let hit = Vec3::new(0.2, -0.1, 0.) + direction * (10. * i as Real / direction.z);
+            Vec5::new(hit.x, hit.y, theta, phi, 0.5)
+        })
+        .collect();
+    let measurements : Vec<Vec2> = truth.iter().map(|x| Vec2::new(x[0], x[1])).collect();
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &truth[0], &(Mat5::identity() * 0.1), &noise, &measurements, &sensors).unwrap();
+
+    // both local coordinates are measured and the seed is carried onto each sensor
+    for i in 0..4 {
+        assert!((workspace.state_vec()[i] - truth[i]).norm() < 1e-6);
+    }
+}
+
+#[test]
+fn both_local_coordinates_are_measured() {
+    let sensors = initialize_sensors(1);
+    let noise = vec![Mat2::identity() * 0.01];
+    let measurements = vec![Vec2::new(0.5, -0.5)];
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &Vec5::new(0., 0., 0., FRAC_PI_2, 0.), &(Mat5::identity() * 0.1), &noise, &measurements, &sensors).unwrap();
+
+    // H maps loc0 and loc1 onto the hit, so both are pulled towards it by C / (C + V)
+    let pull = 0.1 / (0.1 + 0.01);
+    assert!((workspace.filt_state_vec()[0][0] - 0.5 * pull).abs() < 1e-12);
+    assert!((workspace.filt_state_vec()[0][1] + 0.5 * pull).abs() < 1e-12);
+}
+
+#[test]
+fn smoother_uses_the_predicted_covariance() {
+    let sensors = initialize_shifted_sensors(2);
+    let noise = vec![Mat2::identity() * 0.01; 2];
+    let measurements = vec![Vec2::new(0.1, -0.1), Vec2::new(4.0, 1.0)];
+    let seed = Vec5::new(0., 0., 0.3, 1.2, 0.5);
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &seed, &(Mat5::identity() * 0.1), &noise, &measurements, &sensors).unwrap();
+
+    // Rauch-Tung-Striebel step from the second sensor back onto the first: A = C_0 J^T (J C_0 J^T)^-1
+    let filt_x = workspace.filt_state_vec()[0];
+    let filt_c = workspace.filt_cov_mat()[0];
+    let jacobian = prediction::linear_extrapolation_jacobian(&sensors[0], &sensors[1], &filt_x);
+    let pred_x = prediction::linear_extrapolation(&sensors[0], &sensors[1], &filt_x);
+    let pred_c = prediction::covariance_matrix(&jacobian, &filt_c);
+    let gain = filt_c * jacobian.transpose() * pred_c.try_inverse().unwrap();
+
+    let smth_x = filt_x + gain * (workspace.state_vec()[1] - pred_x);
+    let smth_c = filt_c + gain * (workspace.cov_mat()[1] - pred_c) * gain.transpose();
+
+    assert!((workspace.state_vec()[0] - smth_x).norm() < 1e-9);
+    assert!((workspace.cov_mat()[0] - smth_c).norm() < 1e-9);
+}
+
+#[test]
+fn degenerate_track_is_an_error() {
+    let sensors = initialize_sensors(2);
+    let noise = vec![Mat2::zeros(); 2];
+    let measurements = vec![Vec2::new(0.1, 0.), Vec2::new(0.2, 0.)];
+
+    // without any uncertainty the residual covariance can not be inverted
+    let mut workspace = FitWorkspace::new();
+    match linear::run_in(&mut workspace, &Vec5::new(0., 0., 0., FRAC_PI_2, 0.), &Mat5::zeros(), &noise, &measurements, &sensors) {
+        Err(Error::Matrix(MatrixError::NonInvertible)) => {},
+        _ => panic!("a degenerate track was fitted")
+    }
+}
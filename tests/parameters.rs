@@ -0,0 +1,91 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::parameters::{self, BoundParameters, FreeParameters};
+use kalman_rs::config::*;
+
+
+// rotated and shifted sensor so none of the frames line up with the global axes
+fn tilted_sensor() -> Rectangle {
+    let mut tfm = Rot3::from_euler_angles(0.3, -0.2, 0.5).to_homogeneous();
+    tfm[(0, 3)] = 10.;
+    tfm[(1, 3)] = -5.;
+    tfm[(2, 3)] = 100.;
+    Rectangle::new(50., 50., tfm).unwrap()
+}
+
+fn bound() -> BoundParameters {
+    let cov_mat = Mat5::from_diagonal(&Vec5::new(0.01, 0.02, 0.001, 0.002, 0.0001));
+    BoundParameters::new(Vec5::new(1.5, -2., 0.4, 0.9, 0.5), cov_mat)
+}
+
+#[test]
+fn bound_free_round_trip() {
+    let sensor = tilted_sensor();
+    let bound = bound();
+
+    let free = bound.to_free(&sensor);
+    assert!((free.direction().norm() - 1.).abs() < 1e-12);
+
+    let back = free.to_bound(&sensor);
+    assert!((back.state_vec - bound.state_vec).norm() < 1e-9);
+    assert!((back.cov_mat - bound.cov_mat).norm() < 1e-9);
+}
+
+#[test]
+fn curvilinear_round_trip() {
+    let sensor = tilted_sensor();
+    let bound = bound();
+
+    let curvilinear = bound.to_curvilinear(&sensor);
+    assert!(curvilinear.state_vec[0].abs() < 1e-9);
+    assert!(curvilinear.state_vec[1].abs() < 1e-9);
+
+    let free = bound.to_free(&sensor);
+    let free_again = curvilinear.to_free();
+    assert!((free_again.state_vec - free.state_vec).norm() < 1e-9);
+
+    // the free covariances differ since position errors lie in the plane of each frame, but
+    // both map back onto the same bound covariance
+    let back = curvilinear.to_bound(&sensor);
+    assert!((back.state_vec - bound.state_vec).norm() < 1e-9);
+    assert!((back.cov_mat - bound.cov_mat).norm() < 1e-9);
+}
+
+#[test]
+fn jacobians_match_finite_differences() {
+    let sensor = tilted_sensor();
+    let bound_state = bound().state_vec;
+    let step = 1e-6;
+
+    let analytic = parameters::bound_to_free_jacobian(&sensor, &bound_state);
+    for j in 0..5 {
+        let mut forward = bound_state.clone();
+        forward[j] += step;
+        let mut backward = bound_state.clone();
+        backward[j] -= step;
+
+        let column = (BoundParameters::new(forward, Mat5::zeros()).to_free(&sensor).state_vec
+                    - BoundParameters::new(backward, Mat5::zeros()).to_free(&sensor).state_vec) / (2. * step);
+        assert!((analytic.column(j) - column).norm() < 1e-6);
+    }
+
+    // evaluated off the surface so the path length terms are exercised
+    let mut free_state = bound().to_free(&sensor).state_vec;
+    free_state[0] += 0.3;
+    free_state[2] -= 0.2;
+
+    let analytic = parameters::free_to_bound_jacobian(&sensor, &free_state);
+    for j in 0..7 {
+        // only directions of unit length are valid, so vary the angles through the position and q/p
+        if j >= 3 && j < 6 {
+            continue
+        }
+        let mut forward = free_state.clone();
+        forward[j] += step;
+        let mut backward = free_state.clone();
+        backward[j] -= step;
+
+        let column = (FreeParameters::new(forward, Mat7::zeros()).to_bound(&sensor).state_vec
+                    - FreeParameters::new(backward, Mat7::zeros()).to_bound(&sensor).state_vec) / (2. * step);
+        assert!((analytic.column(j) - column).norm() < 1e-6);
+    }
+}
@@ -0,0 +1,97 @@
+use kalman_rs::geometry::{Rectangle, PerigeeSurface};
+use kalman_rs::filter::perigee::{self, PerigeePropagation};
+use kalman_rs::filter::parameters::BoundParameters;
+use kalman_rs::config::*;
+
+use std::f64::consts::FRAC_PI_2;
+
+
+// d0, z0, phi, theta, q/p
+fn truth() -> Vec5 {
+    Vec5::new(0.5, 3., 0.7, 1.2, 0.5)
+}
+
+fn beam() -> PerigeeSurface {
+    PerigeeSurface::new(P3::new(1., -2., 0.))
+}
+
+fn perigee_point(truth: &Vec5, beam: &PerigeeSurface) -> P3 {
+    let b = beam.beam_position;
+    P3::new(b.x - truth[0] * truth[2].sin(), b.y + truth[0] * truth[2].cos(), b.z + truth[1])
+}
+
+// sensor parallel to the x-y plane centered on `point` along with the track state on it
+fn sensor_at(point: &P3, direction: &Vec3, q_over_p: Real) -> (Rectangle, BoundParameters) {
+    let mut tfm = Mat4::identity();
+    tfm[(0, 3)] = point.x;
+    tfm[(1, 3)] = point.y;
+    tfm[(2, 3)] = point.z;
+    let sensor = Rectangle::new(100., 100., tfm).unwrap();
+
+    let transverse = (direction.x * direction.x + direction.y * direction.y).sqrt();
+    let state_vec = Vec5::new(0., 0., direction.y.atan2(direction.x), direction.z.atan2(transverse), q_over_p);
+
+    (sensor, BoundParameters::new(state_vec, Mat5::identity() * 0.01))
+}
+
+fn assert_close(found: &Vec5, expected: &Vec5) {
+    assert!((found - expected).norm() < 1e-6, "found {} expected {}", found, expected);
+}
+
+#[test]
+fn straight_line_recovers_perigee() {
+    let truth = truth();
+    let beam = beam();
+
+    let direction = Vec3::new(truth[3].sin() * truth[2].cos(), truth[3].sin() * truth[2].sin(), truth[3].cos());
+    let point = perigee_point(&truth, &beam) + direction * 150.;
+    let (sensor, bound) = sensor_at(&point, &direction, truth[4]);
+
+    let result = perigee::extrapolate(&sensor, &bound, &beam, &PerigeePropagation::StraightLine);
+    assert_close(&result.state_vec, &truth);
+
+    // q/p is unchanged by the extrapolation
+    assert!((result.cov_mat[(4, 4)] - 0.01).abs() < 1e-9);
+    assert!((result.cov_mat - result.cov_mat.transpose()).norm() < 1e-9);
+    assert!(result.cov_mat[(0, 0)] > 0.);
+}
+
+#[test]
+fn helix_recovers_perigee() {
+    let truth = truth();
+    let beam = beam();
+    let b_field = 2.;
+
+    // follow the helix of a positive particle 200 mm (transverse) away from the perigee. It turns
+    // clockwise seen from +z since q v x B points to the right of the motion.
+    let transverse_momentum = truth[3].sin() / truth[4];
+    let radius = transverse_momentum / (B_FIELD_CONSTANT * b_field);
+    let start = perigee_point(&truth, &beam);
+    let center = P3::new(start.x + radius * truth[2].sin(), start.y - radius * truth[2].cos(), 0.);
+
+    let transverse_path = 200.;
+    let turn = -transverse_path / radius;
+    let arm = (start.x - center.x, start.y - center.y);
+    let point = P3::new(center.x + arm.0 * turn.cos() - arm.1 * turn.sin(),
+                        center.y + arm.0 * turn.sin() + arm.1 * turn.cos(),
+                        start.z + transverse_path / truth[3].tan());
+    let direction = Vec3::new(truth[3].sin() * (truth[2] + turn).cos(), truth[3].sin() * (truth[2] + turn).sin(), truth[3].cos());
+
+    let (sensor, bound) = sensor_at(&point, &direction, truth[4]);
+
+    let result = perigee::extrapolate(&sensor, &bound, &beam, &PerigeePropagation::Helix{b_field: b_field});
+    assert_close(&result.state_vec, &truth);
+
+    // without the field the straight line misses the true perigee
+    let straight = perigee::extrapolate(&sensor, &bound, &beam, &PerigeePropagation::StraightLine);
+    assert!((straight.d0() - truth[0]).abs() > 1e-3);
+}
+
+#[test]
+fn angles_follow_perigee_convention() {
+    let beam = PerigeeSurface::origin();
+    let (sensor, bound) = sensor_at(&P3::new(0., 10., 10.), &Vec3::new(0., 1., 1.).normalize(), 1.);
+
+    let result = perigee::extrapolate(&sensor, &bound, &beam, &PerigeePropagation::StraightLine);
+    assert_close(&result.state_vec, &Vec5::new(0., 0., FRAC_PI_2, FRAC_PI_2 / 2., 1.));
+}
@@ -26,6 +26,15 @@ fn initialize_trap() -> Trapezoid {
     Trapezoid::new(top_base, bottom_base, tfm, height).unwrap()
 }
 
+// rotated about x by 90 degrees and shifted, so the sensor plane is y = 4
+fn placed_tfm() -> Mat4 {
+    let mut tfm = Rot3::from_euler_angles(std::f64::consts::FRAC_PI_2, 0., 0.).to_homogeneous();
+    tfm[(0, 3)] = 1.;
+    tfm[(1, 3)] = 4.;
+    tfm[(2, 3)] = -2.;
+    tfm
+}
+
 
 #[cfg(test)]
 mod plane_tests{
@@ -62,4 +71,29 @@ mod plane_tests{
         assert_eq!(trap.on_plane(&test_point), false)
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn placed_rectangle_plane() {
+        let rect = Rectangle::new(3., 3., placed_tfm()).unwrap();
+
+        assert!((rect.normal - Vec3::new(0., -1., 0.)).norm() < 1e-12);
+        assert!((rect.global_center - P3::new(1., 4., -2.)).norm() < 1e-12);
+        assert!((rect.plane_constant - 4.).abs() < 1e-12);
+
+        assert_eq!(rect.on_plane(&P3::new(7., 4., 3.)), true);
+        assert_eq!(rect.on_plane(&P3::new(1., 0., -2.)), false);
+    }
+
+    #[test]
+    fn placed_trapezoid_plane() {
+        let trap = Trapezoid::new(2., 5., placed_tfm(), 2.).unwrap();
+
+        // any local point lands on the plane once placed
+        let global = geometry::traits::Transform::to_global(&trap, P3::new(0.5, -0.3, 0.));
+        assert_eq!(trap.on_plane(&global), true);
+        assert_eq!(trap.on_plane(&(global + trap.normal)), false);
+
+        // the old check against the global origin
+        assert_eq!(trap.on_plane(&P3::new(1., 0., 1.)), false);
+    }
+
+}
@@ -0,0 +1,106 @@
+use kalman_rs::geometry::{Polygon, Trapezoid};
+use kalman_rs::geometry::traits::{Plane, Transform};
+use kalman_rs::geometry::utils;
+use kalman_rs::error::{Error, SensorError};
+use kalman_rs::config::*;
+
+use std::f64::consts::PI;
+
+
+fn hexagon(radius: Real) -> Vec<P2> {
+    (0..6).map(|i| {
+        let angle = i as Real * PI / 3.;
+        P2::new(radius * angle.cos(), radius * angle.sin())
+    })
+    .collect()
+}
+
+#[test]
+fn hexagon_bounds_and_edge_distance() {
+    let mut tfm = Mat4::identity();
+    tfm[(2, 3)] = 50.;
+    let sensor = Polygon::new(hexagon(10.), tfm).unwrap();
+
+    let apothem = 10. * (PI / 6.).cos();
+    assert!(sensor.inside(&P2::new(0., 0.)));
+    assert!(sensor.inside(&P2::new(0., apothem - 0.01)));
+    assert!(!sensor.inside(&P2::new(0., apothem + 0.01)));
+    assert!(!sensor.inside(&P2::new(-9.9, 1.)));
+
+    assert!((sensor.edge_distance(&P2::new(0., 0.)) - apothem).abs() < 1e-9);
+    assert!((sensor.edge_distance(&P2::new(0., apothem + 2.)) - 2.).abs() < 1e-9);
+    // nearest to a corner
+    assert!((sensor.edge_distance(&P2::new(13., 0.)) - 3.).abs() < 1e-9);
+
+    assert!(sensor.inside_global(P3::new(1., 1., 50.)));
+    assert!(sensor.on_plane(&P3::new(1., 1., 50.)));
+}
+
+#[test]
+fn clockwise_vertices_are_reordered() {
+    let mut clockwise = hexagon(10.);
+    clockwise.reverse();
+    assert!(utils::polygon_signed_area(&clockwise) < 0.);
+
+    let sensor = Polygon::new(clockwise, Mat4::identity()).unwrap();
+    assert!(utils::polygon_signed_area(sensor.vertices()) > 0.);
+    assert!(sensor.inside(&P2::new(1., 2.)));
+}
+
+#[test]
+fn rejects_non_convex_shapes() {
+    let not_convex = |result: Result<Polygon, Error>| {
+        match result {
+            Err(Error::Sensor(SensorError::NotConvex)) => true,
+            _ => false
+        }
+    };
+
+    // an arrow head
+    let arrow = vec![P2::new(0., 0.), P2::new(2., 1.), P2::new(0., 2.), P2::new(1., 1.)];
+    assert!(not_convex(Polygon::new(arrow, Mat4::identity())));
+
+    // a pentagram turns the same way at every corner but winds twice
+    let star = (0..5).map(|i| {
+        let angle = i as Real * 4. * PI / 5.;
+        P2::new(angle.cos(), angle.sin())
+    })
+    .collect();
+    assert!(not_convex(Polygon::new(star, Mat4::identity())));
+
+    assert!(not_convex(Polygon::new(vec![P2::new(0., 0.), P2::new(1., 0.)], Mat4::identity())));
+
+    // collinear vertices turn back on themselves at the ends, which looks like one full turn
+    let collinear = vec![P2::new(0., 0.), P2::new(1., 0.), P2::new(2., 0.)];
+    assert!(!utils::polygon_is_convex(&collinear));
+    assert!(not_convex(Polygon::new(collinear, Mat4::identity())));
+}
+
+#[test]
+fn matches_trapezoid() {
+    let trapezoid = Trapezoid::new(2., 5., Mat4::identity(), 2.).unwrap();
+    let polygon = Polygon::new(vec![P2::new(-2.5, -1.), P2::new(2.5, -1.), P2::new(1., 1.), P2::new(-1., 1.)],
+                               Mat4::identity()).unwrap();
+
+    for i in -30..30 {
+        for j in -15..15 {
+            let point = P2::new(i as Real * 0.1 + 0.05, j as Real * 0.1 + 0.05);
+            assert_eq!(trapezoid.inside(&point), polygon.inside(&point), "{}", point);
+        }
+    }
+}
+
+#[test]
+fn trapezoid_needs_positive_dimensions() {
+    let not_convex = |result: Result<Trapezoid, Error>| {
+        match result {
+            Err(Error::Sensor(SensorError::NotConvex)) => true,
+            _ => false
+        }
+    };
+
+    assert!(not_convex(Trapezoid::new(0., 5., Mat4::identity(), 2.)));
+    assert!(not_convex(Trapezoid::new(2., -5., Mat4::identity(), 2.)));
+    assert!(not_convex(Trapezoid::new(2., 5., Mat4::identity(), 0.)));
+    assert!(not_convex(Trapezoid::new(2., 5., Mat4::identity(), Real::NAN)));
+}
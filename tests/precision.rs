@@ -0,0 +1,164 @@
+use nalgebra as na;
+use kalman_rs::geometry::{Rectangle, Trapezoid};
+use kalman_rs::sensor_traits::{Plane, Transform};
+use kalman_rs::filter::{gaussian_sum, linear, prediction};
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::filter::workspace::FitWorkspace;
+use kalman_rs::config::*;
+
+
+// sensor rotated about the z axis and shifted along it
+fn transform<N: na::Real>(shift: N) -> na::Matrix4<N> {
+    let (sin, cos) = na::convert::<_, N>(0.3).sin_cos();
+    na::Matrix4::new(cos, -sin, na::zero(), na::zero(),
+                     sin, cos, na::zero(), na::zero(),
+                     na::zero(), na::zero(), N::one(), shift,
+                     na::zero(), na::zero(), na::zero(), N::one())
+}
+
+fn close(single: f32, double: f64, tolerance: f64) -> bool {
+    (single as f64 - double).abs() < tolerance
+}
+
+#[test]
+fn rectangle_precisions_agree() {
+    let single = Rectangle::<f32>::new(4., 6., transform(1.)).unwrap();
+    let double = Rectangle::<f64>::new(4., 6., transform(1.)).unwrap();
+
+    let global_single = single.to_global(na::Point3::new(1.5, -2., 0.));
+    let global_double = double.to_global(na::Point3::new(1.5, -2., 0.));
+    for i in 0..3 {
+        assert!(close(global_single[i], global_double[i], 1e-5));
+    }
+
+    let local_single = single.to_local(global_single);
+    let local_double = double.to_local(global_double);
+    assert!(close(local_single.x, local_double.x, 1e-5));
+    assert!(close(local_single.y, local_double.y, 1e-5));
+
+    for point in &[(1.9, 2.9), (2.1, 0.), (0., -3.1), (-1., -1.)] {
+        assert_eq!(single.inside(&na::Point2::new(point.0 as f32, point.1 as f32)),
+                   double.inside(&na::Point2::new(point.0, point.1)));
+    }
+    assert!(close(single.plane_normal_vec().norm(), double.plane_normal_vec().norm(), 1e-5));
+}
+
+#[test]
+fn trapezoid_precisions_agree() {
+    let single = Trapezoid::<f32>::new(4., 8., transform(0.), 10.).unwrap();
+    let double = Trapezoid::<f64>::new(4., 8., transform(0.), 10.).unwrap();
+
+    for point in &[(2.5, 4.), (3.5, -4.), (0., 5.1), (-3.9, -4.9), (-2.9, 0.)] {
+        assert_eq!(single.inside(&na::Point2::new(point.0 as f32, point.1 as f32)),
+                   double.inside(&na::Point2::new(point.0, point.1)));
+    }
+}
+
+#[test]
+fn extrapolation_precisions_agree() {
+    let start_single = Rectangle::<f32>::new(20., 20., transform(0.)).unwrap();
+    let end_single = Rectangle::<f32>::new(20., 20., transform(1.)).unwrap();
+    let start_double = Rectangle::<f64>::new(20., 20., transform(0.)).unwrap();
+    let end_double = Rectangle::<f64>::new(20., 20., transform(1.)).unwrap();
+
+    let state_double = Vec5::new(0.5, -0.5, 0.1, 0.3, 0.5);
+    let state_single : na::Vector5<f32> = na::convert(state_double);
+
+    let pred_single = prediction::linear_extrapolation(&start_single, &end_single, &state_single);
+    let pred_double = prediction::linear_extrapolation(&start_double, &end_double, &state_double);
+
+    for i in 0..5 {
+        assert!(close(pred_single[i], pred_double[i], 1e-4));
+    }
+}
+
+#[test]
+fn linear_fit_precisions_agree() {
+    let sensors_single : Vec<Rectangle<f32>> = (0..5).map(|_| Rectangle::new(20., 20., na::Matrix4::identity()).unwrap()).collect();
+    let sensors_double : Vec<Rectangle<f64>> = (0..5).map(|_| Rectangle::new(20., 20., na::Matrix4::identity()).unwrap()).collect();
+
+    let noise_double = vec![Mat2::identity() * 0.01; 5];
+    let measurements_double : Vec<Vec2> = (0..5).map(|i| Vec2::new(0.1 + 0.02 * i as Real, 0.01 * i as Real)).collect();
+    let noise_single : Vec<na::Matrix2<f32>> = noise_double.iter().map(|v| na::convert(*v)).collect();
+    let measurements_single : Vec<na::Vector2<f32>> = measurements_double.iter().map(|m| na::convert(*m)).collect();
+
+    let seed_x = Vec5::new(0., 0., 0.1, 0.3, 0.5);
+    let seed_c = Mat5::identity() * 0.1;
+
+    let mut single = FitWorkspace::<f32>::new();
+    linear::run_in(&mut single, &na::convert(seed_x), &na::convert(seed_c), &noise_single, &measurements_single, &sensors_single).unwrap();
+
+    let mut double = FitWorkspace::<f64>::new();
+    linear::run_in(&mut double, &seed_x, &seed_c, &noise_double, &measurements_double, &sensors_double).unwrap();
+
+    assert!(close(single.chi_squared(), double.chi_squared(), 1e-3));
+    for (x_single, x_double) in single.state_vec().iter().zip(double.state_vec().iter()) {
+        for i in 0..5 {
+            assert!(close(x_single[i], x_double[i], 1e-4));
+        }
+    }
+    for (c_single, c_double) in single.cov_mat().iter().zip(double.cov_mat().iter()) {
+        for i in 0..25 {
+            assert!(close(c_single[i], c_double[i], 1e-4));
+        }
+    }
+}
+
+#[test]
+fn kalman_filter_precisions_agree() {
+    let sensors_single : Vec<Rectangle<f32>> = (0..4).map(|i| Rectangle::new(20., 20., transform(i as f32)).unwrap()).collect();
+    let sensors_double : Vec<Rectangle<f64>> = (0..4).map(|i| Rectangle::new(20., 20., transform(i as f64)).unwrap()).collect();
+
+    let seed_x = Vec5::new(0., 0., 0.1, 0.3, 0.5);
+    let seed_c = Mat5::identity() * 0.1;
+
+    // hits close to the straight line of the seed
+    let noise_double = Mat2::identity() * 0.01;
+    let measurements_double : Vec<Vec2> = (0..4).map(|i| {
+        let state = prediction::linear_extrapolation(&sensors_double[0], &sensors_double[i], &seed_x);
+        Vec2::new(state[0] + 0.02 * i as Real, state[1] - 0.01 * i as Real)
+    }).collect();
+
+    let mut single = KalmanFilter::<Rectangle<f32>, f32>::new(na::convert(seed_x), na::convert(seed_c));
+    let mut double = KalmanFilter::<Rectangle<f64>, f64>::new(seed_x, seed_c);
+    for i in 0..4 {
+        single.predict(&sensors_single[i]).unwrap();
+        single.update(&na::convert(measurements_double[i]), &na::convert(noise_double)).unwrap();
+        double.predict(&sensors_double[i]).unwrap();
+        double.update(&measurements_double[i], &noise_double).unwrap();
+    }
+
+    assert!(close(single.chi_squared(), double.chi_squared(), 1e-3));
+    let smoothed_single = single.smooth().unwrap();
+    let smoothed_double = double.smooth().unwrap();
+    for (x_single, x_double) in smoothed_single.state_vec().iter().zip(smoothed_double.state_vec().iter()) {
+        for i in 0..5 {
+            assert!(close(x_single[i], x_double[i], 1e-4));
+        }
+    }
+}
+
+#[test]
+fn gaussian_sum_precisions_agree() {
+    let sensors_single : Vec<Rectangle<f32>> = (0..4).map(|_| Rectangle::new(20., 20., na::Matrix4::identity()).unwrap()).collect();
+    let sensors_double : Vec<Rectangle<f64>> = (0..4).map(|_| Rectangle::new(20., 20., na::Matrix4::identity()).unwrap()).collect();
+
+    let noise_double = vec![Mat2::identity() * 0.01; 4];
+    let measurements_double : Vec<Vec2> = (0..4).map(|i| Vec2::new(0.1 + 0.02 * i as Real, 0.01 * i as Real)).collect();
+    let noise_single : Vec<na::Matrix2<f32>> = noise_double.iter().map(|v| na::convert(*v)).collect();
+    let measurements_single : Vec<na::Vector2<f32>> = measurements_double.iter().map(|m| na::convert(*m)).collect();
+
+    let seed_x = Vec5::new(0., 0., 0.1, 0.3, 0.5);
+    let seed_c = Mat5::identity() * 0.1;
+    let mixture_double = gaussian_sum::BetheHeitlerMixture::new(vec![(0.7, 0.95, 0.001), (0.3, 0.8, 0.01)]);
+    let mixture_single = gaussian_sum::BetheHeitlerMixture::new(vec![(0.7, 0.95, 0.001), (0.3, 0.8, 0.01)]);
+
+    let single = gaussian_sum::run(&na::convert(seed_x), &na::convert(seed_c), &noise_single, &measurements_single, &sensors_single, &mixture_single, 4).unwrap();
+    let double = gaussian_sum::run(&seed_x, &seed_c, &noise_double, &measurements_double, &sensors_double, &mixture_double, 4).unwrap();
+
+    for (x_single, x_double) in single.mean_state_vec.iter().zip(double.mean_state_vec.iter()) {
+        for i in 0..5 {
+            assert!(close(x_single[i], x_double[i], 1e-3));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use kalman_rs::geometry::Straw;
+use kalman_rs::geometry::traits::Surface;
+use kalman_rs::filter::drift::{self, DriftMeasurement, Side};
+use kalman_rs::filter::parameters;
+use kalman_rs::config::*;
+
+use std::f64::consts::FRAC_PI_2;
+
+
+// straw with its wire along global y through (x, 0, z)
+fn straw_at(x: Real, z: Real) -> Straw {
+    let mut tfm = Rot3::from_euler_angles(-FRAC_PI_2, 0., 0.).to_homogeneous();
+    tfm[(0, 3)] = x;
+    tfm[(2, 3)] = z;
+    Straw::new(2.5, 1000., tfm).unwrap()
+}
+
+#[test]
+fn signed_radius_follows_side_of_wire() {
+    let straw = straw_at(0., 100.);
+    let direction = Vec3::z();
+
+    // wire along y and the track along z, so positive radii lie along y x z = +x
+    let right = P3::new(1., 3., 0.);
+    let left = P3::new(-1., 3., 0.);
+
+    for (start, expected) in [(right, 1.), (left, -1.)].iter() {
+        let path_length = straw.intersect(start, &direction).unwrap().path_length;
+        assert!((path_length - 100.).abs() < 1e-9);
+
+        let closest = start + direction * path_length;
+        let local = straw.to_local_directed(closest, &direction);
+        assert!((local.x - expected).abs() < 1e-9);
+        assert!((local.y - 3.).abs() < 1e-9);
+
+        let back = straw.to_global_directed(P3::new(local.x, local.y, 0.), &direction);
+        assert!((back - closest).norm() < 1e-9);
+    }
+
+    // a track along the wire never reaches the point of closest approach
+    assert!(straw.intersect(&P3::new(1., 0., 100.), &Vec3::y()).is_none());
+}
+
+#[test]
+fn update_uses_given_side() {
+    let measurement = DriftMeasurement{radius: 1., variance: 1e-4};
+    let pred_state_vec = Vec5::new(0.8, 0., 0., 1., 0.5);
+    let pred_cov_mat = Mat5::identity() * 0.01;
+
+    assert_eq!(drift::resolve_side(&pred_state_vec, &measurement), Side::Right);
+
+    let (right, _, right_chi_squared) = drift::update(&pred_state_vec, &pred_cov_mat, &measurement, Side::Right).unwrap();
+    let (left, _, left_chi_squared) = drift::update(&pred_state_vec, &pred_cov_mat, &measurement, Side::Left).unwrap();
+
+    assert!((right[0] - 1.).abs() < 0.01);
+    assert!((left[0] + 1.).abs() < 0.03);
+    assert!(right_chi_squared < left_chi_squared);
+}
+
+#[test]
+fn fit_resolves_left_right_ambiguity() {
+    let (theta, phi) = (0.2, 1.3);
+    let direction = parameters::direction(theta, phi);
+    let start = P3::new(0., 5., 0.);
+
+    // straws following the track, offset to alternating sides
+    let straws : Vec<Straw> = (1..9).map(|i| {
+        let z = 20. * i as Real;
+        let offset = if i % 2 == 0 {1.5} else {-1.2};
+        straw_at(z * direction.x / direction.z + offset, z)
+    })
+    .collect();
+
+    let truth : Vec<Vec5> = straws.iter().map(|straw| {
+        let closest = straw.intersect(&start, &direction).unwrap().point;
+        let local = straw.to_local_directed(closest, &direction);
+        Vec5::new(local.x, local.y, theta, phi, 0.5)
+    })
+    .collect();
+
+    let measurements = truth.iter().map(|state| DriftMeasurement{radius: state[0].abs(), variance: 1e-4}).collect();
+
+    let seed_state_vec = truth[0] + Vec5::new(0.2, 0.5, 0.005, -0.005, 0.);
+    let seed_cov_mat = Mat5::from_diagonal(&Vec5::new(0.25, 1., 1e-4, 1e-4, 0.01));
+
+    let fit = drift::run(&seed_state_vec, &seed_cov_mat, &measurements, &straws).unwrap();
+
+    for (i, state) in truth.iter().enumerate() {
+        let expected = if state[0] > 0. {Side::Right} else {Side::Left};
+        assert_eq!(fit.sides[i], expected);
+        assert!((fit.filt_state_vec[i][0] - state[0]).abs() < 0.05);
+    }
+}
@@ -0,0 +1,77 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::timing::{self, Measurement};
+use kalman_rs::config::*;
+
+
+// sensors parallel to the x-y plane spaced `spacing` apart along z
+fn initialize_sensors(num: usize, spacing: Real) -> Vec<Rectangle> {
+    (0..num).map(|i| {
+        let mut tfm = Mat4::identity();
+        tfm[(2, 3)] = i as Real * spacing;
+        Rectangle::new(2000., 2000., tfm).unwrap()
+    })
+    .collect()
+}
+
+fn pion_mass() -> Real {
+    ParticleHypothesis::Pion.mass()
+}
+
+#[test]
+fn time_advances_with_path_length() {
+    let sensors = initialize_sensors(2, 100.);
+    let state_vec = Vec6::new(1., 2., 0.2, 1.0, 0.5, 3.);
+
+    let pred = timing::time_extrapolation(&sensors[0], &sensors[1], &state_vec, pion_mass());
+
+    let path_length = 100. / (1.0 as Real).sin();
+    let momentum : Real = 2.;
+    let beta = momentum / (momentum * momentum + pion_mass() * pion_mass()).sqrt();
+
+    assert!((pred[5] - (3. + path_length / (beta * SPEED_OF_LIGHT))).abs() < 1e-9);
+    assert!((pred[0] - (1. + path_length * (1.0 as Real).cos() * (0.2 as Real).cos())).abs() < 1e-9);
+    assert!((pred[1] - (2. + path_length * (1.0 as Real).cos() * (0.2 as Real).sin())).abs() < 1e-9);
+}
+
+#[test]
+fn space_and_time_fit_together() {
+    let sensors = initialize_sensors(6, 100.);
+    let truth_start = Vec6::new(1., 2., 0.2, 1.0, 0.5, 3.);
+
+    let mut truth = vec![truth_start];
+    for i in 1..sensors.len() {
+        let next = timing::time_extrapolation(&sensors[i - 1], &sensors[i], &truth[i - 1], pion_mass());
+        truth.push(next);
+    }
+
+    // alternate between sensors measuring position, time and both
+    let measurements : Vec<Measurement> = truth.iter().enumerate().map(|(i, state)| {
+        match i % 3 {
+            0 => Measurement::Space{position: Vec2::new(state[0], state[1]), noise: Mat2::identity() * 1e-4},
+            1 => Measurement::Time{time: state[5], variance: 1e-4},
+            _ => Measurement::SpaceTime{position: Vec2::new(state[0], state[1]), time: state[5], noise: Mat3::identity() * 1e-4}
+        }
+    })
+    .collect();
+
+    let seed_state_vec = truth_start + Vec6::new(0.3, -0.3, 0.01, -0.01, 0., 0.5);
+    let seed_cov_mat = Mat6::from_diagonal(&Vec6::new(1., 1., 0.01, 0.01, 0.01, 1.));
+
+    let fit = timing::run(&seed_state_vec, &seed_cov_mat, &measurements, &sensors, pion_mass()).unwrap();
+
+    assert_eq!(fit.smth_state_vec.len(), 6);
+    for (smoothed, true_state) in fit.smth_state_vec.iter().zip(truth.iter()) {
+        assert!((smoothed[0] - true_state[0]).abs() < 0.05);
+        assert!((smoothed[1] - true_state[1]).abs() < 0.05);
+        assert!((smoothed[5] - true_state[5]).abs() < 0.05);
+    }
+}
+
+#[test]
+fn empty_track() {
+    let sensors : Vec<Rectangle> = Vec::new();
+
+    let fit = timing::run(&Vec6::zeros(), &(Mat6::identity() * 0.1), &vec![], &sensors, pion_mass()).unwrap();
+    assert!(fit.smth_state_vec.is_empty());
+    assert_eq!(fit.chi_squared, 0.);
+}
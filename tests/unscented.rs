@@ -0,0 +1,86 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::unscented::{self, SigmaPoints};
+use kalman_rs::filter::{filter_gain, utils};
+use kalman_rs::config::*;
+
+
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+fn inputs() -> (Vec<Mat2>, Vec<Vec2>) {
+    let noise = vec![Mat2::identity() * 0.01; 3];
+    let measurements = vec![Vec2::new(0.1, 0.0), Vec2::new(0.12, 0.02), Vec2::new(0.15, 0.01)];
+    (noise, measurements)
+}
+
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0.1, 0.3, 0.5), Mat5::identity() * 0.1)
+}
+
+// sensors on the same plane do not move the state, so the result must match a plain kalman filter
+fn check_against_kalman(sigma_points: SigmaPoints) {
+    let sensors = initialize_sensors(3);
+    let (noise, measurements) = inputs();
+    let (seed_x, seed_c) = seed();
+
+    let smoothed = unscented::run(&seed_x, &seed_c, &noise, &measurements, &sensors, &sigma_points).unwrap();
+
+    let meas_map_mat = utils::sensor_mapping_mat();
+    let mut x = seed_x;
+    let mut c = seed_c;
+    for (v, m) in noise.iter().zip(measurements.iter()) {
+        let gain = filter_gain::kalman_gain(&c, &meas_map_mat, v);
+        x = filter_gain::state_vector(&x, &gain, m, &meas_map_mat);
+        c = filter_gain::covariance_matrix(&gain, &meas_map_mat, &c);
+    }
+
+    assert_eq!(smoothed.state_vec().len(), 3);
+    for state_vec in smoothed.state_vec().iter() {
+        assert!((state_vec - x).norm() < 1e-9);
+    }
+    assert!((smoothed.cov_mat()[2] - c).norm() < 1e-9);
+}
+
+#[test]
+fn julier_matches_kalman() {
+    check_against_kalman(SigmaPoints::Julier{kappa: -2.});
+}
+
+#[test]
+fn merwe_matches_kalman() {
+    check_against_kalman(SigmaPoints::MerweScaled{alpha: 0.5, beta: 2., kappa: 0.});
+}
+
+#[test]
+fn sigma_points_recover_moments() {
+    let (x, c) = seed();
+    let scheme = SigmaPoints::MerweScaled{alpha: 0.3, beta: 2., kappa: 0.};
+
+    let (points, mean_weights, cov_weights) = scheme.generate(&x, &c).unwrap();
+    assert_eq!(points.len(), 11);
+
+    let mean = points.iter().zip(mean_weights.iter()).fold(Vec5::zeros(), |acc, (p, w)| acc + p * *w);
+    let cov = points.iter().zip(cov_weights.iter()).fold(Mat5::zeros(), |acc, (p, w)| acc + (p - mean) * (p - mean).transpose() * *w);
+
+    assert!((mean - x).norm() < 1e-9);
+    assert!((cov - c).norm() < 1e-9);
+}
+
+#[test]
+fn rejects_indefinite_covariance() {
+    let (x, _) = seed();
+    let c = -Mat5::identity();
+
+    assert!(SigmaPoints::Julier{kappa: -2.}.generate(&x, &c).is_err());
+}
+
+#[test]
+fn empty_track() {
+    let (seed_x, seed_c) = seed();
+    let sensors : Vec<Rectangle> = Vec::new();
+
+    let smoothed = unscented::run(&seed_x, &seed_c, &vec![], &vec![], &sensors, &SigmaPoints::Julier{kappa: 2.}).unwrap();
+    assert!(smoothed.state_vec().is_empty());
+}
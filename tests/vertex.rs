@@ -0,0 +1,81 @@
+use kalman_rs::geometry::PerigeeSurface;
+use kalman_rs::filter::perigee::{PerigeeParameters, PerigeePropagation};
+use kalman_rs::vertex::{kalman::{self, VertexFitOptions}, linearize};
+use kalman_rs::config::*;
+
+
+// (phi, theta, q/p) of tracks spread around the detector
+fn momenta() -> Vec<Vec3> {
+    vec![Vec3::new(0.3, 1.2, 0.5),
+         Vec3::new(1.9, 0.8, -0.3),
+         Vec3::new(-2.5, 1.6, 0.8),
+         Vec3::new(-0.9, 2.1, -1.),
+         Vec3::new(2.8, 1.0, 0.2)]
+}
+
+fn track_cov_mat() -> Mat5 {
+    Mat5::from_diagonal(&Vec5::new(1e-4, 1e-4, 1e-6, 1e-6, 1e-6))
+}
+
+fn tracks_from(vertex: &P3, options: &VertexFitOptions) -> Vec<PerigeeParameters> {
+    momenta().iter().map(|momentum| {
+        PerigeeParameters{state_vec: linearize::vertex_state_vec(vertex, momentum, &options.perigee, &options.propagation),
+                          cov_mat: track_cov_mat()}
+    })
+    .collect()
+}
+
+fn fit_recovers_vertex(propagation: PerigeePropagation) {
+    let options = VertexFitOptions::new(PerigeeSurface::origin(), propagation);
+    let truth = P3::new(0.2, -0.1, 5.);
+    let tracks = tracks_from(&truth, &options);
+
+    let vertex = kalman::fit(&tracks, &options).unwrap();
+
+    assert!((vertex.position - truth).norm() < 1e-6, "found {} expected {}", vertex.position, truth);
+    assert!(vertex.cov_mat[(0, 0)] > 0. && vertex.cov_mat[(0, 0)] < 1e-3);
+    assert!((vertex.ndf - 7.).abs() < 1e-12);
+    // only the wide prior around the beam spot contributes
+    assert!(vertex.chi_squared < 1e-3);
+
+    for (track, momentum) in vertex.tracks.iter().zip(momenta().iter()) {
+        assert!((track.momentum - momentum).norm() < 1e-6);
+        assert!(track.momentum_cov_mat[(2, 2)] > 0.);
+        assert!(track.chi_squared < 1e-6);
+    }
+}
+
+#[test]
+fn straight_tracks_recover_vertex() {
+    fit_recovers_vertex(PerigeePropagation::StraightLine);
+}
+
+#[test]
+fn helix_tracks_recover_vertex() {
+    fit_recovers_vertex(PerigeePropagation::Helix{b_field: 2.});
+}
+
+#[test]
+fn outlier_has_largest_chi_squared() {
+    let options = VertexFitOptions::new(PerigeeSurface::origin(), PerigeePropagation::Helix{b_field: 2.});
+    let mut tracks = tracks_from(&P3::new(0., 0., -3.), &options);
+
+    // move one track 0.5 mm along the beam line, fifty standard deviations
+    tracks[2].state_vec[1] += 0.5;
+
+    let vertex = kalman::fit(&tracks, &options).unwrap();
+
+    let outlier = vertex.tracks[2].chi_squared;
+    for (i, track) in vertex.tracks.iter().enumerate() {
+        if i != 2 {
+            assert!(track.chi_squared < outlier);
+        }
+    }
+    assert!(outlier > 100.);
+
+    // without its weight the outlier no longer pulls the vertex
+    let weights = vec![1., 1., 0., 1., 1.];
+    let vertex = kalman::fit_weighted(&tracks, &weights, &options).unwrap();
+    assert!((vertex.position - P3::new(0., 0., -3.)).norm() < 1e-6);
+    assert!(vertex.tracks[2].chi_squared > 100.);
+}
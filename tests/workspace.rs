@@ -0,0 +1,108 @@
+use kalman_rs::geometry::Rectangle;
+use kalman_rs::filter::{linear, prediction};
+use kalman_rs::filter::kalman::KalmanFilter;
+use kalman_rs::filter::workspace::FitWorkspace;
+use kalman_rs::config::*;
+
+
+fn initialize_sensors(num: usize) -> Vec<Rectangle> {
+    (0..num).map(|_| Rectangle::new(20 as Real, 20 as Real, Mat4::identity()).unwrap())
+        .collect()
+}
+
+fn inputs(num: usize) -> (Vec<Mat2>, Vec<Vec2>) {
+    let noise = vec![Mat2::identity() * 0.01; num];
+    let measurements = (0..num).map(|i| Vec2::new(0.1 + 0.02 * i as Real, 0.01 * i as Real)).collect();
+    (noise, measurements)
+}
+
+fn seed() -> (Vec5, Mat5) {
+    (Vec5::new(0., 0., 0.1, 0.3, 0.5), Mat5::identity() * 0.1)
+}
+
+#[test]
+fn matches_kalman_filter() {
+    let sensors = initialize_sensors(4);
+    let (noise, measurements) = inputs(4);
+    let (seed_x, seed_c) = seed();
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &seed_x, &seed_c, &noise, &measurements, &sensors).unwrap();
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    for i in 0..sensors.len() {
+        kf.predict(&sensors[i]).unwrap();
+        kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+    let smoothed = kf.smooth().unwrap();
+
+    assert_eq!(workspace.len(), 4);
+    assert!((workspace.chi_squared() - kf.chi_squared()).abs() < 1e-10);
+    for i in 0..4 {
+        assert!((workspace.state_vec()[i] - smoothed.state_vec()[i]).norm() < 1e-10);
+        assert!((workspace.cov_mat()[i] - smoothed.cov_mat()[i]).norm() < 1e-10);
+        assert!((workspace.res_vec()[i] - smoothed.res_vec()[i]).norm() < 1e-10);
+    }
+}
+
+#[test]
+fn matches_kalman_filter_on_tilted_sensors() {
+    // sensors 10 apart along z, each tilted a little further about x
+    let sensors : Vec<Rectangle> = (0..4).map(|i| {
+            let mut tfm = Rot3::from_axis_angle(&Vec3::x_axis(), 0.1 * i as Real).to_homogeneous();
+            tfm[(2, 3)] = 10. * i as Real;
+            Rectangle::new(100 as Real, 100 as Real, tfm).unwrap()
+        })
+        .collect();
+    let seed_x = Vec5::new(0., 0., 0.3, 1.2, 0.5);
+    let seed_c = Mat5::identity() * 0.1;
+
+    // hits scattered around the straight line of the seed
+    let noise = vec![Mat2::identity() * 0.01; 4];
+    let measurements : Vec<Vec2> = sensors.iter().enumerate().map(|(i, sensor)| {
+            let hit = prediction::linear_extrapolation(&sensors[0], sensor, &seed_x);
+            Vec2::new(hit[0] + 0.02 * i as Real, hit[1] - 0.01 * i as Real)
+        })
+        .collect();
+
+    let mut workspace = FitWorkspace::new();
+    linear::run_in(&mut workspace, &seed_x, &seed_c, &noise, &measurements, &sensors).unwrap();
+
+    let mut kf = KalmanFilter::new(seed_x, seed_c);
+    for i in 0..sensors.len() {
+        kf.predict(&sensors[i]).unwrap();
+        kf.update(&measurements[i], &noise[i]).unwrap();
+    }
+    let smoothed = kf.smooth().unwrap();
+
+    // the covariance is carried between the sensors with the jacobian of the extrapolation
+    assert!((workspace.chi_squared() - kf.chi_squared()).abs() < 1e-8);
+    for i in 0..4 {
+        assert!((workspace.filt_cov_mat()[i] - kf.steps()[i].filt_cov_mat).norm() < 1e-8);
+        assert!((workspace.state_vec()[i] - smoothed.state_vec()[i]).norm() < 1e-8);
+        assert!((workspace.cov_mat()[i] - smoothed.cov_mat()[i]).norm() < 1e-8);
+    }
+}
+
+#[test]
+fn reused_workspace_matches_fresh() {
+    let (seed_x, seed_c) = seed();
+    let mut reused = FitWorkspace::with_capacity(8);
+
+    // fit a long track first so the buffers hold stale values from it
+    let (noise, measurements) = inputs(8);
+    linear::run_in(&mut reused, &seed_x, &seed_c, &noise, &measurements, &initialize_sensors(8)).unwrap();
+
+    let (noise, measurements) = inputs(3);
+    let sensors = initialize_sensors(3);
+    linear::run_in(&mut reused, &seed_x, &seed_c, &noise, &measurements, &sensors).unwrap();
+
+    let mut fresh = FitWorkspace::new();
+    linear::run_in(&mut fresh, &seed_x, &seed_c, &noise, &measurements, &sensors).unwrap();
+
+    assert_eq!(reused.len(), 3);
+    assert_eq!(reused.state_vec(), fresh.state_vec());
+    assert_eq!(reused.cov_mat(), fresh.cov_mat());
+    assert_eq!(reused.res_mat(), fresh.res_mat());
+    assert_eq!(reused.chi_squared(), fresh.chi_squared());
+}